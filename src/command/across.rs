@@ -0,0 +1,379 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{Arg, Command as ClapCommand};
+use comfy_table::{Cell, CellAlignment, Color, Table};
+use regex::Regex;
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command_processor::CommandProcessor,
+    completer,
+    env::{Env, FanoutEnv},
+    error::ClickError,
+    kobj::KObj,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::thread;
+
+// commands that only read and print, and so can run on many contexts at once. anything else is
+// interactive, never finishes (like logs -f), or changes things
+const ALLOWED_COMMANDS: &[&str] = &[
+    "can-i",
+    "configmaps",
+    "containers",
+    "crd",
+    "cronjobs",
+    "daemonsets",
+    "deployments",
+    "describe",
+    "events",
+    "find",
+    "jobs",
+    "namespaces",
+    "nodes",
+    "persistentvolumes",
+    "pods",
+    "replicasets",
+    "rollouts",
+    "secrets",
+    "services",
+    "statefulsets",
+    "storageclasses",
+    "whoami",
+];
+
+/// What running a command against one context produced
+struct FanoutResult {
+    context: crate::k8s::Context,
+    output: Vec<u8>,
+    error: Option<String>,
+    listed: Option<(Vec<KObj>, Table)>,
+}
+
+/// Turn a glob with * and ? wildcards into a regex that matches the whole string
+fn glob_to_regex(glob: &str) -> Result<Regex, ClickError> {
+    let re = regex::escape(glob).replace("\\*", ".*").replace("\\?", ".");
+    Regex::new(&format!("^{re}$"))
+        .map_err(|e| ClickError::CommandError(format!("Invalid context pattern {glob}: {e}")))
+}
+
+/// Get the names of all contexts that match any of the comma separated globs, in sorted order
+fn matching_contexts<'a>(
+    globs: &str,
+    contexts: impl Iterator<Item = &'a String>,
+) -> Result<Vec<String>, ClickError> {
+    let regexes = globs
+        .split(',')
+        .map(|glob| glob_to_regex(glob.trim()))
+        .collect::<Result<Vec<Regex>, ClickError>>()?;
+    Ok(contexts
+        .filter(|context| regexes.iter().any(|re| re.is_match(context)))
+        .cloned()
+        .collect())
+}
+
+/// Make sure cmd is a command that can be run with across
+fn check_allowed(cmd: &str) -> Result<(), ClickError> {
+    match CommandProcessor::get_command_vec()
+        .iter()
+        .find(|c| c.is(cmd))
+        .map(|c| c.get_name())
+    {
+        Some(name) if ALLOWED_COMMANDS.contains(&name) => Ok(()),
+        Some(name) => Err(ClickError::CommandError(format!(
+            "{name} can't be run with across, only commands that list or describe objects can"
+        ))),
+        None => Err(ClickError::CommandError(format!("Unknown command {cmd}"))),
+    }
+}
+
+fn run_on_fanout(fanout: FanoutEnv, args: &[String]) -> FanoutResult {
+    let mut env = fanout.into_env();
+    let mut writer = ClickWriter::with_buffer(vec![], false);
+    let commands = CommandProcessor::get_command_vec();
+    let mut parts = args.iter().map(|s| s.as_str());
+    let cmdstr = parts.next().unwrap_or_default();
+    let error = match commands.iter().find(|c| c.is(cmdstr)) {
+        Some(cmd) => cmd
+            .exec(&mut env, &mut parts, &mut writer)
+            .err()
+            .map(|e| e.to_string()),
+        None => Some(format!("Unknown command {cmdstr}")),
+    };
+    let listed = match env.take_last_objs() {
+        (Some(objs), Some(table)) => Some((objs, table)),
+        _ => None,
+    };
+    FanoutResult {
+        context: env.context.take().unwrap(), // safe, fanout envs always have a context
+        output: writer.finish_output().unwrap_or_default(),
+        error,
+        listed,
+    }
+}
+
+fn header_titles(table: &Table) -> Vec<String> {
+    table
+        .header()
+        .map(|header| header.cell_iter().map(|cell| cell.content()).collect())
+        .unwrap_or_default()
+}
+
+/// Merge tables listed from several contexts into one, with a Context column after the index
+/// column. The index column is renumbered, and each object remembers which context it's from.
+fn merge_tables(
+    listed: Vec<(String, Vec<KObj>, Table)>,
+    context_color: Option<Color>,
+) -> (Vec<KObj>, Table) {
+    let mut merged = Table::new();
+    if let Some(header) = listed.first().and_then(|(_, _, table)| table.header()) {
+        let mut cells = header.cell_iter().cloned();
+        let mut titles: Vec<Cell> = cells.next().into_iter().collect();
+        titles.push(Cell::new("Context"));
+        titles.extend(cells);
+        merged.set_header(titles);
+    }
+    let mut kobjs = vec![];
+    for (context, objs, table) in listed.into_iter() {
+        for (obj, row) in objs.into_iter().zip(table.row_iter()) {
+            let context_cell = match context_color {
+                Some(color) => Cell::new(&context).fg(color),
+                None => Cell::new(&context),
+            };
+            let mut cells = vec![
+                Cell::new(kobjs.len()).set_alignment(CellAlignment::Right),
+                context_cell,
+            ];
+            cells.extend(row.cell_iter().skip(1).cloned());
+            merged.add_row(cells);
+            kobjs.push(KObj {
+                context: Some(context.clone()),
+                ..obj
+            });
+        }
+    }
+    (kobjs, merged)
+}
+
+/// Run the command in args against each of contexts in parallel, and print the results. Output from
+/// list commands is merged into one table, which becomes the last list in env.
+pub fn run_across(
+    env: &mut Env,
+    contexts: &[String],
+    args: &[String],
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let mut handles = vec![];
+    for context in contexts.iter() {
+        match env.fanout(context) {
+            Ok(fanout) => {
+                let args = args.to_vec();
+                handles.push(thread::spawn(move || run_on_fanout(fanout, &args)));
+            }
+            Err(e) => clickwriteln!(writer, "[{}] Couldn't load context: {}", context, e),
+        }
+    }
+
+    let mut header = None;
+    let mut listed = vec![];
+    let mut unmerged = vec![];
+    for handle in handles.into_iter() {
+        let result = handle
+            .join()
+            .map_err(|_| ClickError::CommandError("Command panicked".to_string()))?;
+        let name = result.context.name.clone();
+        if let Some(e) = result.error {
+            clickwriteln!(writer, "[{}] {}", name, e);
+        }
+        match result.listed {
+            Some((objs, table))
+                if header.get_or_insert_with(|| header_titles(&table))
+                    == &header_titles(&table) =>
+            {
+                listed.push((name, objs, table))
+            }
+            _ => unmerged.push((name, result.output)),
+        }
+        env.add_fanout_context(result.context);
+    }
+
+    for (name, output) in unmerged.into_iter() {
+        if !output.is_empty() {
+            clickwriteln!(writer, "--- {} ---", env.styles.prompt_context(&name));
+            writer.write_all(&output)?;
+        }
+    }
+    if !listed.is_empty() {
+        let (kobjs, mut table) = merge_tables(listed, env.styles.context_table_color());
+        crate::table::print_filled_table(&mut table, writer);
+        env.set_last_objs(kobjs, Some(table));
+    }
+    Ok(())
+}
+
+command!(
+    Across,
+    "across",
+    "Run a command against several contexts at once. List output is merged into one table",
+    |clap: ClapCommand<'static>| clap
+        .arg(
+            Arg::new("contexts")
+                .help(
+                    "Comma separated list of contexts to run against. Each can be a glob, so \
+                     prod-* runs against every context starting with prod-"
+                )
+                .required(true)
+                .index(1)
+        )
+        .arg(
+            Arg::new("command")
+                .help("The command to run")
+                .required(true)
+                .multiple_values(true) // required for trailing_var_arg
+                .index(2)
+        )
+        .after_help(
+            "The command runs against each context in parallel, using the current namespace and \
+             selection. Output from list commands (pods, deployments, ...) is merged into one \
+             table with a Context column, and objects selected from that table remember their \
+             context, so commands like logs or describe go to the right cluster. Only commands \
+             that list or describe objects can be run this way.
+
+Examples:
+  # List pods in all production contexts
+  across prod-* pods
+
+  # Describe the currently selected deployment in two contexts
+  across staging,prod-us describe"
+        ),
+    vec!["across"],
+    vec![&completer::context_complete],
+    no_named_complete!(),
+    |matches, env, writer| {
+        let globs = matches.get_one::<String>("contexts").unwrap(); // safe, required
+        let args: Vec<String> = matches
+            .get_many::<String>("command")
+            .unwrap() // safe, required
+            .cloned()
+            .collect();
+        check_allowed(&args[0])?;
+        let contexts = matching_contexts(globs, env.get_contexts().keys())?;
+        if contexts.is_empty() {
+            return Err(ClickError::CommandError(format!(
+                "No contexts match {globs}"
+            )));
+        }
+
+        run_across(env, &contexts, &args, writer)
+    },
+    true // gather up all trailing args as the command to run
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kobj::ObjType;
+
+    #[test]
+    fn test_matching_contexts() {
+        let contexts: Vec<String> = ["dev", "prod-eu", "prod-us", "staging"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            matching_contexts("prod-*", contexts.iter()).unwrap(),
+            vec!["prod-eu", "prod-us"]
+        );
+        assert_eq!(
+            matching_contexts("dev, prod-?s", contexts.iter()).unwrap(),
+            vec!["dev", "prod-us"]
+        );
+        assert_eq!(matching_contexts("*", contexts.iter()).unwrap(), contexts);
+        assert!(matching_contexts("prod", contexts.iter())
+            .unwrap()
+            .is_empty());
+        assert!(matching_contexts("d.v", contexts.iter())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_check_allowed() {
+        assert!(check_allowed("pods").is_ok());
+        assert!(check_allowed("describe").is_ok());
+        for cmd in [
+            "logs",
+            "pick",
+            "ui",
+            "config",
+            "export-kubeconfig",
+            "set",
+            "delete",
+        ] {
+            match check_allowed(cmd) {
+                Err(ClickError::CommandError(msg)) => {
+                    assert!(msg.contains("can't be run with across"), "{msg}")
+                }
+                other => panic!("expected {cmd} to be rejected, got {other:?}"),
+            }
+        }
+        assert!(check_allowed("nope").is_err());
+    }
+
+    fn make_listed(context: &str, names: &[&str]) -> (String, Vec<KObj>, Table) {
+        let mut table = Table::new();
+        table.set_header(vec!["####", "Name"]);
+        let mut objs = vec![];
+        for (i, name) in names.iter().enumerate() {
+            table.add_row(vec![Cell::new(i), Cell::new(name)]);
+            objs.push(KObj {
+                name: name.to_string(),
+                namespace: None,
+                typ: ObjType::Node,
+                context: None,
+            });
+        }
+        (context.to_string(), objs, table)
+    }
+
+    #[test]
+    fn test_merge_tables() {
+        let (kobjs, table) = merge_tables(
+            vec![make_listed("c1", &["a", "b"]), make_listed("c2", &["c"])],
+            Some(Color::Red),
+        );
+        assert_eq!(header_titles(&table), vec!["####", "Context", "Name"]);
+        let rows: Vec<Vec<String>> = table
+            .row_iter()
+            .map(|row| row.cell_iter().map(|cell| cell.content()).collect())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["0", "c1", "a"],
+                vec!["1", "c1", "b"],
+                vec!["2", "c2", "c"]
+            ]
+        );
+        assert_eq!(kobjs.len(), 3);
+        assert_eq!(kobjs[2].name, "c");
+        assert_eq!(kobjs[2].context.as_deref(), Some("c2"));
+    }
+}
@@ -79,7 +79,24 @@ Examples:
   alias pn \"pods -r nginx\"
 
   # alias el to run logs and grep for ERROR
-  alias el \"logs | grep ERROR\""
+  alias el \"logs | grep ERROR\"
+
+Aliases can also take arguments, like a bash function. If the expansion refers to any positional
+parameters, the arguments typed after the alias are substituted into it (rather than being appended
+to the end):
+  $1..$9         the argument at that position (or nothing if it wasn't given)
+  $@             all the arguments
+  ${1:-default}  the argument, or 'default' if it wasn't given
+Parameters inside single quotes are not substituted, so use single quotes when defining the alias
+to keep a literal $. An expansion can also contain several commands separated by ';', which are run
+in order.
+
+More examples:
+  # lg to show the last 100 lines of logs for a container (default 'app')
+  alias lg \"logs -c ${1:-app} -t 100\"
+
+  # pe to select pod number $1 and show its events
+  alias pe \"$1; events\""
         ),
     vec!["alias", "aliases"],
     noop_complete!(),
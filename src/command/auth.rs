@@ -0,0 +1,691 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use base64::engine::{general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{TimeZone, Utc};
+use clap::{Arg, Command as ClapCommand};
+use k8s_openapi::api::authorization::v1::{
+    NonResourceAttributes, ResourceAttributes, ResourceRule, SelfSubjectAccessReview,
+    SelfSubjectAccessReviewSpec, SelfSubjectRulesReview, SelfSubjectRulesReviewSpec,
+    SubjectRulesReviewStatus,
+};
+use k8s_openapi::{http, CreateResponse, RequestError};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::{json, Value};
+
+use crate::{
+    command::command_def::{exec_match, start_clap, try_complete, Cmd},
+    completer,
+    env::Env,
+    error::ClickError,
+    output::ClickWriter,
+    table::{CellSpec, ColorType},
+    values::{val_str, val_str_opt},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+// newest first, SelfSubjectReview went GA in 1.28
+const SELF_SUBJECT_REVIEW_VERSIONS: [&str; 3] = ["v1", "v1beta1", "v1alpha1"];
+
+const VERBS: [&str; 10] = [
+    "create",
+    "delete",
+    "deletecollection",
+    "escalate",
+    "get",
+    "impersonate",
+    "list",
+    "patch",
+    "update",
+    "watch",
+];
+
+// groups of built in resources, so "deployments" means "deployments.apps" like it does for kubectl
+const BUILTIN_GROUPS: [(&str, &str); 17] = [
+    ("clusterrolebindings", "rbac.authorization.k8s.io"),
+    ("clusterroles", "rbac.authorization.k8s.io"),
+    ("cronjobs", "batch"),
+    ("customresourcedefinitions", "apiextensions.k8s.io"),
+    ("daemonsets", "apps"),
+    ("deployments", "apps"),
+    ("horizontalpodautoscalers", "autoscaling"),
+    ("ingresses", "networking.k8s.io"),
+    ("jobs", "batch"),
+    ("networkpolicies", "networking.k8s.io"),
+    ("poddisruptionbudgets", "policy"),
+    ("replicasets", "apps"),
+    ("rolebindings", "rbac.authorization.k8s.io"),
+    ("roles", "rbac.authorization.k8s.io"),
+    ("rollouts", "argoproj.io"),
+    ("statefulsets", "apps"),
+    ("storageclasses", "storage.k8s.io"),
+];
+
+fn verb_complete(prefix: &str, _env: &Env) -> Vec<RustlinePair> {
+    try_complete(prefix, &VERBS, false)
+}
+
+// Ask the server who we are. Returns the version that answered and the userInfo it returned, or
+// None if the server is too old to have SelfSubjectReview
+fn self_subject_review(env: &Env) -> Result<Option<(&'static str, Value)>, ClickError> {
+    for version in SELF_SUBJECT_REVIEW_VERSIONS {
+        let body = json!({
+            "apiVersion": format!("authentication.k8s.io/{version}"),
+            "kind": "SelfSubjectReview",
+        });
+        let request = http::Request::post(format!(
+            "/apis/authentication.k8s.io/{version}/selfsubjectreviews"
+        ))
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(&body)?)
+        .map_err(|e| ClickError::RequestError(RequestError::Http(e)))?;
+        let response = env.run_on_context(|c| c.execute(env.get_impersonation(), request))?;
+        let status = response.status();
+        if status == http::StatusCode::NOT_FOUND {
+            continue;
+        }
+        let val: Value = serde_json::from_slice(response.body())?;
+        if !status.is_success() {
+            return Err(ClickError::CommandError(format!(
+                "SelfSubjectReview failed with status {status}. Message: {}",
+                val_str("/message", &val, "<No message>")
+            )));
+        }
+        let user_info = val.pointer("/status/userInfo").cloned().unwrap_or_default();
+        return Ok(Some((version, user_info)));
+    }
+    Ok(None)
+}
+
+// a claim that's either a string or a list of strings, as groups can be
+fn string_list(val: Option<&Value>) -> Vec<String> {
+    match val {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(a)) => a
+            .iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// rows for a userInfo from a SelfSubjectReview
+fn user_info_rows(user_info: &Value) -> Vec<(String, String)> {
+    let mut rows = vec![(
+        "Username".to_string(),
+        val_str("/username", user_info, "<none>").into_owned(),
+    )];
+    if let Some(uid) = val_str_opt("/uid", user_info) {
+        rows.push(("UID".to_string(), uid));
+    }
+    rows.push((
+        "Groups".to_string(),
+        string_list(user_info.get("groups")).join("\n"),
+    ));
+    if let Some(extra) = user_info.get("extra").and_then(|e| e.as_object()) {
+        for (key, values) in extra {
+            rows.push((
+                format!("Extra: {key}"),
+                string_list(Some(values)).join("\n"),
+            ));
+        }
+    }
+    rows
+}
+
+// rows for whatever can be read out of a JWT bearer token, without verifying it
+fn token_rows(token: &str) -> Result<Vec<(String, String)>, ClickError> {
+    let payload = token.split('.').nth(1).ok_or_else(|| {
+        ClickError::CommandError("The token isn't a JWT, so there's nothing to decode".to_string())
+    })?;
+    let claims: Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload.trim_end_matches('='))?)?;
+
+    let mut rows = vec![];
+    // service account tokens, bound ones have a kubernetes.io claim, older ones flat claims
+    let sa_namespace = val_str_opt("/kubernetes.io/namespace", &claims)
+        .or_else(|| val_str_opt("/kubernetes.io~1serviceaccount~1namespace", &claims));
+    let sa_name = val_str_opt("/kubernetes.io/serviceaccount/name", &claims).or_else(|| {
+        val_str_opt(
+            "/kubernetes.io~1serviceaccount~1service-account.name",
+            &claims,
+        )
+    });
+    match (sa_namespace, sa_name) {
+        (Some(namespace), Some(name)) => {
+            rows.push((
+                "Username".to_string(),
+                format!("system:serviceaccount:{namespace}:{name}"),
+            ));
+            rows.push((
+                "Groups".to_string(),
+                format!(
+                    "system:serviceaccounts\nsystem:serviceaccounts:{namespace}\nsystem:authenticated"
+                ),
+            ));
+        }
+        _ => {
+            // the cluster decides which claim is the username, email and sub are the usual ones
+            if let Some(email) = val_str_opt("/email", &claims) {
+                rows.push(("Email".to_string(), email));
+            }
+            if let Some(sub) = val_str_opt("/sub", &claims) {
+                rows.push(("Subject".to_string(), sub));
+            }
+            let groups = string_list(claims.get("groups"));
+            if !groups.is_empty() {
+                rows.push(("Groups".to_string(), groups.join("\n")));
+            }
+        }
+    }
+    if let Some(iss) = val_str_opt("/iss", &claims) {
+        rows.push(("Issuer".to_string(), iss));
+    }
+    if let Some(exp) = claims.get("exp").and_then(|e| e.as_i64()) {
+        if let Some(exp) = Utc.timestamp_opt(exp, 0).single() {
+            rows.push(("Expires".to_string(), exp.to_rfc3339()));
+        }
+    }
+    Ok(rows)
+}
+
+fn print_rows(rows: Vec<(String, String)>, env: &Env, writer: &mut ClickWriter) {
+    let rows = rows
+        .into_iter()
+        .map(|(attr, val)| vec![attr.into(), val.into()])
+        .collect();
+    crate::table::print_table(vec!["Attribute", "Value"], rows, env, writer);
+}
+
+command!(
+    WhoAmI,
+    "whoami",
+    "Show who the cluster thinks you are",
+    |clap: ClapCommand<'static>| {
+        clap.after_help(
+            "This asks the cluster with a SelfSubjectReview, so it takes 'as' into account. Clusters \
+             older than 1.26 can't answer, so the bearer token is decoded instead, which is only \
+             what the token claims, not what the cluster makes of it.",
+        )
+    },
+    vec!["whoami"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_matches, env, writer| {
+        match self_subject_review(env)? {
+            Some((version, user_info)) => {
+                print_rows(user_info_rows(&user_info), env, writer);
+                clickwriteln!(writer, "From a SelfSubjectReview ({version})");
+            }
+            None => {
+                match env.get_impersonation() {
+                    // the token says who we are, but the cluster acts as whoever we impersonate
+                    Some(imp) => {
+                        let mut rows = vec![
+                            ("Username".to_string(), imp.user.clone()),
+                            ("Groups".to_string(), imp.groups.join("\n")),
+                        ];
+                        if let Some(uid) = imp.uid.as_ref() {
+                            rows.push(("UID".to_string(), uid.clone()));
+                        }
+                        for (key, values) in imp.extra.iter() {
+                            rows.push((format!("Extra: {key}"), values.join("\n")));
+                        }
+                        print_rows(rows, env, writer);
+                        clickwriteln!(
+                            writer,
+                            "The cluster doesn't support SelfSubjectReview, this is who 'as' \
+                         impersonates"
+                        );
+                    }
+                    None => match env.run_on_context(|c| c.bearer_token())? {
+                        Some(token) => {
+                            print_rows(token_rows(&token)?, env, writer);
+                            clickwriteln!(
+                            writer,
+                            "The cluster doesn't support SelfSubjectReview, this is decoded from \
+                             the token and not checked by the cluster"
+                        );
+                        }
+                        None => return Err(ClickError::CommandError(
+                            "The cluster doesn't support SelfSubjectReview, and the credentials \
+                             aren't a token that can be decoded"
+                                .to_string(),
+                        )),
+                    },
+                }
+            }
+        }
+        Ok(())
+    }
+);
+
+// split "deployments.apps/scale" into the resource, group and subresource
+fn parse_resource(spec: &str) -> (String, String, Option<String>) {
+    let (resource, subresource) = match spec.split_once('/') {
+        Some((resource, subresource)) => (resource, Some(subresource.to_string())),
+        None => (spec, None),
+    };
+    let (resource, group) = match resource.split_once('.') {
+        Some((resource, group)) => (resource, group),
+        None => {
+            let group = BUILTIN_GROUPS
+                .iter()
+                .find(|(r, _)| *r == resource)
+                .map(|(_, g)| *g)
+                .unwrap_or("");
+            (resource, group)
+        }
+    };
+    (resource.to_string(), group.to_string(), subresource)
+}
+
+// ask if we can do one thing
+fn can_i(
+    env: &Env,
+    verb: &str,
+    resource: &str,
+    name: Option<&str>,
+    namespace: Option<&str>,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let spec = if resource.starts_with('/') {
+        SelfSubjectAccessReviewSpec {
+            non_resource_attributes: Some(NonResourceAttributes {
+                path: Some(resource.to_string()),
+                verb: Some(verb.to_string()),
+            }),
+            ..Default::default()
+        }
+    } else {
+        let (resource, group, subresource) = parse_resource(resource);
+        SelfSubjectAccessReviewSpec {
+            resource_attributes: Some(ResourceAttributes {
+                group: Some(group),
+                name: name.map(|n| n.to_string()),
+                namespace: namespace.map(|ns| ns.to_string()),
+                resource: Some(resource),
+                subresource,
+                verb: Some(verb.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    };
+    let body = SelfSubjectAccessReview {
+        spec,
+        ..Default::default()
+    };
+    let (request, _) =
+        SelfSubjectAccessReview::create_self_subject_access_review(&body, Default::default())?;
+    let status = match env.run_on_context::<_, CreateResponse<SelfSubjectAccessReview>>(|c| {
+        c.read(env.get_impersonation(), request)
+    })? {
+        CreateResponse::Ok(review)
+        | CreateResponse::Created(review)
+        | CreateResponse::Accepted(review) => review.status.ok_or_else(|| {
+            ClickError::CommandError("SelfSubjectAccessReview returned no status".to_string())
+        })?,
+        CreateResponse::Other(Ok(Some(val))) => {
+            return Err(ClickError::CommandError(format!(
+                "SelfSubjectAccessReview failed. Message: {}",
+                val_str("/message", &val, "<No message>")
+            )))
+        }
+        CreateResponse::Other(Ok(None)) => {
+            return Err(ClickError::CommandError(
+                "SelfSubjectAccessReview failed with no reason given".to_string(),
+            ))
+        }
+        CreateResponse::Other(Err(e)) => {
+            return Err(ClickError::CommandError(format!(
+                "SelfSubjectAccessReview failed with an error: {e}"
+            )))
+        }
+    };
+
+    let allowed = if status.allowed {
+        CellSpec::with_colors("yes".into(), Some(ColorType::Success.into()), None)
+    } else {
+        CellSpec::with_colors("no".into(), Some(ColorType::Danger.into()), None)
+    };
+    let resource = match name {
+        Some(name) => format!("{resource} {name}"),
+        None => resource.to_string(),
+    };
+    let mut reason = status.reason.unwrap_or_default();
+    if let Some(err) = status.evaluation_error {
+        reason.push_str(&format!("\n(error evaluating: {err})"));
+    }
+    let row = vec![
+        verb.into(),
+        resource.into(),
+        namespace.unwrap_or("<all>").into(),
+        allowed,
+        reason.into(),
+    ];
+    crate::table::print_table(
+        vec!["Verb", "Resource", "Namespace", "Allowed", "Reason"],
+        vec![row],
+        env,
+        writer,
+    );
+    Ok(())
+}
+
+// "resource.group" for each resource and group of a rule, like kubectl shows them
+fn rule_resources(rule: &ResourceRule) -> Vec<String> {
+    let resources = rule.resources.clone().unwrap_or_default();
+    let groups = rule.api_groups.clone().unwrap_or_default();
+    resources
+        .iter()
+        .flat_map(|resource| {
+            groups.iter().map(move |group| {
+                if group.is_empty() {
+                    resource.clone()
+                } else {
+                    format!("{resource}.{group}")
+                }
+            })
+        })
+        .collect()
+}
+
+fn rules_rows(status: &SubjectRulesReviewStatus) -> Vec<Vec<CellSpec<'_>>> {
+    let resource_rows = status.resource_rules.iter().map(|rule| {
+        vec![
+            rule_resources(rule).join("\n").into(),
+            "".into(),
+            rule.resource_names
+                .as_ref()
+                .map(|names| names.join("\n"))
+                .unwrap_or_default()
+                .into(),
+            rule.verbs.join(", ").into(),
+        ]
+    });
+    let non_resource_rows = status.non_resource_rules.iter().map(|rule| {
+        vec![
+            "".into(),
+            rule.non_resource_urls
+                .as_ref()
+                .map(|urls| urls.join("\n"))
+                .unwrap_or_default()
+                .into(),
+            "".into(),
+            rule.verbs.join(", ").into(),
+        ]
+    });
+    resource_rows.chain(non_resource_rows).collect()
+}
+
+// list everything we can do in a namespace
+fn can_i_list(env: &Env, namespace: &str, writer: &mut ClickWriter) -> Result<(), ClickError> {
+    let body = SelfSubjectRulesReview {
+        spec: SelfSubjectRulesReviewSpec {
+            namespace: Some(namespace.to_string()),
+        },
+        ..Default::default()
+    };
+    let (request, _) =
+        SelfSubjectRulesReview::create_self_subject_rules_review(&body, Default::default())?;
+    let status = match env.run_on_context::<_, CreateResponse<SelfSubjectRulesReview>>(|c| {
+        c.read(env.get_impersonation(), request)
+    })? {
+        CreateResponse::Ok(review)
+        | CreateResponse::Created(review)
+        | CreateResponse::Accepted(review) => review.status.ok_or_else(|| {
+            ClickError::CommandError("SelfSubjectRulesReview returned no status".to_string())
+        })?,
+        CreateResponse::Other(Ok(Some(val))) => {
+            return Err(ClickError::CommandError(format!(
+                "SelfSubjectRulesReview failed. Message: {}",
+                val_str("/message", &val, "<No message>")
+            )))
+        }
+        CreateResponse::Other(Ok(None)) => {
+            return Err(ClickError::CommandError(
+                "SelfSubjectRulesReview failed with no reason given".to_string(),
+            ))
+        }
+        CreateResponse::Other(Err(e)) => {
+            return Err(ClickError::CommandError(format!(
+                "SelfSubjectRulesReview failed with an error: {e}"
+            )))
+        }
+    };
+
+    crate::table::print_table(
+        vec!["Resources", "Non-Resource URLs", "Resource Names", "Verbs"],
+        rules_rows(&status),
+        env,
+        writer,
+    );
+    if status.incomplete {
+        clickwriteln!(
+            writer,
+            "The list is incomplete, the cluster's authorizer can't list everything it allows{}",
+            status
+                .evaluation_error
+                .as_ref()
+                .map(|e| format!(": {e}"))
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+command!(
+    CanI,
+    "can-i",
+    "Check if you are allowed to do something",
+    |clap: ClapCommand<'static>| {
+        clap.arg(
+            Arg::new("verb")
+                .help("The verb, like get, list, create or delete")
+                .required_unless_present("list")
+                .index(1),
+        )
+        .arg(
+            Arg::new("resource")
+                .help(
+                    "The resource, like pods, deployments.apps or pods/log, or a non-resource \
+                     url like /version",
+                )
+                .required_unless_present("list")
+                .index(2),
+        )
+        .arg(
+            Arg::new("name")
+                .help("Only check for the object with this name")
+                .required(false)
+                .index(3),
+        )
+        .arg(
+            Arg::new("namespace")
+                .short('n')
+                .long("namespace")
+                .help("Check in this namespace instead of the current one")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("all-namespaces")
+                .short('A')
+                .long("all-namespaces")
+                .help("Check across all namespaces, or for a resource that isn't namespaced")
+                .conflicts_with("namespace")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .help("List everything you can do in the namespace")
+                .conflicts_with_all(&["verb", "all-namespaces"])
+                .takes_value(false),
+        )
+        .after_help(
+            "Checks are made by the cluster, and take 'as' into account.
+
+Examples:
+  # can I delete pods in the current namespace
+  can-i delete pods
+
+  # can I read logs in the prod namespace
+  can-i get pods/log -n prod
+
+  # what can I do in the current namespace
+  can-i --list",
+        )
+    },
+    vec!["can-i"],
+    vec![&verb_complete],
+    no_named_complete!(),
+    |matches, env, writer| {
+        let namespace = if matches.contains_id("all-namespaces") {
+            None
+        } else {
+            matches
+                .get_one::<String>("namespace")
+                .cloned()
+                .or_else(|| env.namespace.clone())
+        };
+        if matches.contains_id("list") {
+            let namespace = namespace.ok_or_else(|| {
+                ClickError::CommandError(
+                    "can-i --list needs a namespace, set one or pass -n".to_string(),
+                )
+            })?;
+            can_i_list(env, &namespace, writer)
+        } else {
+            let verb = matches.get_one::<String>("verb").unwrap(); // safe, required without list
+            let resource = matches.get_one::<String>("resource").unwrap(); // safe, same
+            let name = matches.get_one::<String>("name").map(|s| s.as_str());
+            can_i(env, verb, resource, name, namespace.as_deref(), writer)
+        }
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::URL_SAFE;
+
+    fn jwt(claims: Value) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap());
+        format!("eyJhbGciOiJSUzI1NiJ9.{payload}.c2lnbmF0dXJl")
+    }
+
+    #[test]
+    fn test_parse_resource() {
+        assert_eq!(
+            parse_resource("pods"),
+            ("pods".to_string(), "".to_string(), None)
+        );
+        assert_eq!(
+            parse_resource("deployments"),
+            ("deployments".to_string(), "apps".to_string(), None)
+        );
+        assert_eq!(
+            parse_resource("ingresses.networking.k8s.io"),
+            (
+                "ingresses".to_string(),
+                "networking.k8s.io".to_string(),
+                None
+            )
+        );
+        assert_eq!(
+            parse_resource("pods/log"),
+            ("pods".to_string(), "".to_string(), Some("log".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_token_rows() {
+        let token = jwt(json!({
+            "iss": "https://kubernetes.default.svc",
+            "sub": "system:serviceaccount:dev:deployer",
+            "exp": 1893456000,
+            "kubernetes.io": {
+                "namespace": "dev",
+                "serviceaccount": {"name": "deployer", "uid": "1234"},
+            },
+        }));
+        let rows = token_rows(&token).unwrap();
+        assert_eq!(
+            rows[0],
+            (
+                "Username".to_string(),
+                "system:serviceaccount:dev:deployer".to_string()
+            )
+        );
+        assert!(rows[1].1.contains("system:serviceaccounts:dev"));
+        assert_eq!(
+            rows[3],
+            (
+                "Expires".to_string(),
+                "2030-01-01T00:00:00+00:00".to_string()
+            )
+        );
+
+        // oidc tokens can be padded, and have a single group as a string
+        let payload = URL_SAFE.encode(br#"{"email":"jane@example.com","groups":"dev"}"#);
+        let rows = token_rows(&format!("x.{payload}.y")).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("Email".to_string(), "jane@example.com".to_string()),
+                ("Groups".to_string(), "dev".to_string()),
+            ]
+        );
+
+        assert!(token_rows("not-a-jwt").is_err());
+    }
+
+    #[test]
+    fn test_user_info_rows() {
+        let user_info = json!({
+            "username": "jane",
+            "groups": ["dev", "system:authenticated"],
+            "extra": {"scopes": ["view"]},
+        });
+        assert_eq!(
+            user_info_rows(&user_info),
+            vec![
+                ("Username".to_string(), "jane".to_string()),
+                (
+                    "Groups".to_string(),
+                    "dev\nsystem:authenticated".to_string()
+                ),
+                ("Extra: scopes".to_string(), "view".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rule_resources() {
+        let rule = ResourceRule {
+            api_groups: Some(vec!["".to_string(), "apps".to_string()]),
+            resources: Some(vec!["deployments".to_string()]),
+            verbs: vec!["get".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            rule_resources(&rule),
+            vec!["deployments", "deployments.apps"]
+        );
+    }
+}
@@ -12,17 +12,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use chrono::offset::Utc;
+use chrono::offset::{Local, Utc};
 use clap::{Arg, Command as ClapCommand};
 use comfy_table::Table;
+use regex::Regex;
 use rustyline::completion::Pair as RustlinePair;
 
 use crate::{
-    command::command_def::{exec_match, identity, start_clap, Cmd},
+    command::command_def::{add_export_args, exec_match, identity, output_arg, start_clap, Cmd},
     completer, config,
     env::Env,
-    output::ClickWriter,
-    table::CellSpec,
+    error::ClickError,
+    k8s::Impersonation,
+    output::{ClickWriter, Pager},
+    table::{CellSpec, ExportFormat, TableOptions},
 };
 
 use std::cell::RefCell;
@@ -43,7 +46,7 @@ command!(
     }
 );
 
-fn print_contexts(env: &Env, writer: &mut ClickWriter) {
+fn print_contexts(env: &Env, options: TableOptions, writer: &mut ClickWriter) {
     let mut contexts: Vec<&String> = env.config.contexts.keys().collect();
     contexts.sort();
     let ctxs = contexts
@@ -56,14 +59,21 @@ fn print_contexts(env: &Env, writer: &mut ClickWriter) {
             };
             row.push(CellSpec::with_colors(
                 (*context).clone().into(),
-                Some(env.styles.context_table_color().into()),
+                env.styles.context_table_color().map(|c| c.into()),
                 None,
             ));
             row.push(cluster.into());
             row
         })
         .collect();
-    crate::table::print_table(vec!["Context", "Api Server Address"], ctxs, env, writer);
+    crate::table::print_or_export_table(
+        &["Context", "Api Server Address"],
+        ctxs,
+        &[],
+        options,
+        env,
+        writer,
+    );
 }
 
 command!(
@@ -92,7 +102,7 @@ command!(
             env.set_context(context);
             env.clear_current();
         } else {
-            print_contexts(env, writer);
+            print_contexts(env, TableOptions::default(), writer);
         }
         Ok(())
     }
@@ -102,12 +112,12 @@ command!(
     Contexts,
     "contexts",
     "List available contexts",
-    identity,
+    add_export_args,
     vec!["contexts", "ctxs"],
     noop_complete!(),
     no_named_complete!(),
-    |_, env, writer| {
-        print_contexts(env, writer);
+    |matches, env, writer| {
+        print_contexts(env, TableOptions::from_matches(&matches), writer);
         Ok(())
     }
 );
@@ -126,10 +136,173 @@ command!(
     }
 );
 
+command!(
+    HistoryCmd,
+    "history",
+    "Show command history for the current context",
+    |clap: ClapCommand<'static>| {
+        clap.arg(
+            Arg::new("all")
+                .short('a')
+                .long("all")
+                .help("Show history for all contexts")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("grep")
+                .short('g')
+                .long("grep")
+                .help("Only show commands that match this regex")
+                .takes_value(true),
+        )
+        .after_help(
+            "Any command in the history can be run again by its number with !n. !! runs the \
+             last command, and !prefix runs the most recent command that starts with prefix. \
+             Anything after the reference is added to the command, so '!! | grep foo' works.
+
+If context_history is set to true (see 'set'), !! and !prefix only look at, and the up arrow only \
+shows, commands run in the current context.",
+        )
+    },
+    vec!["history", "hist"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let regex = match matches.get_one::<String>("grep") {
+            Some(re) => Some(
+                Regex::new(re)
+                    .map_err(|e| ClickError::CommandError(format!("Invalid regex: {e}")))?,
+            ),
+            None => None,
+        };
+        let context = if matches.contains_id("all") {
+            None
+        } else {
+            Some(env.context.as_ref().map(|c| c.name.as_str()).unwrap_or(""))
+        };
+        for (index, entry) in env.get_history().iter_for(context) {
+            if let Some(ref regex) = regex {
+                if !regex.is_match(&entry.line) {
+                    continue;
+                }
+            }
+            let time = entry
+                .time
+                .map(|time| {
+                    time.with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string()
+                })
+                .unwrap_or_else(|| "-".to_string());
+            clickwriteln!(
+                writer,
+                "{:>5}  {}  {}/{}  {}",
+                index,
+                time,
+                entry.context.as_deref().unwrap_or("none"),
+                entry.namespace.as_deref().unwrap_or("none"),
+                entry.line
+            );
+        }
+        Ok(())
+    }
+);
+
+command!(
+    Let,
+    "let",
+    "Set a variable that can be used in later commands as $NAME. With no args, lists variables",
+    |clap: ClapCommand<'static>| {
+        clap.arg(
+            Arg::new("assignment")
+                .help("NAME = value")
+                .required(false)
+                .multiple_values(true),
+        )
+        .arg(
+            Arg::new("unset")
+                .short('u')
+                .long("unset")
+                .help("Remove the specified variable")
+                .takes_value(true)
+                .conflicts_with("assignment"),
+        )
+        .after_help(
+            "Variables are expanded anywhere in a line as $NAME or ${NAME}, except inside single
+quotes. The output of a click command can be captured with $( command ), which is expanded
+to the command's output.
+
+The following variables are always set and can't be changed:
+  $CONTEXT    the current context
+  $NAMESPACE  the current namespace
+  $SELECTED   the name of the selected object (or names, if a range is selected)
+
+Examples:
+  # save the name of a pod
+  let P = my-pod-1234
+
+  # save the current namespace, and switch back to it later
+  let NS = $NAMESPACE
+  ns $NS
+
+  # save the image of the selected pod
+  let IMG = $(describe -j | jq -r '.spec.containers[0].image')
+
+  # remove a variable
+  let -u P",
+        )
+    },
+    vec!["let"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        if let Some(name) = matches.get_one::<String>("unset") {
+            if env.remove_variable(name) {
+                clickwriteln!(writer, "Removed {}", name);
+            } else {
+                clickwriteln!(writer, "No such variable: {}", name);
+            }
+        } else if let Some(assignment) = matches.get_many::<String>("assignment") {
+            let assignment: Vec<&str> = assignment.map(|s| s.as_str()).collect();
+            let assignment = assignment.join(" ");
+            let (name, value) = match assignment.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => {
+                    return Err(ClickError::CommandError(
+                        "Expected: let NAME = value".to_string(),
+                    ))
+                }
+            };
+            if !valid_variable_name(name) {
+                return Err(ClickError::CommandError(format!(
+                    "Invalid variable name '{name}'. Names must start with a letter or _, and \
+                     contain only letters, numbers and _"
+                )));
+            }
+            if !env.set_variable(name, value.to_string()) {
+                return Err(ClickError::CommandError(format!(
+                    "Can't set {name}, it's a builtin variable"
+                )));
+            }
+        } else {
+            for (name, value) in env.get_variables().iter() {
+                clickwriteln!(writer, "{} = '{}'", name, value);
+            }
+        }
+        Ok(())
+    }
+);
+
+fn valid_variable_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 command!(
     As,
     "as",
-    "Set the username to impersonate for requests. With no arg, shows the current setting",
+    "Set the user (and optionally groups, uid and extra fields) to impersonate for requests. With \
+     no arg, shows the current setting",
     |clap: ClapCommand<'static>| {
         clap.arg(
             Arg::new("user")
@@ -137,35 +310,77 @@ command!(
                 .required(false)
                 .index(1),
         )
+        .arg(
+            Arg::new("group")
+                .short('g')
+                .long("group")
+                .help("A group to impersonate, can be given more than once")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .requires("user"),
+        )
+        .arg(
+            Arg::new("uid")
+                .long("uid")
+                .help("The uid to impersonate")
+                .takes_value(true)
+                .requires("user"),
+        )
+        .arg(
+            Arg::new("extra")
+                .long("extra")
+                .help("An extra field to impersonate as key=value, can be given more than once")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .requires("user"),
+        )
         .arg(
             Arg::new("clear")
                 .short('c')
                 .long("clear")
                 .help("revert to the default user"),
         )
+        .after_help(
+            "Impersonation is sent with every request, and passed on to kubectl for exec, \
+             port-forward and copy. kubectl has no flag for extra fields, so those commands refuse \
+             to run while extra fields are set.
+
+Examples:
+  # see what a member of the dev group can do
+  as jane --group dev --group system:authenticated",
+        )
     },
     vec!["as"],
     noop_complete!(),
     no_named_complete!(),
     |matches, env, writer| {
         if matches.contains_id("clear") {
-            env.set_impersonate_user(None);
-            clickwriteln!(writer, "Impersonate user cleared");
-        } else if matches.contains_id("user") {
-            let user = matches
-                .get_one::<String>("user")
-                .map(|s| s.as_str())
-                .map(|s| s.to_string());
-            clickwriteln!(
-                writer,
-                "Set impersonate user to: {}",
-                user.as_deref().unwrap()
-            );
-            env.set_impersonate_user(user);
+            env.set_impersonation(None);
+            clickwriteln!(writer, "Impersonation cleared");
+        } else if let Some(user) = matches.get_one::<String>("user") {
+            let mut impersonation = Impersonation::user(user.as_str());
+            if let Some(groups) = matches.get_many::<String>("group") {
+                impersonation.groups = groups.cloned().collect();
+            }
+            impersonation.uid = matches.get_one::<String>("uid").cloned();
+            for extra in matches.get_many::<String>("extra").into_iter().flatten() {
+                let (key, value) = extra.split_once('=').ok_or_else(|| {
+                    ClickError::CommandError(format!(
+                        "Invalid extra field {extra}, must be key=value"
+                    ))
+                })?;
+                impersonation
+                    .extra
+                    .entry(key.to_string())
+                    .or_default()
+                    .push(value.to_string());
+            }
+            clickwriteln!(writer, "Impersonating: {}", impersonation);
+            env.set_impersonation(Some(impersonation));
         } else {
-            match env.get_impersonate_user() {
-                Some(user) => {
-                    clickwriteln!(writer, "Impersonate user: {}", user);
+            match env.get_impersonation() {
+                Some(impersonation) => {
+                    clickwriteln!(writer, "Impersonating: {}", impersonation);
                 }
                 None => {
                     clickwriteln!(writer, "Using default user from config");
@@ -219,28 +434,46 @@ command!(
     Last,
     "last",
     "List target objects from the last executed query",
-    identity,
+    |clap: ClapCommand<'static>| {
+        clap.arg(output_arg()).arg(
+            Arg::new("no-headers")
+                .long("no-headers")
+                .help("Don't export column headers (ignored for markdown)")
+                .takes_value(false),
+        )
+    },
     vec!["last"],
     noop_complete!(),
     no_named_complete!(),
-    |_, env, writer| {
-        if let Some(table) = env.get_last_table() {
-            clickwriteln!(writer, "{table}");
-        } else {
-            clickwriteln!(writer, "no last objects to display");
+    |matches, env, writer| {
+        match (ExportFormat::from_matches(&matches), env.get_last_table()) {
+            (_, None) => clickwriteln!(writer, "no last objects to display"),
+            (Some(format), Some(_)) => {
+                if let Some(export) = env.get_last_export() {
+                    let no_headers = matches.contains_id("no-headers");
+                    clickwrite!(writer, "{}", export.render(format, no_headers));
+                }
+            }
+            (None, Some(table)) => clickwriteln!(writer, "{table}"),
         }
         Ok(())
     }
 );
 
-pub const SET_OPTS: [&str; 7] = [
+pub const SET_OPTS: [&str; 13] = [
     "completion_type",
     "edit_mode",
     "editor",
     "kubectl_binary",
+    "pager",
     "terminal",
     "range_separator",
     "describe_include_events",
+    "context_history",
+    "exec_credential_cache",
+    "prompt_template",
+    "production_context_pattern",
+    "theme",
 ];
 
 command!(
@@ -270,7 +503,22 @@ Example:
   set -- range_separator \"---- {name} [{namespace}] ----\"
 
   # set edit_mode
-  set edit_mode emacs",
+  set edit_mode emacs
+
+  # Show the impersonated user and the time in the prompt. Placeholders are {context},
+  # {namespace}, {selection}, {user}, {server}, {time}, {forwards} and {status}
+  set prompt_template \"{time} [{context}] [{namespace}] [{selection}] {user}> \"
+
+  # Highlight the context in the prompt when it matches a regex
+  set production_context_pattern \"^prod\"
+
+  # Use the theme for light terminal backgrounds. Possible values are dark, light and monochrome.
+  # Individual colors can be set in the theme section of the click config file
+  set theme light
+
+  # Page long output with less instead of the internal pager. Use 'internal' to go back to the
+  # internal pager, or 'off' to never page
+  set pager \"less -R\"",
         )
     },
     vec!["set"],
@@ -318,6 +566,13 @@ Example:
             "kubectl_binary" => {
                 env.set_kubectl_binary(Some(value));
             }
+            "pager" => match Pager::from_setting(Some(value)).validate() {
+                Ok(()) => env.click_config.pager = Some(value.to_string()),
+                Err(e) => {
+                    clickwriteln!(writer, "{}", e);
+                    failed = true;
+                }
+            },
             "range_separator" => {
                 env.click_config.range_separator = value.to_string();
             }
@@ -331,6 +586,57 @@ Example:
                     failed = true;
                 }
             },
+            "context_history" => match value.parse() {
+                Ok(b) => env.click_config.context_history = b,
+                Err(_) => {
+                    clickwriteln!(writer, "context_history must be set to 'true' or 'false'");
+                    failed = true;
+                }
+            },
+            "exec_credential_cache" => match value.parse() {
+                Ok(b) => env.click_config.exec_credential_cache = b,
+                Err(_) => {
+                    clickwriteln!(
+                        writer,
+                        "exec_credential_cache must be set to 'true' or 'false'"
+                    );
+                    failed = true;
+                }
+            },
+            "prompt_template" => {
+                if let Err(e) = env.set_prompt_template(Some(value)) {
+                    clickwriteln!(writer, "{}", e);
+                    failed = true;
+                }
+            }
+            "production_context_pattern" => {
+                if let Err(e) = env.set_production_context_pattern(Some(value)) {
+                    clickwriteln!(writer, "{}", e);
+                    failed = true;
+                }
+            }
+            "theme" => {
+                let preset = match value {
+                    "dark" => Some(config::ThemePreset::Dark),
+                    "light" => Some(config::ThemePreset::Light),
+                    "monochrome" => Some(config::ThemePreset::Monochrome),
+                    _ => None,
+                };
+                match preset.map(|preset| env.set_theme_preset(preset)) {
+                    Some(Ok(())) => {}
+                    Some(Err(e)) => {
+                        clickwriteln!(writer, "{}", e);
+                        failed = true;
+                    }
+                    None => {
+                        clickwriteln!(
+                            writer,
+                            "Invalid theme.  Possible values are: [dark, light, monochrome]"
+                        );
+                        failed = true;
+                    }
+                }
+            }
             _ => {
                 // this shouldn't happen
                 writeln!(stderr(), "Invalid option").unwrap_or(());
@@ -344,7 +650,15 @@ Example:
     }
 );
 
-pub const UNSET_OPTS: [&str; 4] = ["editor", "kubectl_binary", "terminal", "range_separator"];
+pub const UNSET_OPTS: [&str; 7] = [
+    "editor",
+    "kubectl_binary",
+    "pager",
+    "terminal",
+    "range_separator",
+    "prompt_template",
+    "production_context_pattern",
+];
 
 command!(
     UnSetCmd,
@@ -378,9 +692,19 @@ command!(
             "kubectl_binary" => {
                 env.set_kubectl_binary(None);
             }
+            "pager" => {
+                env.click_config.pager = None;
+            }
             "range_separator" => {
                 env.click_config.range_separator = crate::config::default_range_sep();
             }
+            "prompt_template" => {
+                // unsetting can't fail
+                let _ = env.set_prompt_template(None);
+            }
+            "production_context_pattern" => {
+                let _ = env.set_production_context_pattern(None);
+            }
             _ => {
                 // this shouldn't happen
                 writeln!(stderr(), "Invalid option").unwrap_or(());
@@ -17,9 +17,11 @@ use clap::builder::{PossibleValue, PossibleValuesParser};
 use clap::{Arg, ArgMatches, Command as ClapCommand};
 use rustyline::completion::Pair as RustlinePair;
 
+use crate::command::Col;
 use crate::env::Env;
 use crate::error::ClickError;
 use crate::output::ClickWriter;
+use crate::table::ExportFormat;
 
 use std::cell::RefCell;
 use std::io::Write;
@@ -70,7 +72,7 @@ macro_rules! extract_first {
         let mut result: [&str; $map.len()] = [""; $map.len()];
         let mut i = 0;
         while i < $map.len() {
-            result[i] = $map[i].0;
+            result[i] = $map[i].flag;
             i += 1;
         }
         result
@@ -378,9 +380,16 @@ macro_rules! list_command {
             $cmd_name,
             $name,
             $about,
-            $extra_args,
+            |clap| {
+                use crate::command::command_def::{add_table_args, add_view_args};
+                add_table_args(add_view_args($extra_args(clap)))
+            },
             $aliases,
-            $cmplters,
+            $cmplters
+                .into_iter()
+                .chain([&crate::completer::view_completer
+                    as &'static dyn Fn(&str, &Env) -> Vec<RustlinePair>])
+                .collect(),
             //$named_cmplters,
             [
                 (
@@ -409,24 +418,18 @@ macro_rules! list_command {
 /// to be added to this vector
 /// lables: If the --lables flag was specified (deprecated)
 /// flags: A vector of the flags that were passed by the user
-/// extra_cols: Extra cols to consider. This is a vector of (column_name, flag). If flag is in
-/// flags, then column_name is added to cols. The order in this vector is the order columns will be
-/// displayed in the output
-pub fn add_extra_cols<'a>(
-    cols: &mut Vec<&'a str>,
-    labels: bool,
-    flags: Vec<&str>,
-    extra_cols: &[(&'a str, &'a str)],
-) {
+/// extra_cols: Extra cols to consider. If a col's flag is in flags, then its title is added to
+/// cols. The order in this slice is the order columns will be displayed in the output
+pub fn add_extra_cols(cols: &mut Vec<&str>, labels: bool, flags: Vec<&str>, extra_cols: &[Col]) {
     let show_all = flags.iter().any(|e| e.eq_ignore_ascii_case("all"));
 
-    for (flag, col) in extra_cols.iter() {
-        if col.eq(&"Labels") {
+    for col in extra_cols.iter() {
+        if col.title.eq("Labels") {
             if labels || flags.iter().any(|e| e.eq_ignore_ascii_case("labels")) {
-                cols.push(col)
+                cols.push(col.title)
             }
-        } else if show_all || flags.iter().any(|e| e.eq_ignore_ascii_case(flag)) {
-            cols.push(col)
+        } else if show_all || flags.iter().any(|e| e.eq_ignore_ascii_case(col.flag)) {
+            cols.push(col.title)
         }
     }
 }
@@ -461,6 +464,80 @@ pub fn sort_arg<'a>(cols: &'a [&'static str], extra_cols: Option<&'a [&'static s
         .value_parser(pvp)
 }
 
+/// add the args that control how list tables are printed
+pub fn add_table_args(clap: ClapCommand<'static>) -> ClapCommand<'static> {
+    clap.arg(
+        Arg::new("wide")
+            .long("wide")
+            .help("Show all columns at full width, even if the output is wider than the terminal")
+            .takes_value(false),
+    )
+    .arg(
+        Arg::new("no-headers")
+            .long("no-headers")
+            .help("Don't print column headers or borders")
+            .takes_value(false),
+    )
+    .arg(
+        Arg::new("no-index")
+            .long("no-index")
+            .help("Don't print the index column")
+            .takes_value(false),
+    )
+    .arg(output_arg())
+}
+
+/// add the args for exporting a table that isn't a list of objects, so has no index column
+pub fn add_export_args(clap: ClapCommand<'static>) -> ClapCommand<'static> {
+    clap.arg(
+        Arg::new("no-headers")
+            .long("no-headers")
+            .help("Don't print column headers or borders")
+            .takes_value(false),
+    )
+    .arg(output_arg())
+}
+
+/// add the args to pick the saved view a list command uses
+pub fn add_view_args(clap: ClapCommand<'static>) -> ClapCommand<'static> {
+    clap.arg(
+        Arg::new("view")
+            .help(
+                "Use the saved view with this name, specified as @name. See 'help views' for \
+                 how to define views",
+            )
+            .value_parser(|s: &str| {
+                if s.starts_with('@') {
+                    Ok(s.to_string())
+                } else {
+                    Err("views are specified as @name".to_string())
+                }
+            }),
+    )
+    .arg(
+        Arg::new("no-view")
+            .long("no-view")
+            .help("Don't use the default view for this type")
+            .conflicts_with("view")
+            .takes_value(false),
+    )
+}
+
+/// get a clap arg for exporting a table as csv, tsv, or markdown
+pub fn output_arg() -> Arg<'static> {
+    Arg::new("output")
+        .short('o')
+        .long("output")
+        .help(
+            "Export the table in the specified format instead of printing it. Exported values \
+             are raw, so times are full timestamps and quantities are in base units. The \
+             index column isn't exported.",
+        )
+        .takes_value(true)
+        .ignore_case(true)
+        .value_parser(PossibleValuesParser::new(ExportFormat::NAMES))
+}
+
 static SHOW_HELP: &str =
     "Comma separated list (case-insensitive) of extra columns to show in output. \
      Use '--show all' to show all available columns.";
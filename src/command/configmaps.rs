@@ -17,7 +17,7 @@ use k8s_openapi::api::core::v1 as api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -36,11 +36,15 @@ lazy_static! {
         m
     };
 }
-const COL_MAP: &[(&str, &str)] = &[("name", "Name"), ("data", "Data"), ("age", "Age")];
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("data", "Data").priority(6),
+    Col::new("age", "Age").priority(7),
+];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[("labels", "Labels")];
+const EXTRA_COL_MAP: &[Col] = &[Col::new("labels", "Labels").priority(1).truncate(40)];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
 
@@ -50,6 +54,7 @@ fn cm_to_kobj(configmap: &api::ConfigMap) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::ConfigMap,
+        context: None,
     }
 }
 
@@ -98,7 +103,7 @@ list_command!(
             Some(ns) => api::ConfigMap::list_namespaced_config_map(ns, Default::default())?,
             None => api::ConfigMap::list_config_map_for_all_namespaces(Default::default())?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
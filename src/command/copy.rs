@@ -33,7 +33,7 @@ use std::process::Command;
 #[allow(clippy::too_many_arguments)]
 fn do_copy(
     pod: &KObj,
-    context: &str,
+    kubectl_args: &[String],
     src: &str,
     dest: &str,
     from: bool,
@@ -56,8 +56,7 @@ fn do_copy(
     let mut command = Command::new("kubectl");
     command
         .arg("cp")
-        .arg("--context")
-        .arg(context)
+        .args(kubectl_args)
         .arg(&*src_arg)
         .arg(&*dest_arg)
         .arg("--retries")
@@ -179,7 +178,10 @@ Examples:
             Some(&env.click_config.range_separator),
             |obj, writer| {
                 if obj.is_pod() {
-                    do_copy(obj, &context.name, src, dest, from, retries, writer)
+                    let context = env.context_name_for(obj).unwrap_or(&context.name);
+                    let mut kubectl_args = env.config.kubectl_context_args(context)?;
+                    kubectl_args.extend(env.kubectl_impersonation_args()?);
+                    do_copy(obj, &kubectl_args, src, dest, from, retries, writer)
                 } else {
                     Err(ClickError::CommandError(
                         "Copy only possible on pods".to_string(),
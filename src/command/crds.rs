@@ -63,7 +63,7 @@ fn find_desc_for(env: &mut Env, name: &str) -> Result<Option<CrdApiDesc>, ClickE
         if let Some(group_version) = version {
             let (group_req, _) = crate::crd::get_api_group_resources(group_version)?;
             match env.run_on_context::<_, GetAPIGroupResourcesResponse>(|c| {
-                c.read(env.get_impersonate_user(), group_req)
+                c.read(env.get_impersonation(), group_req)
             })? {
                 GetAPIGroupResourcesResponse::Ok(resp) => {
                     for resource in resp.resources.iter() {
@@ -108,7 +108,7 @@ command!(
             Some(desc) => {
                 let (request, _) = get_k8s_table(&desc.url(env.namespace.as_deref()))?;
                 match env.run_on_context::<_, GetTableResponse>(|c| {
-                    c.read(env.get_impersonate_user(), request)
+                    c.read(env.get_impersonation(), request)
                 })? {
                     GetTableResponse::Ok(resp) => {
                         let kobjs = resp.print_to(
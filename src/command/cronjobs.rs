@@ -17,7 +17,7 @@ use k8s_openapi::api::batch::v1beta1 as batch_api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{keyval_string, run_list_command, time_since, Extractor},
+    command::{keyval_string, run_list_command, time_since, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -42,22 +42,24 @@ lazy_static! {
         m
     };
 }
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("schedule", "Schedule"),
-    ("suspend", "Suspend"),
-    ("active", "Active"),
-    ("lastschedule", "Last Schedule"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("schedule", "Schedule").priority(6),
+    Col::new("suspend", "Suspend").priority(6),
+    Col::new("active", "Active").priority(6),
+    Col::new("lastschedule", "Last Schedule").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[
-    ("containers", "Containers"),
-    ("images", "Images"),
-    ("selector", "Selector"),
-    ("labels", "Labels"),
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("containers", "Containers")
+        .priority(3)
+        .truncate(30),
+    Col::new("images", "Images").priority(2).truncate(40),
+    Col::new("selector", "Selector").priority(2).truncate(40),
+    Col::new("labels", "Labels").priority(1).truncate(40),
 ];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
@@ -68,6 +70,7 @@ fn cjob_to_kobj(cjob: &batch_api::CronJob) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::CronJob,
+        context: None,
     }
 }
 
@@ -182,7 +185,7 @@ list_command!(
             Some(ns) => batch_api::CronJob::list_namespaced_cron_job(ns, Default::default())?,
             None => batch_api::CronJob::list_cron_job_for_all_namespaces(Default::default())?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
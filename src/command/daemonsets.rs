@@ -17,7 +17,7 @@ use k8s_openapi::api::apps::v1 as apps_api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -42,23 +42,25 @@ lazy_static! {
         m
     };
 }
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("desired", "Desired"),
-    ("current", "Current"),
-    ("ready", "Ready"),
-    ("uptodate", "Up-To-Date"),
-    ("available", "Available"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("desired", "Desired").priority(6),
+    Col::new("current", "Current").priority(6),
+    Col::new("ready", "Ready").priority(6),
+    Col::new("uptodate", "Up-To-Date").priority(6),
+    Col::new("available", "Available").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[
-    ("containers", "Containers"),
-    ("images", "Images"),
-    ("labels", "Labels"),
-    ("namespace", "Namespace"),
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("containers", "Containers")
+        .priority(3)
+        .truncate(30),
+    Col::new("images", "Images").priority(2).truncate(40),
+    Col::new("labels", "Labels").priority(1).truncate(40),
+    Col::new("namespace", "Namespace"),
 ];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
@@ -69,6 +71,7 @@ fn ds_to_kobj(daemonset: &apps_api::DaemonSet) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::DaemonSet,
+        context: None,
     }
 }
 
@@ -171,7 +174,7 @@ list_command!(
             Some(ns) => apps_api::DaemonSet::list_namespaced_daemon_set(ns, Default::default())?,
             None => apps_api::DaemonSet::list_daemon_set_for_all_namespaces(Default::default())?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
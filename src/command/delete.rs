@@ -41,8 +41,8 @@ fn send_delete<D: DeserializeOwned + Debug>(
     writer: &mut ClickWriter,
     request: Request<Vec<u8>>,
 ) -> Result<(), ClickError> {
-    let r = env
-        .run_on_context::<_, DeleteResponse<D>>(|c| c.read(env.get_impersonate_user(), request))?;
+    let r =
+        env.run_on_context::<_, DeleteResponse<D>>(|c| c.read(env.get_impersonation(), request))?;
     match r {
         DeleteResponse::OkStatus(_) | DeleteResponse::OkValue(_) => {
             clickwriteln!(writer, "Deleted");
@@ -241,7 +241,7 @@ fn confirm_delete(
 ) -> Result<(), ClickError> {
     let name = obj.name();
     clickwrite!(writer, "Delete {} {} [y/N]? ", obj.type_str(), name);
-    io::stdout().flush().expect("Could not flush stdout");
+    writer.flush().expect("Could not flush stdout");
     let mut conf = String::new();
     if io::stdin().read_line(&mut conf).is_ok() {
         if conf.trim() == "y" || conf.trim() == "yes" {
@@ -17,7 +17,7 @@ use k8s_openapi::api::apps::v1 as apps_api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -41,21 +41,23 @@ lazy_static! {
         m
     };
 }
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("ready", "Ready"),
-    ("desired", "Desired"),
-    ("uptodate", "Up To Date"),
-    ("available", "Available"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("ready", "Ready").priority(6),
+    Col::new("desired", "Desired").priority(6),
+    Col::new("uptodate", "Up To Date").priority(6),
+    Col::new("available", "Available").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[
-    ("containers", "Containers"),
-    ("images", "Images"),
-    ("namespace", "Namespace"),
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("containers", "Containers")
+        .priority(3)
+        .truncate(30),
+    Col::new("images", "Images").priority(2).truncate(40),
+    Col::new("namespace", "Namespace"),
 ];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
@@ -66,6 +68,7 @@ fn deployment_to_kobj(deployment: &apps_api::Deployment) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::Deployment,
+        context: None,
     }
 }
 
@@ -164,13 +167,17 @@ list_command!(
         ),
     vec!["deps", "deployments"],
     noop_complete!(),
-    [].into_iter(),
+    [(
+        "regex".to_string(),
+        completer::deployment_name_completer as fn(&str, &Env) -> Vec<RustlinePair>
+    )]
+    .into_iter(),
     |matches, env, writer| {
         let (request, _response_body) = match &env.namespace {
             Some(ns) => apps_api::Deployment::list_namespaced_deployment(ns, Default::default())?,
             None => apps_api::Deployment::list_deployment_for_all_namespaces(Default::default())?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
@@ -85,3 +85,86 @@ command!(
         )
     }
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ClickErrNo, ClickError};
+    use crate::kobj::{KObj, ObjType};
+    use crate::stub_server::{self, env_for, StubResponse};
+    use tempdir::TempDir;
+
+    fn describe(env: &mut Env, typ: ObjType, name: &str) -> Result<String, ClickError> {
+        let obj = KObj {
+            name: name.to_string(),
+            namespace: Some("dev".to_string()),
+            typ,
+            context: None,
+        };
+        env.set_last_objs(vec![obj], None);
+        env.set_current(0);
+        let mut writer = ClickWriter::with_buffer(vec![], false);
+        Describe::new().exec(env, &mut "-e false".split_whitespace(), &mut writer)?;
+        Ok(String::from_utf8(writer.finish_output().unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_describe_survives_forbidden() {
+        let dir = TempDir::new("click_describe").unwrap();
+        let forbidden = || StubResponse::new("403 Forbidden", r#"{"message":"forbidden"}"#);
+        let (server, handle) = stub_server::serve(vec![
+            forbidden(),
+            StubResponse::new(
+                "200 OK",
+                r#"{"metadata":{"name":"web","namespace":"dev"},"spec":{"type":"ClusterIP"}}"#,
+            ),
+            forbidden(),
+        ]);
+        let mut env = env_for(&server, &dir);
+
+        // endpoints can't be read, but the service still gets described
+        let out = describe(&mut env, ObjType::Service, "web").unwrap();
+        assert!(out.contains("Error fetching endpoints"));
+        assert!(out.contains("web"));
+
+        match describe(&mut env, ObjType::ConfigMap, "settings") {
+            Err(ClickError::Kube(ClickErrNo::Forbidden(message, _))) => {
+                assert_eq!(message, "forbidden")
+            }
+            other => panic!("expected forbidden, got {other:?}"),
+        }
+        let lines: Vec<String> = handle
+            .join()
+            .unwrap()
+            .into_iter()
+            .map(|request| request.line)
+            .collect();
+        assert!(lines[0].contains("/endpoints/web"));
+        assert!(lines[1].contains("/services/web"));
+        assert!(lines[2].contains("/configmaps/settings"));
+    }
+
+    #[test]
+    fn test_describe_crd_forbidden() {
+        let dir = TempDir::new("click_describe").unwrap();
+        let (server, _) = stub_server::serve(vec![StubResponse::new(
+            "403 Forbidden",
+            r#"{"message":"widgets is forbidden"}"#,
+        )]);
+        let mut env = env_for(&server, &dir);
+        let typ = ObjType::Crd {
+            _type: "widgets".to_string(),
+            group_version: "example.com/v1".to_string(),
+        };
+        match describe(&mut env, typ, "gear") {
+            Err(ClickError::Kube(ClickErrNo::Forbidden(message, suggestion))) => {
+                assert_eq!(message, "widgets is forbidden");
+                assert_eq!(
+                    suggestion.unwrap(),
+                    "can-i get widgets.example.com gear -n dev"
+                );
+            }
+            other => panic!("expected forbidden, got {other:?}"),
+        }
+    }
+}
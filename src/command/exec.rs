@@ -47,6 +47,8 @@ fn do_exec(
         .kubectl_binary
         .as_deref()
         .unwrap_or("kubectl");
+    let impersonate_args = env.kubectl_impersonation_args()?;
+    let context_args = env.config.kubectl_context_args(kluster_name)?;
     if do_terminal {
         let terminal = if let Some(t) = term_opt {
             t
@@ -56,15 +58,9 @@ fn do_exec(
             "xterm -e"
         };
         let mut targs: Vec<&str> = terminal.split_whitespace().collect();
-        let mut kubectl_args = vec![
-            kubectl_binary,
-            "--namespace",
-            ns,
-            "--context",
-            kluster_name,
-            "exec",
-        ];
-        targs.append(&mut kubectl_args);
+        targs.extend([kubectl_binary, "--namespace", ns]);
+        targs.extend(context_args.iter().map(|arg| arg.as_str()));
+        targs.push("exec");
         if let Some(it) = it_arg {
             targs.push(it);
         }
@@ -73,10 +69,7 @@ fn do_exec(
             targs.push("-c");
             targs.push(cont);
         }
-        if let Some(user) = env.get_impersonate_user() {
-            targs.push("--as");
-            targs.push(user);
-        }
+        targs.extend(impersonate_args.iter().map(|arg| arg.as_str()));
         targs.push("--");
         targs.extend(cmd.iter());
         clickwriteln!(writer, "Starting on {} in terminal", pod.name());
@@ -87,16 +80,13 @@ fn do_exec(
         command
             .arg("--namespace")
             .arg(ns)
-            .arg("--context")
-            .arg(kluster_name)
+            .args(&context_args)
             .arg("exec");
         if let Some(it) = it_arg {
             command.arg(it);
         }
         command.arg(pod.name());
-        if let Some(user) = env.get_impersonate_user() {
-            command.arg("--as").arg(user);
-        }
+        command.args(&impersonate_args);
         if let Some(cont) = cont_opt {
             command.arg("-c").arg(cont).arg("--").args(cmd);
         } else {
@@ -197,6 +187,7 @@ command!(
             .unwrap()
             .map(|s| s.as_str())
             .collect(); // safe as required
+
         let tty = !matches.contains_id("tty") || *matches.get_one::<bool>("tty").unwrap();
         let stdin = !matches.contains_id("stdin") || *matches.get_one::<bool>("stdin").unwrap();
         let it_arg = match (tty, stdin) {
@@ -213,7 +204,7 @@ command!(
                     do_exec(
                         env,
                         obj,
-                        &context.name,
+                        env.context_name_for(obj).unwrap_or(&context.name),
                         &cmd,
                         &it_arg,
                         &matches.get_one::<String>("container").map(|s| s.as_str()),
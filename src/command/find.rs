@@ -0,0 +1,447 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{Arg, Command as ClapCommand};
+use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{APIResource, ObjectMeta};
+use k8s_openapi::ListResponse;
+use regex::Regex;
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{add_table_args, exec_match, start_clap, Cmd},
+    completer,
+    crd::{GetAPIGroupResourcesResponse, ListMetadataResponse},
+    env::Env,
+    error::{ClickErrNo, ClickError},
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+    table::{CellSpec, TableOptions},
+};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+/// A type of resource the server can list
+struct ListableType {
+    group_version: String,
+    name: String,
+    kind: String,
+}
+
+// does the resource match any of the kinds the user asked for
+fn kind_matches(resource: &APIResource, kinds: &[String]) -> bool {
+    kinds.iter().any(|kind| {
+        resource.kind.eq_ignore_ascii_case(kind)
+            || resource.name == *kind
+            || resource.singular_name == *kind
+            || resource
+                .short_names
+                .as_ref()
+                .map(|names| names.contains(kind))
+                .unwrap_or(false)
+    })
+}
+
+// the group part of a group version, "" for the core group
+fn api_group(group_version: &str) -> String {
+    match group_version.split_once('/') {
+        Some((group, _)) => group.to_string(),
+        None => String::new(),
+    }
+}
+
+// the request failed because the user isn't allowed to make it
+fn is_forbidden(err: &ClickError) -> bool {
+    matches!(err, ClickError::Kube(ClickErrNo::Forbidden(_, _)))
+}
+
+/// Use api discovery to find every type of resource that can be listed. If kinds is Some, only
+/// return types that match one of them. Group versions the user isn't allowed to discover are added
+/// to forbidden.
+fn listable_types(
+    env: &mut Env,
+    kinds: Option<&[String]>,
+    forbidden: &mut Vec<String>,
+) -> Result<Vec<ListableType>, ClickError> {
+    let mut types = vec![];
+    // only search a kind once per group. The same kind in different groups (like Event in core and
+    // in events.k8s.io) are different types, so both get searched.
+    let mut seen_kinds = HashSet::new();
+    for group_version in crate::crd::get_api_group_versions(env)?.into_iter() {
+        let (request, _) = crate::crd::get_api_group_resources(&group_version)?;
+        let response = match env.run_on_context::<_, GetAPIGroupResourcesResponse>(|c| {
+            c.read(env.get_impersonation(), request)
+        }) {
+            Err(e) if is_forbidden(&e) => {
+                forbidden.push(group_version);
+                continue;
+            }
+            response => response?,
+        };
+        if let GetAPIGroupResourcesResponse::Ok(resp) = response {
+            for resource in resp.resources.into_iter() {
+                let listable = !resource.name.contains('/') // skip subresources
+                    && resource.verbs.iter().any(|verb| verb == "list");
+                if listable
+                    && kinds.map(|k| kind_matches(&resource, k)).unwrap_or(true)
+                    && seen_kinds.insert((api_group(&group_version), resource.kind.clone()))
+                {
+                    types.push(ListableType {
+                        group_version: group_version.clone(),
+                        name: resource.name,
+                        kind: resource.kind,
+                    });
+                }
+            }
+        }
+    }
+    Ok(types)
+}
+
+/// Get the ObjType for a found object. Types click knows about get their own ObjType, so all
+/// commands work on them, anything else is treated like a crd
+fn obj_type_for(typ: &ListableType) -> ObjType {
+    match (typ.group_version.as_str(), typ.kind.as_str()) {
+        ("v1", "Pod") => ObjType::Pod { containers: vec![] },
+        ("v1", "Node") => ObjType::Node,
+        ("v1", "Service") => ObjType::Service,
+        ("v1", "ConfigMap") => ObjType::ConfigMap,
+        ("v1", "Secret") => ObjType::Secret,
+        ("v1", "Namespace") => ObjType::Namespace,
+        ("v1", "PersistentVolume") => ObjType::PersistentVolume,
+        ("apps/v1", "Deployment") => ObjType::Deployment,
+        ("apps/v1", "ReplicaSet") => ObjType::ReplicaSet,
+        ("apps/v1", "StatefulSet") => ObjType::StatefulSet,
+        ("apps/v1", "DaemonSet") => ObjType::DaemonSet,
+        ("batch/v1", "CronJob") => ObjType::CronJob,
+        ("batch/v1", "Job") => ObjType::Job,
+        ("storage.k8s.io/v1", "StorageClass") => ObjType::StorageClass,
+        #[cfg(feature = "argorollouts")]
+        ("argoproj.io/v1alpha1", "Rollout") => ObjType::Rollout,
+        _ => ObjType::Crd {
+            _type: typ.name.clone(),
+            group_version: typ.group_version.clone(),
+        },
+    }
+}
+
+/// List every object of type typ. Pods are listed in full, since metadata-only lists don't include
+/// the containers that commands like logs need.
+fn list_objects(env: &Env, typ: &ListableType) -> Result<Vec<(KObj, ObjectMeta)>, ClickError> {
+    match obj_type_for(typ) {
+        ObjType::Pod { .. } => {
+            let (request, _) = api::Pod::list_pod_for_all_namespaces(Default::default())?;
+            match env.run_on_context(|c| {
+                c.read::<ListResponse<api::Pod>>(env.get_impersonation(), request)
+            })? {
+                ListResponse::Ok(list) => Ok(list
+                    .items
+                    .into_iter()
+                    .map(|pod| (crate::command::pods::pod_to_kobj(&pod), pod.metadata))
+                    .collect()),
+                _ => Ok(vec![]),
+            }
+        }
+        obj_type => {
+            let (request, _) =
+                crate::crd::list_resource_metadata(&typ.name, &typ.group_version, None)?;
+            match env.run_on_context(|c| c.read(env.get_impersonation(), request))? {
+                ListMetadataResponse::Ok(list) => Ok(list
+                    .items
+                    .into_iter()
+                    .map(|item| {
+                        let kobj = KObj {
+                            name: item.metadata.name.clone().unwrap_or_default(),
+                            namespace: item.metadata.namespace.clone(),
+                            typ: obj_type.clone(),
+                            context: None,
+                        };
+                        (kobj, item.metadata)
+                    })
+                    .collect()),
+                _ => Ok(vec![]),
+            }
+        }
+    }
+}
+
+/// Search every type in types for objects whose name matches regex. Types the user isn't allowed
+/// to list don't get searched, and are added to forbidden.
+fn find_objects(
+    env: &Env,
+    types: &[ListableType],
+    regex: &Regex,
+    forbidden: &mut Vec<String>,
+) -> Result<Vec<(KObj, String, ObjectMeta)>, ClickError> {
+    let mut found = vec![];
+    for typ in types.iter() {
+        let objects = match list_objects(env, typ) {
+            Err(e) if is_forbidden(&e) => {
+                forbidden.push(typ.name.clone());
+                continue;
+            }
+            objects => objects?,
+        };
+        for (kobj, metadata) in objects.into_iter() {
+            if metadata
+                .name
+                .as_deref()
+                .is_some_and(|name| regex.is_match(name))
+            {
+                found.push((kobj, typ.kind.clone(), metadata));
+            }
+        }
+    }
+    Ok(found)
+}
+
+command!(
+    Find,
+    "find",
+    "Find objects of any type, in any namespace, whose name matches a regex",
+    |clap: ClapCommand<'static>| add_table_args(clap)
+        .arg(
+            Arg::new("regex")
+                .help("Find objects whose name matches this regex")
+                .required(true)
+                .index(1)
+        )
+        .arg(
+            Arg::new("kinds")
+                .short('k')
+                .long("kinds")
+                .help(
+                    "Only search these kinds of objects. A comma separated list of kinds, \
+                     resource names, or short names (i.e. pods,deploy,Service)"
+                )
+                .takes_value(true)
+        )
+        .arg(
+            Arg::new("all_contexts")
+                .long("all-contexts")
+                .help("Search all contexts, not just the current one")
+                .takes_value(false)
+                // the tables from each context are merged, which can't be exported yet
+                .conflicts_with("output")
+        ),
+    vec!["find"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let regex_str = matches.get_one::<String>("regex").unwrap(); // safe, required
+        if matches.contains_id("all_contexts") {
+            let contexts: Vec<String> = env.get_contexts().keys().cloned().collect();
+            let mut args = vec!["find".to_string(), regex_str.clone()];
+            if let Some(kinds) = matches.get_one::<String>("kinds") {
+                args.push("--kinds".to_string());
+                args.push(kinds.clone());
+            }
+            return crate::command::across::run_across(env, &contexts, &args, writer);
+        }
+
+        let regex = Regex::new(regex_str)
+            .map_err(|e| ClickError::CommandError(format!("Invalid regex: {e}")))?;
+        let kinds: Option<Vec<String>> = matches
+            .get_one::<String>("kinds")
+            .map(|kinds| kinds.split(',').map(|k| k.trim().to_string()).collect());
+        let mut forbidden = vec![];
+        let types = listable_types(env, kinds.as_deref(), &mut forbidden)?;
+        if types.is_empty() {
+            return Err(ClickError::CommandError(
+                "No listable types match the specified kinds".to_string(),
+            ));
+        }
+
+        let found = find_objects(env, &types, &regex, &mut forbidden)?;
+        let (kobjs, rows): (Vec<KObj>, Vec<Vec<CellSpec>>) = found
+            .into_iter()
+            .map(|(kobj, kind, meta)| {
+                let row = vec![
+                    CellSpec::new_index(),
+                    kind.into(),
+                    meta.namespace.into(),
+                    kobj.name.clone().into(),
+                    meta.creation_timestamp.map(|ts| ts.0).into(),
+                ];
+                (kobj, row)
+            })
+            .unzip();
+        let (table, export) = crate::table::print_or_export_table(
+            &["####", "Kind", "Namespace", "Name", "Age"],
+            rows,
+            &[],
+            TableOptions::from_matches(&matches),
+            env,
+            writer,
+        );
+        env.set_last_objs(kobjs, Some(table));
+        env.set_last_export(export);
+        if !forbidden.is_empty() {
+            clickwriteln!(
+                writer,
+                "Not searched, since you aren't allowed to list them: {}",
+                forbidden.join(", ")
+            );
+        }
+        Ok(())
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{self, env_for, StubResponse};
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_kind_matches() {
+        let resource = APIResource {
+            kind: "Deployment".to_string(),
+            name: "deployments".to_string(),
+            singular_name: "deployment".to_string(),
+            short_names: Some(vec!["deploy".to_string()]),
+            ..Default::default()
+        };
+        let kinds = |k: &[&str]| k.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert!(kind_matches(&resource, &kinds(&["deployment"])));
+        assert!(kind_matches(&resource, &kinds(&["pods", "Deployment"])));
+        assert!(kind_matches(&resource, &kinds(&["deployments"])));
+        assert!(kind_matches(&resource, &kinds(&["deploy"])));
+        assert!(!kind_matches(&resource, &kinds(&["pods", "svc"])));
+    }
+
+    #[test]
+    fn test_obj_type_for() {
+        let typ = |group_version: &str, name: &str, kind: &str| ListableType {
+            group_version: group_version.to_string(),
+            name: name.to_string(),
+            kind: kind.to_string(),
+        };
+        assert_eq!(
+            obj_type_for(&typ("apps/v1", "deployments", "Deployment")),
+            ObjType::Deployment
+        );
+        assert_eq!(
+            obj_type_for(&typ("v1", "serviceaccounts", "ServiceAccount")),
+            ObjType::Crd {
+                _type: "serviceaccounts".to_string(),
+                group_version: "v1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_skips_forbidden() {
+        let ok = |body: &str| StubResponse::new("200 OK", body);
+        let forbidden = || StubResponse::new("403 Forbidden", r#"{"message":"forbidden"}"#);
+        let resource = |name: &str, kind: &str| {
+            format!(
+                r#"{{"name":"{name}","singularName":"","namespaced":true,"kind":"{kind}",
+                    "verbs":["get","list"]}}"#
+            )
+        };
+        let (server, handle) = stub_server::serve(vec![
+            ok(
+                r#"{"groups":[{"name":"apps","versions":[{"groupVersion":"apps/v1",
+                "version":"v1"}]}]}"#,
+            ),
+            // core types can't be discovered
+            forbidden(),
+            ok(&format!(
+                r#"{{"groupVersion":"apps/v1","resources":[{},{}]}}"#,
+                resource("deployments", "Deployment"),
+                resource("statefulsets", "StatefulSet")
+            )),
+            forbidden(),
+            ok(r#"{"items":[{"metadata":{"name":"web","namespace":"dev"}},
+                {"metadata":{"name":"db","namespace":"dev"}}]}"#),
+        ]);
+        let dir = TempDir::new("click_find").unwrap();
+        let mut env = env_for(&server, &dir);
+        let mut writer = ClickWriter::with_buffer(vec![], false);
+        Find::new()
+            .exec(&mut env, &mut "we".split_whitespace(), &mut writer)
+            .unwrap();
+        let out = String::from_utf8(writer.finish_output().unwrap()).unwrap();
+        assert!(out.contains("StatefulSet"));
+        assert!(out.contains("web"));
+        assert!(!out.contains("db"));
+        assert!(
+            out.contains("Not searched, since you aren't allowed to list them: v1, deployments")
+        );
+        assert_eq!(handle.join().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_find_pods_and_events() {
+        let ok = |body: &str| StubResponse::new("200 OK", body);
+        let resource = |name: &str, kind: &str| {
+            format!(
+                r#"{{"name":"{name}","singularName":"","namespaced":true,"kind":"{kind}",
+                    "verbs":["get","list"]}}"#
+            )
+        };
+        let (server, handle) = stub_server::serve(vec![
+            ok(r#"{"groups":[{"name":"events.k8s.io","versions":[
+                {"groupVersion":"events.k8s.io/v1","version":"v1"}]}]}"#),
+            ok(&format!(
+                r#"{{"groupVersion":"v1","resources":[{},{}]}}"#,
+                resource("pods", "Pod"),
+                resource("events", "Event")
+            )),
+            ok(&format!(
+                r#"{{"groupVersion":"events.k8s.io/v1","resources":[{}]}}"#,
+                resource("events", "Event")
+            )),
+            ok(r#"{"items":[
+                {"metadata":{"name":"web-1","namespace":"dev"},
+                 "spec":{"containers":[{"name":"app"},{"name":"sidecar"}]}},
+                {"metadata":{"name":"db-1","namespace":"dev"},
+                 "spec":{"containers":[{"name":"db"}]}}]}"#),
+            ok(r#"{"items":[{"metadata":{"name":"web-1.a","namespace":"dev"}}]}"#),
+            ok(r#"{"items":[{"metadata":{"name":"web-1.b","namespace":"dev"}}]}"#),
+        ]);
+        let dir = TempDir::new("click_find").unwrap();
+        let mut env = env_for(&server, &dir);
+        let mut writer = ClickWriter::with_buffer(vec![], false);
+        Find::new()
+            .exec(&mut env, &mut "web".split_whitespace(), &mut writer)
+            .unwrap();
+        let out = String::from_utf8(writer.finish_output().unwrap()).unwrap();
+        assert!(out.contains("web-1.a"));
+        assert_eq!(
+            env.get_last_export().unwrap().rows[0][..3],
+            ["Pod", "dev", "web-1"]
+        );
+        assert!(out.contains("web-1.b"));
+        assert!(!out.contains("db-1"));
+
+        // pods come from one full list, so each has its containers without another request
+        let requests = handle.join().unwrap();
+        assert_eq!(requests.len(), 6);
+        assert!(requests[3].line.starts_with("GET /api/v1/pods?"));
+        let pod = (0..3)
+            .filter_map(|i| env.item_at(i))
+            .find(|obj| obj.name == "web-1")
+            .unwrap();
+        assert_eq!(
+            pod.typ,
+            ObjType::Pod {
+                containers: vec!["app".to_string(), "sidecar".to_string()]
+            }
+        );
+    }
+}
@@ -17,7 +17,7 @@ use k8s_openapi::api::batch::v1 as batch_api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{keyval_string, run_list_command, time_since, Extractor},
+    command::{keyval_string, run_list_command, time_since, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -40,20 +40,22 @@ lazy_static! {
         m
     };
 }
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("completions", "Completions"),
-    ("duration", "Duration"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("completions", "Completions").priority(6),
+    Col::new("duration", "Duration").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[
-    ("containers", "Containers"),
-    ("images", "Images"),
-    ("selector", "Selector"),
-    ("labels", "Labels"),
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("containers", "Containers")
+        .priority(3)
+        .truncate(30),
+    Col::new("images", "Images").priority(2).truncate(40),
+    Col::new("selector", "Selector").priority(2).truncate(40),
+    Col::new("labels", "Labels").priority(1).truncate(40),
 ];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
@@ -64,6 +66,7 @@ fn job_to_kobj(job: &batch_api::Job) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::StatefulSet,
+        context: None,
     }
 }
 
@@ -178,7 +181,7 @@ list_command!(
             Some(ns) => batch_api::Job::list_namespaced_job(ns, Default::default())?,
             None => batch_api::Job::list_job_for_all_namespaces(Default::default())?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
@@ -0,0 +1,881 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use base64::engine::{general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use clap::{Arg, ArgMatches, Command as ClapCommand};
+use k8s_openapi::api::authentication::v1::{TokenRequest, TokenRequestSpec};
+use k8s_openapi::{CreateResponse, GetCodeVersionResponse};
+use rustyline::completion::Pair as RustlinePair;
+use serde_yaml::Mapping;
+
+use crate::{
+    command::command_def::{add_export_args, exec_match, start_clap, try_complete, Cmd},
+    completer,
+    config::{inline_files, overall_status, CheckStatus, Finding, KubeconfigFile, Section},
+    env::Env,
+    error::ClickError,
+    output::ClickWriter,
+    table::{CellSpec, ColorType, TableOptions},
+    values::val_str,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+
+const SUBCOMMANDS: [&str; 7] = [
+    "add-context",
+    "check",
+    "delete",
+    "merge",
+    "rename",
+    "set-credentials",
+    "set-namespace",
+];
+
+// credential flags for set-credentials, and the kubeconfig field each one sets
+const CREDENTIAL_FIELDS: [(&str, &str); 5] = [
+    ("token", "token"),
+    ("client-certificate", "client-certificate"),
+    ("client-key", "client-key"),
+    ("username", "username"),
+    ("password", "password"),
+];
+
+fn subcommand_complete(prefix: &str, _env: &Env) -> Vec<RustlinePair> {
+    try_complete(prefix, &SUBCOMMANDS, false)
+}
+
+fn context_arg(help: &'static str) -> Arg<'static> {
+    Arg::new("context").help(help).required(true).index(1)
+}
+
+// the file an existing entry was read from
+fn source_of<'a>(env: &'a Env, section: Section, name: &str) -> Result<&'a str, ClickError> {
+    let sources = match section {
+        Section::Clusters => &env.config.sources.clusters,
+        Section::Contexts => &env.config.sources.contexts,
+        Section::Users => &env.config.sources.users,
+    };
+    sources.get(name).map(|s| s.as_str()).ok_or_else(|| {
+        let exists = match section {
+            Section::Clusters => env.config.clusters.contains_key(name),
+            Section::Contexts => env.config.contexts.contains_key(name),
+            Section::Users => env.config.users.contains_key(name),
+        };
+        if exists {
+            ClickError::CommandError(format!(
+                "{name} was not loaded from a kubeconfig file, so it can't be changed"
+            ))
+        } else {
+            ClickError::CommandError(format!("Unknown {}: {name}", section.singular()))
+        }
+    })
+}
+
+// the file new entries are added to
+fn primary_file(env: &Env) -> Result<&str, ClickError> {
+    env.config.sources.primary_file().ok_or_else(|| {
+        ClickError::CommandError("No kubeconfig file is loaded to add entries to".to_string())
+    })
+}
+
+// load path, make a change to it, save it, and then load the changed config. context is the context
+// that should be current afterwards.
+fn edit<F>(env: &mut Env, path: &str, context: Option<&str>, change: F) -> Result<(), ClickError>
+where
+    F: FnOnce(&mut KubeconfigFile) -> Result<(), ClickError>,
+{
+    let mut file = KubeconfigFile::load(path)?;
+    change(&mut file)?;
+    file.save()?;
+    let config = env.config.reload()?;
+    env.replace_config(config, context);
+    Ok(())
+}
+
+fn current_context(env: &Env) -> Option<String> {
+    env.context.as_ref().map(|c| c.name.clone())
+}
+
+fn set_namespace(
+    matches: &ArgMatches,
+    env: &mut Env,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let context = matches.get_one::<String>("context").unwrap(); // safe, required
+    let namespace = matches.get_one::<String>("namespace").map(|s| s.as_str());
+    let path = source_of(env, Section::Contexts, context)?.to_string();
+    let current = current_context(env);
+    edit(env, &path, current.as_deref(), |file| {
+        file.set_namespace(context, namespace)
+    })?;
+    match namespace {
+        Some(namespace) => clickwriteln!(
+            writer,
+            "Set default namespace of {context} to {namespace} in {path}"
+        ),
+        None => clickwriteln!(writer, "Removed default namespace of {context} in {path}"),
+    }
+    Ok(())
+}
+
+fn rename(matches: &ArgMatches, env: &mut Env, writer: &mut ClickWriter) -> Result<(), ClickError> {
+    let old = matches.get_one::<String>("context").unwrap(); // safe, required
+    let new = matches.get_one::<String>("new").unwrap(); // safe, required
+    if env.config.contexts.contains_key(new) {
+        return Err(ClickError::CommandError(format!(
+            "A context named {new} already exists"
+        )));
+    }
+    let path = source_of(env, Section::Contexts, old)?.to_string();
+    let current = current_context(env).map(|c| if &c == old { new.clone() } else { c });
+    edit(env, &path, current.as_deref(), |file| {
+        file.rename_context(old, new)
+    })?;
+    clickwriteln!(writer, "Renamed context {old} to {new} in {path}");
+    Ok(())
+}
+
+fn delete(matches: &ArgMatches, env: &mut Env, writer: &mut ClickWriter) -> Result<(), ClickError> {
+    let context = matches.get_one::<String>("context").unwrap(); // safe, required
+    let path = source_of(env, Section::Contexts, context)?.to_string();
+    let current = current_context(env).filter(|c| c != context);
+    edit(env, &path, current.as_deref(), |file| {
+        file.delete_context(context)
+    })?;
+    clickwriteln!(writer, "Deleted context {context} from {path}");
+    Ok(())
+}
+
+fn add_context(
+    matches: &ArgMatches,
+    env: &mut Env,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let name = matches.get_one::<String>("name").unwrap(); // safe, required
+    let cluster = matches.get_one::<String>("cluster").unwrap(); // safe, required
+    let user = matches.get_one::<String>("user").unwrap(); // safe, required
+    let namespace = matches.get_one::<String>("namespace").map(|s| s.as_str());
+    if env.config.contexts.contains_key(name) {
+        return Err(ClickError::CommandError(format!(
+            "A context named {name} already exists"
+        )));
+    }
+    if !env.config.clusters.contains_key(cluster) {
+        return Err(ClickError::CommandError(format!(
+            "Unknown cluster: {cluster}"
+        )));
+    }
+    if !env.config.users.contains_key(user) {
+        return Err(ClickError::CommandError(format!("Unknown user: {user}")));
+    }
+    let path = primary_file(env)?.to_string();
+    let current = current_context(env);
+    edit(env, &path, current.as_deref(), |file| {
+        file.add_context(name, cluster, user, namespace)
+    })?;
+    clickwriteln!(writer, "Added context {name} to {path}");
+    Ok(())
+}
+
+fn set_credentials(
+    matches: &ArgMatches,
+    env: &mut Env,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let user = matches.get_one::<String>("user").unwrap(); // safe, required
+    let fields: Vec<(&str, &str)> = CREDENTIAL_FIELDS
+        .iter()
+        .filter_map(|(flag, field)| {
+            matches
+                .get_one::<String>(flag)
+                .map(|value| (*field, value.as_str()))
+        })
+        .collect();
+    if fields.is_empty() {
+        return Err(ClickError::CommandError(
+            "Nothing to set, specify at least one credential".to_string(),
+        ));
+    }
+    let path = if env.config.users.contains_key(user) {
+        source_of(env, Section::Users, user)?
+    } else {
+        primary_file(env)?
+    }
+    .to_string();
+    let current = current_context(env);
+    edit(env, &path, current.as_deref(), |file| {
+        file.set_credentials(user, &fields)
+    })?;
+    clickwriteln!(writer, "Set credentials for {user} in {path}");
+    Ok(())
+}
+
+fn merge(matches: &ArgMatches, env: &mut Env, writer: &mut ClickWriter) -> Result<(), ClickError> {
+    let other_path = matches.get_one::<String>("file").unwrap(); // safe, required
+    let other = KubeconfigFile::load(other_path)?;
+    let path = primary_file(env)?.to_string();
+    let mut file = KubeconfigFile::load(&path)?;
+    // names loaded from any file are taken, not just names in the file we're changing
+    let result = file.merge(&other, |section, name| match section {
+        Section::Clusters => env.config.clusters.contains_key(name),
+        Section::Contexts => env.config.contexts.contains_key(name),
+        Section::Users => env.config.users.contains_key(name),
+    })?;
+    if result.added.is_empty() {
+        clickwriteln!(writer, "Nothing to merge from {other_path}");
+    } else {
+        file.save()?;
+        let config = env.config.reload()?;
+        let current = current_context(env);
+        env.replace_config(config, current.as_deref());
+        clickwriteln!(writer, "Added to {path}:\n  {}", result.added.join("\n  "));
+    }
+    if !result.skipped.is_empty() {
+        clickwriteln!(
+            writer,
+            "Skipped, as the names already exist:\n  {}",
+            result.skipped.join("\n  ")
+        );
+    }
+    if !result.conflicts.is_empty() {
+        clickwriteln!(
+            writer,
+            "Not added, rename the cluster or user in {other_path} to merge them:\n  {}",
+            result.conflicts.join("\n  ")
+        );
+    }
+    Ok(())
+}
+
+// see if the server for context answers, and what version it is
+fn probe_version(env: &Env, context: &str) -> Finding {
+    let result = env
+        .config
+        .get_context(context, &env.click_config)
+        .and_then(|ctx| {
+            let (request, _) = k8s_openapi::get_code_version()?;
+            ctx.read::<GetCodeVersionResponse>(env.get_impersonation(), request)
+        });
+    match result {
+        Ok(GetCodeVersionResponse::Ok(info)) => Finding::new(
+            CheckStatus::Pass,
+            format!("Server version {}", info.git_version),
+        ),
+        Ok(GetCodeVersionResponse::Other(_)) => Finding::new(
+            CheckStatus::Fail,
+            "Server did not return its version".to_string(),
+        ),
+        Err(e) => Finding::new(CheckStatus::Fail, format!("Could not reach server: {e}")),
+    }
+}
+
+fn check(matches: &ArgMatches, env: &mut Env, writer: &mut ClickWriter) -> Result<(), ClickError> {
+    let probe = matches.contains_id("probe");
+    let now = Utc::now();
+    let mut failed = 0;
+    let rows = env
+        .get_contexts()
+        .keys()
+        .map(|context| {
+            let mut findings = env.config.check_context(context, now);
+            // no point in trying to talk to a cluster we already know we can't
+            if probe && overall_status(&findings) != CheckStatus::Fail {
+                findings.push(probe_version(env, context));
+            }
+            let status = overall_status(&findings);
+            let color = match status {
+                CheckStatus::Pass => ColorType::Success,
+                CheckStatus::Warn => ColorType::Warn,
+                CheckStatus::Fail => {
+                    failed += 1;
+                    ColorType::Danger
+                }
+            };
+            let details: Vec<&str> = findings.iter().map(|f| f.message.as_str()).collect();
+            vec![
+                context.as_str().into(),
+                CellSpec::with_colors(status.as_str().into(), Some(color.into()), None),
+                details.join("\n").into(),
+            ]
+        })
+        .collect();
+    crate::table::print_or_export_table(
+        &["Context", "Status", "Details"],
+        rows,
+        &[],
+        TableOptions::from_matches(matches),
+        env,
+        writer,
+    );
+    if failed > 0 {
+        clickwriteln!(writer, "{failed} context(s) failed checks");
+    }
+    Ok(())
+}
+
+command!(
+    ConfigCmd,
+    "config",
+    "Change the kubeconfig files click loaded",
+    |clap: ClapCommand<'static>| {
+        clap.subcommand_required(true)
+            .arg_required_else_help(true)
+            .subcommand(
+                ClapCommand::new("set-namespace")
+                    .about("Set the default namespace of a context")
+                    .arg(context_arg("The context to change"))
+                    .arg(
+                        Arg::new("namespace")
+                            .help("The namespace, leave out to remove the default")
+                            .required(false)
+                            .index(2),
+                    ),
+            )
+            .subcommand(
+                ClapCommand::new("rename")
+                    .about("Rename a context")
+                    .arg(context_arg("The context to rename"))
+                    .arg(Arg::new("new").help("The new name").required(true).index(2)),
+            )
+            .subcommand(
+                ClapCommand::new("delete")
+                    .about("Delete a context (the cluster and user it uses are kept)")
+                    .arg(context_arg("The context to delete")),
+            )
+            .subcommand(
+                ClapCommand::new("add-context")
+                    .about("Add a context for an existing cluster and user")
+                    .arg(
+                        Arg::new("name")
+                            .help("The name of the new context")
+                            .required(true)
+                            .index(1),
+                    )
+                    .arg(
+                        Arg::new("cluster")
+                            .long("cluster")
+                            .help("The cluster the context uses")
+                            .required(true)
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::new("user")
+                            .long("user")
+                            .help("The user the context uses")
+                            .required(true)
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::new("namespace")
+                            .short('n')
+                            .long("namespace")
+                            .help("The default namespace of the context")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(
+                CREDENTIAL_FIELDS.iter().fold(
+                    ClapCommand::new("set-credentials")
+                        .about("Set credentials of a user, adding the user if it doesn't exist")
+                        .arg(
+                            Arg::new("user")
+                                .help("The user to change")
+                                .required(true)
+                                .index(1),
+                        ),
+                    |cmd, (flag, _)| {
+                        cmd.arg(
+                            Arg::new(*flag)
+                                .long(flag)
+                                .help("Value to set")
+                                .takes_value(true),
+                        )
+                    },
+                ),
+            )
+            .subcommand(add_export_args(
+                ClapCommand::new("check")
+                    .about(
+                        "Check every context for missing clusters and users, unreadable or \
+                         expiring certificates, and exec plugins that aren't installed",
+                    )
+                    .arg(
+                        Arg::new("probe")
+                            .long("probe")
+                            .help(
+                                "Also ask each server for its version, to see if it can be reached",
+                            )
+                            .takes_value(false),
+                    ),
+            ))
+            .subcommand(
+                ClapCommand::new("merge")
+                    .about("Add the clusters, users and contexts from another kubeconfig file")
+                    .arg(
+                        Arg::new("file")
+                            .help("The kubeconfig file to merge in")
+                            .required(true)
+                            .index(1),
+                    ),
+            )
+            .after_help(
+                "Changes are written to the kubeconfig file the context or user was loaded from. \
+                 New contexts and users, and merged entries, go in the first kubeconfig file.
+
+Examples:
+  # make kube-system the default namespace for the prod context
+  config set-namespace prod kube-system
+
+  # add a context that uses the staging cluster as the admin user
+  config add-context staging-admin --cluster staging --user admin",
+            )
+    },
+    vec!["config"],
+    vec![&subcommand_complete, &completer::context_complete],
+    no_named_complete!(),
+    |matches, env, writer| {
+        match matches.subcommand() {
+            Some(("set-namespace", sub)) => set_namespace(sub, env, writer),
+            Some(("rename", sub)) => rename(sub, env, writer),
+            Some(("delete", sub)) => delete(sub, env, writer),
+            Some(("add-context", sub)) => add_context(sub, env, writer),
+            Some(("set-credentials", sub)) => set_credentials(sub, env, writer),
+            Some(("merge", sub)) => merge(sub, env, writer),
+            Some(("check", sub)) => check(sub, env, writer),
+            _ => unreachable!(), // clap requires one of the above
+        }
+    }
+);
+
+// the settings of the cluster named cluster, from the file it was loaded from if there is one, or
+// else from what click parsed (i.e. the in-cluster context)
+fn cluster_settings(env: &Env, cluster: &str) -> Result<Mapping, ClickError> {
+    if let Some(path) = env.config.sources.clusters.get(cluster) {
+        if let Some(settings) = KubeconfigFile::load(path)?.settings(Section::Clusters, cluster) {
+            return Ok(settings.clone());
+        }
+    }
+    let conf = env
+        .config
+        .clusters
+        .get(cluster)
+        .ok_or_else(|| ClickError::CommandError(format!("Unknown cluster: {cluster}")))?;
+    let mut settings = Mapping::new();
+    settings.insert("server".into(), conf.server.as_str().into());
+    if let Some(cert) = conf.cert.as_ref() {
+        settings.insert(
+            "certificate-authority-data".into(),
+            STANDARD.encode(cert).into(),
+        );
+    }
+    if let Some(tls_server_name) = conf.tls_server_name.as_ref() {
+        settings.insert("tls-server-name".into(), tls_server_name.as_str().into());
+    }
+    if conf.insecure_skip_tls_verify {
+        settings.insert("insecure-skip-tls-verify".into(), true.into());
+    }
+    if let Some(proxy_url) = conf.proxy_url.as_ref() {
+        settings.insert("proxy-url".into(), proxy_url.as_str().into());
+    }
+    Ok(settings)
+}
+
+fn user_settings(env: &Env, user: &str) -> Result<Mapping, ClickError> {
+    let path = source_of(env, Section::Users, user).map_err(|_| {
+        ClickError::CommandError(format!(
+            "User {user} was not loaded from a kubeconfig file, use --sa to export a service \
+             account token instead"
+        ))
+    })?;
+    KubeconfigFile::load(path)?
+        .settings(Section::Users, user)
+        .cloned()
+        .ok_or_else(|| ClickError::CommandError(format!("User {user} is not in {path}")))
+}
+
+/// Get a token for a service account through the TokenRequest api
+fn request_token(
+    env: &Env,
+    namespace: &str,
+    service_account: &str,
+    duration: &Duration,
+) -> Result<String, ClickError> {
+    let body = TokenRequest {
+        spec: TokenRequestSpec {
+            expiration_seconds: Some(duration.as_secs() as i64),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (request, _) = TokenRequest::create_namespaced_service_account_token(
+        service_account,
+        namespace,
+        &body,
+        Default::default(),
+    )?;
+    match env.run_on_context::<_, CreateResponse<TokenRequest>>(|c| {
+        c.read(env.get_impersonation(), request)
+    })? {
+        CreateResponse::Ok(tr) | CreateResponse::Created(tr) | CreateResponse::Accepted(tr) => tr
+            .status
+            .map(|status| status.token)
+            .ok_or_else(|| ClickError::CommandError("Token request returned no token".to_string())),
+        CreateResponse::Other(Ok(Some(val))) => Err(ClickError::CommandError(format!(
+            "Token request failed. Message: {}",
+            val_str("/message", &val, "<No message>")
+        ))),
+        CreateResponse::Other(Ok(None)) => Err(ClickError::CommandError(
+            "Token request failed with no reason given".to_string(),
+        )),
+        CreateResponse::Other(Err(e)) => Err(ClickError::CommandError(format!(
+            "Token request failed with an error: {e}"
+        ))),
+    }
+}
+
+command!(
+    ExportKubeconfig,
+    "export-kubeconfig",
+    "Print a kubeconfig with just the current context, i.e. to hand to a CI job",
+    |clap: ClapCommand<'static>| {
+        clap.arg(
+            Arg::new("flatten")
+                .long("flatten")
+                .help("Include the contents of certificate, key and token files, not their paths")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("sa")
+                .long("sa")
+                .help(
+                    "Use a short-lived token for this service account (in the current \
+                     namespace) instead of your credentials",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("duration")
+                .long("duration")
+                .help("How long the service account token is valid for")
+                .requires("sa")
+                .takes_value(true)
+                .value_parser(humantime::parse_duration)
+                .default_value("1h"),
+        )
+        .after_help(
+            "Without --flatten, the exported config refers to certificate, key and token files by \
+             their full path, so it only works where those files exist.
+
+Examples:
+  # save a config for the current context
+  export-kubeconfig --flatten > ci.kubeconfig
+
+  # a config that can act as the deployer service account for two hours
+  export-kubeconfig --sa deployer --duration 2h > deployer.kubeconfig",
+        )
+    },
+    vec!["export-kubeconfig"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let context = env
+            .context
+            .as_ref()
+            .map(|c| c.name.clone())
+            .ok_or_else(|| ClickError::CommandError("Need an active context".to_string()))?;
+        let conf = env.config.contexts.get(&context).cloned().ok_or_else(|| {
+            ClickError::CommandError(format!("Context {context} is not in the kubeconfig"))
+        })?;
+        let flatten = matches.contains_id("flatten");
+
+        let mut cluster = cluster_settings(env, &conf.cluster)?;
+        inline_files(Section::Clusters, &mut cluster, flatten)?;
+
+        let (user_name, user, namespace) = match matches.get_one::<String>("sa") {
+            Some(sa) => {
+                let namespace = env.namespace.clone().ok_or_else(|| {
+                    ClickError::CommandError(
+                        "Need an active namespace to find the service account in".to_string(),
+                    )
+                })?;
+                let duration = matches.get_one::<Duration>("duration").unwrap(); // safe, has default
+                let token = request_token(env, &namespace, sa, duration)?;
+                let mut user = Mapping::new();
+                user.insert("token".into(), token.into());
+                (sa.clone(), user, Some(namespace))
+            }
+            None => {
+                let mut user = user_settings(env, &conf.user)?;
+                inline_files(Section::Users, &mut user, flatten)?;
+                (conf.user.clone(), user, conf.namespace.clone())
+            }
+        };
+
+        let file = KubeconfigFile::single_context(
+            "exported kubeconfig",
+            &context,
+            namespace.as_deref(),
+            (&conf.cluster, cluster),
+            (&user_name, user),
+        )?;
+        clickwrite!(writer, "{}", file.to_yaml()?);
+        Ok(())
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ClickConfig, Config};
+    use crate::env::ObjectSelection;
+    use crate::kobj::{KObj, ObjType};
+    use crate::stub_server::{self, StubResponse};
+    use std::fs;
+    use tempdir::TempDir;
+
+    static FIRST: &str = "apiVersion: v1
+kind: Config
+clusters:
+- name: prod
+  cluster:
+    server: https://prod.example.com
+contexts:
+- name: prod
+  context:
+    cluster: prod
+    user: admin
+users:
+- name: admin
+  user:
+    token: abc
+";
+
+    static SECOND: &str = "apiVersion: v1
+kind: Config
+clusters: []
+contexts:
+- name: staging
+  context:
+    cluster: prod
+    user: admin
+    extensions:
+    - name: kept
+users: []
+";
+
+    fn run_cmd(cmd: &dyn Cmd, env: &mut Env, args: &str) -> Result<String, ClickError> {
+        let mut writer = ClickWriter::with_buffer(vec![], false);
+        cmd.exec(env, &mut args.split_whitespace(), &mut writer)?;
+        Ok(String::from_utf8(writer.finish_output().unwrap()).unwrap())
+    }
+
+    fn run(env: &mut Env, args: &str) -> Result<String, ClickError> {
+        run_cmd(&ConfigCmd::new(), env, args)
+    }
+
+    #[test]
+    fn test_writes_to_source_file() {
+        let dir = TempDir::new("click_kubeconfig_cmd").unwrap();
+        let first = dir.path().join("first").to_str().unwrap().to_string();
+        let second = dir.path().join("second").to_str().unwrap().to_string();
+        fs::write(&first, FIRST).unwrap();
+        fs::write(&second, SECOND).unwrap();
+        let config = Config::from_files(&[first.clone(), second.clone()]).unwrap();
+        let mut env = Env::new(
+            config,
+            ClickConfig::default(),
+            dir.path().join("click.conf"),
+        );
+        env.set_context(Some("prod"));
+        env.set_namespace(Some("apps"));
+        let web = KObj {
+            name: "web".to_string(),
+            namespace: Some("apps".to_string()),
+            typ: ObjType::Service,
+            context: None,
+        };
+        env.set_last_objs(vec![web.clone()], None);
+        env.set_current(0);
+
+        run(&mut env, "set-namespace staging kube-system").unwrap();
+        // editing another context leaves the current one as it was
+        assert_eq!(env.namespace.as_deref(), Some("apps"));
+        assert_eq!(env.current_selection(), &ObjectSelection::Single(web));
+        assert_eq!(fs::read_to_string(&first).unwrap(), FIRST);
+        assert_eq!(
+            fs::read_to_string(&second).unwrap(),
+            SECOND.replace(
+                "    - name: kept\n",
+                "    - name: kept\n    namespace: kube-system\n"
+            )
+        );
+        assert_eq!(
+            env.config.contexts["staging"].namespace.as_deref(),
+            Some("kube-system")
+        );
+
+        run(&mut env, "rename prod prod-old").unwrap();
+        assert_eq!(env.current_selection(), &ObjectSelection::None);
+        assert!(fs::read_to_string(&first)
+            .unwrap()
+            .contains("- name: prod-old"));
+        assert_eq!(env.context.as_ref().unwrap().name, "prod-old");
+        assert!(run(&mut env, "rename staging prod-old").is_err());
+
+        run(&mut env, "add-context new --cluster prod --user admin").unwrap();
+        assert_eq!(env.config.sources.contexts["new"], first);
+        assert!(run(&mut env, "add-context other --cluster nope --user admin").is_err());
+
+        run(&mut env, "delete prod-old").unwrap();
+        assert!(!env.config.contexts.contains_key("prod-old"));
+        assert!(env.context.is_none());
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = TempDir::new("click_kubeconfig_cmd").unwrap();
+        let first = dir.path().join("first").to_str().unwrap().to_string();
+        let second = dir.path().join("second").to_str().unwrap().to_string();
+        fs::write(&first, FIRST).unwrap();
+        fs::write(&second, SECOND).unwrap();
+        let config = Config::from_files(&[first, second]).unwrap();
+        let mut env = Env::new(
+            config,
+            ClickConfig::default(),
+            dir.path().join("click.conf"),
+        );
+        let export = ExportKubeconfig::new();
+        assert!(run_cmd(&export, &mut env, "").is_err());
+
+        env.set_context(Some("staging"));
+        assert_eq!(
+            run_cmd(&export, &mut env, "--flatten").unwrap(),
+            "apiVersion: v1
+kind: Config
+clusters:
+- name: prod
+  cluster:
+    server: https://prod.example.com
+contexts:
+- name: staging
+  context:
+    cluster: prod
+    user: admin
+users:
+- name: admin
+  user:
+    token: abc
+current-context: staging
+"
+        );
+        // a service account token needs to know the namespace
+        env.set_namespace(None);
+        assert!(run_cmd(&export, &mut env, "--sa deployer").is_err());
+        assert!(run_cmd(&export, &mut env, "--duration 2h").is_err());
+    }
+
+    #[test]
+    fn test_export_service_account() {
+        let (server, handle) = stub_server::serve(vec![StubResponse::new(
+            "201 Created",
+            r#"{"apiVersion":"authentication.k8s.io/v1","kind":"TokenRequest",
+                "spec":{"audiences":["https://kubernetes.default.svc"],"expirationSeconds":7200},
+                "status":{"token":"sa-token","expirationTimestamp":"2030-01-01T00:00:00Z"}}"#,
+        )]);
+        let dir = TempDir::new("click_kubeconfig_cmd").unwrap();
+        let path = dir.path().join("config").to_str().unwrap().to_string();
+        fs::write(
+            &path,
+            FIRST.replace("https://prod.example.com", server.as_str()),
+        )
+        .unwrap();
+        let config = Config::from_files(&[path]).unwrap();
+        let mut env = Env::new(
+            config,
+            ClickConfig::default(),
+            dir.path().join("click.conf"),
+        );
+        env.set_context(Some("prod"));
+        env.set_namespace(Some("ci"));
+
+        let out = run_cmd(
+            &ExportKubeconfig::new(),
+            &mut env,
+            "--sa deployer --duration 2h",
+        )
+        .unwrap();
+        let requests = handle.join().unwrap();
+        assert!(requests[0]
+            .line
+            .starts_with("POST /api/v1/namespaces/ci/serviceaccounts/deployer/token"));
+        assert!(requests[0].body.contains(r#""expirationSeconds":7200"#));
+        assert_eq!(
+            out,
+            format!(
+                "apiVersion: v1
+kind: Config
+clusters:
+- name: prod
+  cluster:
+    server: {server}
+contexts:
+- name: prod
+  context:
+    cluster: prod
+    user: deployer
+    namespace: ci
+users:
+- name: deployer
+  user:
+    token: sa-token
+current-context: prod
+"
+            )
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let dir = TempDir::new("click_kubeconfig_cmd").unwrap();
+        let first = dir.path().join("first").to_str().unwrap().to_string();
+        fs::write(&first, FIRST.replace("user: admin\n", "user: nobody\n")).unwrap();
+        let config = Config::from_files(&[first]).unwrap();
+        let mut env = Env::new(
+            config,
+            ClickConfig::default(),
+            dir.path().join("click.conf"),
+        );
+        let out = run(&mut env, "check").unwrap();
+        assert!(out.contains("prod"));
+        assert!(out.contains("fail"));
+        assert!(out.contains("User nobody not found"));
+        assert!(out.ends_with("1 context(s) failed checks\n"));
+
+        let out = run(&mut env, "check -o csv --no-headers").unwrap();
+        assert_eq!(
+            out,
+            "prod,fail,User nobody not found\n1 context(s) failed checks\n"
+        );
+
+        let out = run_cmd(&crate::command::click::Contexts::new(), &mut env, "-o tsv").unwrap();
+        assert_eq!(
+            out,
+            "Context\tApi Server Address\nprod\thttps://prod.example.com\n"
+        );
+    }
+}
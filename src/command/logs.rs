@@ -90,7 +90,7 @@ fn do_logs<'a>(
         api::Pod::read_namespaced_pod_log(obj.name(), obj.namespace.as_ref().unwrap(), opts)?;
 
     let logs_reader_res =
-        env.run_on_context(|c| c.execute_reader(env.get_impersonate_user(), request, timeout));
+        env.run_on_context(|c| c.execute_reader(env.get_impersonation(), request, timeout));
     match logs_reader_res {
         Ok(lreader) => {
             let mut reader = BufReader::new(lreader);
@@ -314,6 +314,8 @@ command!(
 
         if matches.contains_id("follow") {
             opts.follow = Some(true);
+            // followed logs never end, so they can't be paged
+            writer.stop_paging();
         }
         k8s_if_ge_1_17! {
             if matches.contains_id("insecure") {
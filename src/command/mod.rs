@@ -21,13 +21,15 @@ use k8s_openapi::{
     List, ListOptional, ListResponse, ListableResource, Metadata, RequestError, ResponseBody,
 };
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
+use crate::config::{View, ViewColumn};
 use crate::env::Env;
 use crate::error::ClickError;
 use crate::kobj::KObj;
 use crate::output::ClickWriter;
-use crate::table::CellSpec;
+use crate::table::{CellSpec, ColumnFit, TableOptions};
 
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
@@ -37,7 +39,9 @@ use std::io::{stderr, Write};
 #[macro_use]
 pub mod command_def;
 
+pub mod across; // command to run other commands against multiple contexts
 pub mod alias; // commands for alias/unalias
+pub mod auth; // commands to inspect who you are and what you can do
 pub mod click; // commands internal to click (setting config values, etc)
 pub mod configmaps; // commands relating to configmaps
 pub mod copy; // command to copy files to/from pods
@@ -49,10 +53,13 @@ pub mod deployments; // command to list deployments
 pub mod describe; // the describe command
 pub mod events; // commands to print events
 pub mod exec; // command to exec into pods
+pub mod find; // command to find objects of any type by name
 pub mod jobs; // commands relating to jobs
+pub mod kubeconfig; // commands to change and export kubeconfig files
 pub mod logs; // command to get pod logs
 pub mod namespaces; // commands relating to namespaces
 pub mod nodes; // commands relating to nodes
+pub mod pick; // fuzzy finder to select objects from the last list
 pub mod pods; //commands relating to pods
 pub mod portforwards; // commands for forwarding ports
 pub mod replicasets; // commands relating to relicasets
@@ -60,6 +67,7 @@ pub mod secrets; // commands for secrets
 pub mod services; // commands for services
 pub mod statefulsets; // commands for statefulsets
 pub mod storage; // commands relating to storage objects (like storageclass)
+pub mod ui; // full screen mode to browse objects
 pub mod volumes; // commands relating to volumes
 
 #[cfg(feature = "argorollouts")]
@@ -69,13 +77,134 @@ pub mod rollouts;
 type RowSpec<'a> = Vec<CellSpec<'a>>;
 type Extractor<T> = fn(&T) -> Option<CellSpec<'_>>;
 
-fn mapped_val(key: &str, map: &[(&'static str, &'static str)]) -> Option<&'static str> {
-    for (map_key, val) in map.iter() {
-        if &key == map_key {
-            return Some(val);
+/// A column a list command can show. `flag` is what's passed to --show/--sort, and `title` is the
+/// header in the output
+pub struct Col {
+    pub flag: &'static str,
+    pub title: &'static str,
+    pub fit: ColumnFit,
+}
+
+impl Col {
+    /// A column that is never dropped or truncated to fit the terminal
+    pub const fn new(flag: &'static str, title: &'static str) -> Col {
+        Col {
+            flag,
+            title,
+            fit: ColumnFit::KEEP,
+        }
+    }
+
+    /// Allow dropping this column if the table doesn't fit the terminal. Lower priority columns
+    /// are dropped first
+    pub const fn priority(self, priority: u8) -> Col {
+        Col {
+            fit: ColumnFit {
+                priority,
+                max_width: self.fit.max_width,
+            },
+            ..self
+        }
+    }
+
+    /// Ellipsize cells wider than width if the table doesn't fit the terminal
+    pub const fn truncate(self, width: usize) -> Col {
+        Col {
+            fit: ColumnFit {
+                priority: self.fit.priority,
+                max_width: Some(width),
+            },
+            ..self
         }
     }
-    None
+}
+
+fn mapped_val(key: &str, map: &[Col]) -> Option<&'static str> {
+    map.iter().find(|col| col.flag == key).map(|col| col.title)
+}
+
+// the fit for each of cols, looked up by title
+fn col_fits(cols: &[&str], col_map: &[Col], extra_col_map: Option<&[Col]>) -> Vec<ColumnFit> {
+    cols.iter()
+        .map(|title| {
+            col_map
+                .iter()
+                .chain(extra_col_map.unwrap_or_default().iter())
+                .find(|col| col.title == *title)
+                .map(|col| col.fit)
+                .unwrap_or(ColumnFit::KEEP)
+        })
+        .collect()
+}
+
+/// The view to use for a list command. This is the view passed as @name, or the default view for
+/// the listed type if none was passed (and --no-view wasn't specified)
+fn get_list_view(
+    matches: &ArgMatches,
+    env: &Env,
+    resource: &str,
+) -> Result<Option<View>, ClickError> {
+    let view = match matches.try_get_one::<String>("view") {
+        Ok(Some(name)) => match name.strip_prefix('@') {
+            Some(name) => Some(env.click_config.get_view(name, resource)?),
+            None => {
+                return Err(ClickError::CommandError(format!(
+                    "Views are specified as @name, not '{name}'"
+                )))
+            }
+        },
+        _ if matches.try_contains_id("no-view").unwrap_or(false) => None,
+        _ => env.click_config.default_view(resource),
+    };
+    if let Some(column) = view
+        .iter()
+        .flat_map(|view| view.extra_columns.iter())
+        .find(|column| !column.pointer.starts_with('/'))
+    {
+        return Err(ClickError::CommandError(format!(
+            "Invalid pointer for view column {}: '{}' (pointers look like /status/phase)",
+            column.title, column.pointer
+        )));
+    }
+    Ok(view.cloned())
+}
+
+/// Add label and field selectors to a list request, combining them with any selectors the request
+/// already has
+fn add_selectors(
+    request: Request<Vec<u8>>,
+    label_selector: Option<&str>,
+    field_selector: Option<&str>,
+) -> Result<Request<Vec<u8>>, ClickError> {
+    if label_selector.is_none() && field_selector.is_none() {
+        return Ok(request);
+    }
+    let (mut parts, body) = request.into_parts();
+    let mut pairs: Vec<(String, String)> =
+        url::form_urlencoded::parse(parts.uri.query().unwrap_or("").as_bytes())
+            .into_owned()
+            .collect();
+    for (key, selector) in [
+        ("labelSelector", label_selector),
+        ("fieldSelector", field_selector),
+    ] {
+        if let Some(selector) = selector {
+            match pairs.iter_mut().find(|(k, _)| k == key) {
+                Some((_, existing)) => {
+                    existing.push(',');
+                    existing.push_str(selector);
+                }
+                None => pairs.push((key.to_string(), selector.to_string())),
+            }
+        }
+    }
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(pairs)
+        .finish();
+    parts.uri = format!("{}?{query}", parts.uri.path())
+        .parse()
+        .map_err(|e| ClickError::CommandError(format!("Invalid selector: {e}")))?;
+    Ok(Request::from_parts(parts, body))
 }
 
 #[allow(clippy::too_many_arguments)] // factoring this out into structs just makes it worse
@@ -85,13 +214,13 @@ pub fn run_list_command<T, F>(
     writer: &mut ClickWriter,
     mut cols: Vec<&str>,
     request: Request<Vec<u8>>,
-    col_map: &[(&'static str, &'static str)],
-    extra_col_map: Option<&[(&'static str, &'static str)]>,
+    col_map: &[Col],
+    extra_col_map: Option<&[Col]>,
     extractors: Option<&HashMap<String, Extractor<T>>>,
     get_kobj: F,
 ) -> Result<(), ClickError>
 where
-    T: ListableResource + Metadata<Ty = ObjectMeta> + for<'de> Deserialize<'de> + Debug,
+    T: ListableResource + Metadata<Ty = ObjectMeta> + for<'de> Deserialize<'de> + Serialize + Debug,
     F: Fn(&T) -> KObj,
 {
     let regex = match crate::table::get_regex(&matches) {
@@ -102,8 +231,25 @@ where
         }
     };
 
+    let view = get_list_view(&matches, env, T::URL_PATH_SEGMENT)?;
+    let regex =
+        match (regex, view.as_ref().and_then(|view| view.regex.as_ref())) {
+            (None, Some(pattern)) => Some(Regex::new(pattern).map_err(|_| {
+                ClickError::CommandError(format!("Invalid regex in view: {pattern}"))
+            })?),
+            (regex, _) => regex,
+        };
+    let request = match view.as_ref() {
+        Some(view) => add_selectors(
+            request,
+            view.label_selector.as_deref(),
+            view.field_selector.as_deref(),
+        )?,
+        None => request,
+    };
+
     let list_res =
-        env.run_on_context::<_, List<T>>(|c| c.execute_list(env.get_impersonate_user(), request));
+        env.run_on_context::<_, List<T>>(|c| c.execute_list(env.get_impersonation(), request));
     if list_res.is_err() {
         env.clear_last_objs();
     }
@@ -112,20 +258,44 @@ where
         Ok(Some(v)) => v.map(|s| s.as_str()).collect(),
         _ => vec![],
     };
+    let view_sort = match view.as_ref() {
+        Some(view) => {
+            let known = |flag: &str| {
+                mapped_val(flag, col_map).is_some()
+                    || extra_col_map
+                        .map(|ecm| mapped_val(flag, ecm).is_some())
+                        .unwrap_or(false)
+            };
+            let columns = view.columns.iter().map(|c| c.to_lowercase());
+            if let Some(unknown) = columns
+                .chain(view.sort.iter().map(|s| s.to_lowercase()))
+                .find(|flag| !known(flag) && flag != "all" && flag != "labels")
+            {
+                return Err(ClickError::CommandError(format!(
+                    "View has unknown column for {}: {unknown}",
+                    T::URL_PATH_SEGMENT
+                )));
+            }
+            flags.extend(view.columns.iter().map(|c| c.as_str()));
+            view.sort.as_deref()
+        }
+        None => None,
+    };
 
     let sort = matches
         .get_one::<String>("sort")
         .map(|s| s.as_str())
+        .or(view_sort)
         .map(|s| {
             let colname = s.to_lowercase();
             if let Some(col) = mapped_val(&colname, col_map) {
                 command_def::SortCol(col)
             } else if let Some(ecm) = extra_col_map {
                 let mut func = None;
-                for (flag, col) in ecm.iter() {
-                    if flag.eq(&colname) {
-                        flags.push(flag);
-                        func = Some(command_def::SortCol(col));
+                for col in ecm.iter() {
+                    if col.flag.eq(&colname) {
+                        flags.push(col.flag);
+                        func = Some(command_def::SortCol(col.title));
                     }
                 }
                 match func {
@@ -150,16 +320,30 @@ where
             ecm,
         );
     }
-
+    let view_cols: &[ViewColumn] = view
+        .as_ref()
+        .map(|view| view.extra_columns.as_slice())
+        .unwrap_or_default();
+    let cols: Vec<&str> = cols
+        .into_iter()
+        .chain(view_cols.iter().map(|col| col.title.as_str()))
+        .collect();
+
+    let fits = col_fits(&cols, col_map, extra_col_map);
+    let reverse =
+        matches.contains_id("reverse") || view.as_ref().map(|v| v.reverse).unwrap_or(false);
     handle_list_result(
         env,
         writer,
         cols,
+        &fits,
+        TableOptions::from_matches(&matches),
         list,
         extractors,
+        view_cols,
         regex,
         sort,
-        matches.contains_id("reverse"),
+        reverse,
         get_kobj,
     )
 }
@@ -182,25 +366,30 @@ pub fn uppercase_first(s: &str) -> String {
  * -- clears the env list if the built list was empty
  *
  * NB: This function assumes you want the printed list to be numbered. It further assumes the cols
- * will NOT include a colume named ####, and inserts it for you at the start.
+ * will NOT include a colume named ####, and inserts it for you at the start. fits has the fit for
+ * each of cols, and is used to fit the printed table to the terminal. Any of cols that are the title
+ * of one of view_cols are extracted with its json pointer.
  */
 #[allow(clippy::too_many_arguments)]
 pub fn handle_list_result<'a, T, F>(
     env: &mut Env,
     writer: &mut ClickWriter,
     cols: Vec<&str>,
+    fits: &[ColumnFit],
+    options: TableOptions,
     list: List<T>,
     extractors: Option<&HashMap<String, Extractor<T>>>,
+    view_cols: &[ViewColumn],
     regex: Option<Regex>,
     sort: Option<command_def::SortCol>,
     reverse: bool,
     get_kobj: F,
 ) -> Result<(), ClickError>
 where
-    T: 'a + ListableResource + Metadata<Ty = ObjectMeta>,
+    T: 'a + ListableResource + Metadata<Ty = ObjectMeta> + Serialize,
     F: Fn(&T) -> KObj,
 {
-    let mut specs = build_specs(&cols, &list, extractors, true, regex, get_kobj);
+    let mut specs = build_specs(&cols, &list, extractors, view_cols, true, regex, get_kobj);
 
     let mut titles: Vec<&str> = vec!["####"];
     titles.reserve(cols.len());
@@ -229,8 +418,12 @@ where
         specs.into_iter().unzip()
     };
 
-    let table = crate::table::print_table(titles, rows, env, writer);
+    let mut title_fits = vec![ColumnFit::KEEP];
+    title_fits.extend_from_slice(fits);
+    let (table, export) =
+        crate::table::print_or_export_table(&titles, rows, &title_fits, options, env, writer);
     env.set_last_objs(kobjs, Some(table));
+    env.set_last_export(export);
     Ok(())
 }
 
@@ -239,7 +432,8 @@ where
 /* Build row specs and a kobj vec from data returned from k8s.
  *
  * cols is a list of names of columns to build. "Name" * and "Age" are handled, other names need to
- * be in 'extractors', and the extractor for the specified name will be used.
+ * be in 'extractors' or 'view_cols', and the extractor or json pointer for the specified name will
+ * be used.
  *
  * include_index = true will put an index (numbered) column as the first item in the row
  *
@@ -255,16 +449,23 @@ pub fn build_specs<'a, T, F>(
     cols: &[&str],
     list: &'a List<T>,
     extractors: Option<&HashMap<String, Extractor<T>>>,
+    view_cols: &[ViewColumn],
     include_index: bool,
     regex: Option<Regex>,
     get_kobj: F,
 ) -> Vec<(KObj, RowSpec<'a>)>
 where
-    T: 'a + ListableResource + Metadata<Ty = ObjectMeta>,
+    T: 'a + ListableResource + Metadata<Ty = ObjectMeta> + Serialize,
     F: Fn(&T) -> KObj,
 {
     let mut ret = vec![];
     for item in list.items.iter() {
+        // only needed for view columns, so avoid the cost of serializing otherwise
+        let value = if view_cols.is_empty() {
+            None
+        } else {
+            serde_json::to_value(item).ok()
+        };
         let mut row: Vec<CellSpec> = if include_index {
             vec![CellSpec::new_index()]
         } else {
@@ -276,12 +477,15 @@ where
                 "Labels" => row.push(extract_labels(item).into()),
                 "Name" => row.push(extract_name(item).into()),
                 "Namespace" => row.push(extract_namespace(item).into()),
-                _ => match extractors {
-                    Some(extractors) => match extractors.get(*col) {
-                        Some(extractor) => row.push(extractor(item).into()),
+                _ => match view_cols.iter().find(|view_col| view_col.title == *col) {
+                    Some(view_col) => row.push(extract_pointer(&view_col.pointer, &value).into()),
+                    None => match extractors {
+                        Some(extractors) => match extractors.get(*col) {
+                            Some(extractor) => row.push(extractor(item).into()),
+                            None => panic!("Can't extract"),
+                        },
                         None => panic!("Can't extract"),
                     },
-                    None => panic!("Can't extract"),
                 },
             }
         }
@@ -327,6 +531,16 @@ pub fn extract_labels<T: Metadata<Ty = ObjectMeta>>(obj: &T) -> Option<Cow<'_, s
         .map(|labels| keyval_string(labels.iter(), None).into())
 }
 
+/// An extractor for view columns. Strings are shown as is, and other values as json
+fn extract_pointer(pointer: &str, value: &Option<Value>) -> Option<String> {
+    let value = value.as_ref()?;
+    match value.pointer(pointer)? {
+        Value::Null => None,
+        Value::String(_) => Some(crate::values::val_str(pointer, value, "").into_owned()),
+        other => Some(other.to_string()),
+    }
+}
+
 // utility functions
 fn row_matches(row: &[CellSpec<'_>], regex: &Regex) -> bool {
     let mut has_match = false;
@@ -445,3 +659,54 @@ pub fn get_list_request_for_url<T: ListableResource + for<'de> serde::Deserializ
         Err(err) => Err(RequestError::Http(err)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_selectors() {
+        let (request, _) = k8s_openapi::api::core::v1::Pod::list_namespaced_pod(
+            "ns",
+            ListOptional {
+                label_selector: Some("app=web"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let request =
+            add_selectors(request, Some("team=foo"), Some("status.phase=Running")).unwrap();
+        let uri = request.uri().to_string();
+        assert!(uri.starts_with("/api/v1/namespaces/ns/pods?"));
+        let pairs: HashMap<String, String> =
+            url::form_urlencoded::parse(request.uri().query().unwrap().as_bytes())
+                .into_owned()
+                .collect();
+        assert_eq!(pairs["labelSelector"], "app=web,team=foo");
+        assert_eq!(pairs["fieldSelector"], "status.phase=Running");
+
+        let (request, _) =
+            k8s_openapi::api::core::v1::Pod::list_namespaced_pod("ns", Default::default()).unwrap();
+        let before = request.uri().clone();
+        let request = add_selectors(request, None, None).unwrap();
+        assert_eq!(request.uri(), &before);
+    }
+
+    #[test]
+    fn test_extract_pointer() {
+        let value = Some(serde_json::json!({
+            "status": {"qosClass": "Burstable", "replicas": 3, "missing": null}
+        }));
+        assert_eq!(
+            extract_pointer("/status/qosClass", &value),
+            Some("Burstable".to_string())
+        );
+        assert_eq!(
+            extract_pointer("/status/replicas", &value),
+            Some("3".to_string())
+        );
+        assert_eq!(extract_pointer("/status/missing", &value), None);
+        assert_eq!(extract_pointer("/status/nope", &value), None);
+        assert_eq!(extract_pointer("/status", &None), None);
+    }
+}
@@ -17,8 +17,8 @@ use k8s_openapi::api::core::v1 as api;
 use rustyline::completion::Pair as RustlinePair;
 
 use crate::{
-    command::command_def::{exec_match, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::command_def::{add_table_args, add_view_args, exec_match, sort_arg, start_clap, Cmd},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -38,7 +38,11 @@ lazy_static! {
     };
 }
 
-const COL_MAP: &[(&str, &str)] = &[("name", "Name"), ("age", "Age"), ("status", "Status")];
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("age", "Age").priority(7),
+    Col::new("status", "Status"),
+];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
@@ -71,6 +75,7 @@ fn namespace_to_kobj(namespace: &api::Namespace) -> KObj {
             .unwrap_or_else(|| "<Unknown>".into()),
         namespace: None,
         typ: ObjType::Namespace,
+        context: None,
     }
 }
 
@@ -86,27 +91,28 @@ command!(
     "namespaces",
     "Get namespaces in current context",
     |clap: ClapCommand<'static>| {
-        clap.arg(
-            Arg::new("regex")
-                .short('r')
-                .long("regex")
-                .help("Filter returned value by the specified regex")
-                .takes_value(true),
-        )
-        .arg(sort_arg(COL_FLAGS, None))
-        .arg(
-            Arg::new("reverse")
-                .short('R')
-                .long("reverse")
-                .help("Reverse the order of the returned list")
-                .takes_value(false),
-        )
+        add_table_args(add_view_args(clap))
+            .arg(
+                Arg::new("regex")
+                    .short('r')
+                    .long("regex")
+                    .help("Filter returned value by the specified regex")
+                    .takes_value(true),
+            )
+            .arg(sort_arg(COL_FLAGS, None))
+            .arg(
+                Arg::new("reverse")
+                    .short('R')
+                    .long("reverse")
+                    .help("Reverse the order of the returned list")
+                    .takes_value(false),
+            )
     },
     vec!["namespaces"],
-    noop_complete!(),
+    vec![&completer::view_completer],
     no_named_complete!(),
     |matches, env, writer| {
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
         let (request, _response_body) = api::Namespace::list_namespace(Default::default())?;
         run_list_command(
             matches,
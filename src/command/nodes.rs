@@ -17,7 +17,7 @@ use k8s_openapi::api::core::v1 as api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -45,23 +45,23 @@ lazy_static! {
     };
 }
 
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("state", "State"),
-    ("roles", "Roles"),
-    ("age", "Age"),
-    ("version", "Version"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("state", "State"),
+    Col::new("roles", "Roles").priority(6),
+    Col::new("age", "Age").priority(7),
+    Col::new("version", "Version").priority(6),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[
-    ("internalip", "Internal Ip"),
-    ("externalip", "External Ip"),
-    ("osimage", "Os Image"),
-    ("kernelversion", "Kernel Version"),
-    ("containerruntime", "Container Runtime"),
-    ("labels", "Labels"),
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("internalip", "Internal Ip").priority(4),
+    Col::new("externalip", "External Ip").priority(4),
+    Col::new("osimage", "Os Image").priority(3),
+    Col::new("kernelversion", "Kernel Version").priority(3),
+    Col::new("containerruntime", "Container Runtime").priority(3),
+    Col::new("labels", "Labels").priority(1).truncate(40),
 ];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
@@ -75,6 +75,7 @@ fn node_to_kobj(node: &api::Node) -> KObj {
             .unwrap_or_else(|| "<Unknown>".into()),
         namespace: None,
         typ: ObjType::Node,
+        context: None,
     }
 }
 
@@ -217,7 +218,7 @@ list_command!(
     noop_complete!(),
     [].into_iter(),
     |matches, env, writer| {
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
         let (request, _response_body) = api::Node::list_node(Default::default())?;
 
         run_list_command(
@@ -0,0 +1,442 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An inline fuzzy finder over the rows of the last list, to select objects without having to
+//! find their index
+
+use clap::{Arg, Command as ClapCommand};
+use crossterm::{
+    cursor::{MoveToColumn, MoveUp},
+    event::{read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{disable_raw_mode, enable_raw_mode, size, Clear, ClearType},
+    tty::IsTty,
+};
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    completer,
+    env::Env,
+    error::ClickError,
+    kobj::KObj,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, stdout, Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// set by the key binding for pick, so the repl knows to run it when readline returns
+static PICK_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Ask the repl to run pick once readline returns
+pub fn request_pick() {
+    PICK_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Check if pick was requested, clearing the request
+pub fn take_pick_request() -> bool {
+    PICK_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Max number of rows shown at once
+const MAX_HEIGHT: usize = 12;
+const QUERY_PROMPT: &str = "pick> ";
+
+/// Score how well query matches text, or None if it doesn't match at all. Each space separated
+/// term in query has to match. A term matches if all of its characters appear in text in order,
+/// ignoring case. Runs of consecutive characters, and matches at the start of words, score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    for term in query.split_whitespace() {
+        let term = term.to_lowercase();
+        let mut term_chars = term.chars().peekable();
+        let mut last_match: Option<usize> = None;
+        for (i, c) in text.iter().enumerate() {
+            match term_chars.peek() {
+                Some(tc) if tc == c => {
+                    score += 1;
+                    if last_match.is_some() && last_match == i.checked_sub(1) {
+                        score += 5;
+                    }
+                    if i == 0 || !text[i - 1].is_alphanumeric() {
+                        score += 3;
+                    }
+                    last_match = Some(i);
+                    term_chars.next();
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+        if term_chars.peek().is_some() {
+            return None;
+        }
+    }
+    Some(score)
+}
+
+enum PickAction {
+    Continue,
+    Done,
+    Cancel,
+}
+
+struct Picker<'a> {
+    rows: &'a [String],
+    query: String,
+    // indexes into rows that match the query, best match first
+    matches: Vec<usize>,
+    // position in matches of the highlighted row
+    cursor: usize,
+    marked: BTreeSet<usize>,
+}
+
+impl<'a> Picker<'a> {
+    fn new(rows: &'a [String], query: &str) -> Picker<'a> {
+        let mut picker = Picker {
+            rows,
+            query: query.to_string(),
+            matches: vec![],
+            cursor: 0,
+            marked: BTreeSet::new(),
+        };
+        picker.update_matches();
+        picker
+    }
+
+    fn update_matches(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter_map(|(i, row)| fuzzy_score(&self.query, row).map(|score| (score, i)))
+            .collect();
+        // best score first, and list order for equal scores
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.cursor = 0;
+    }
+
+    fn move_cursor(&mut self, down: bool) {
+        if down {
+            if self.cursor + 1 < self.matches.len() {
+                self.cursor += 1;
+            }
+        } else {
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(row) = self.matches.get(self.cursor) {
+            if !self.marked.remove(row) {
+                self.marked.insert(*row);
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> PickAction {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return PickAction::Done,
+            KeyCode::Esc => return PickAction::Cancel,
+            KeyCode::Char('c') | KeyCode::Char('g') if ctrl => return PickAction::Cancel,
+            KeyCode::Up => self.move_cursor(false),
+            KeyCode::Char('p') if ctrl => self.move_cursor(false),
+            KeyCode::Down => self.move_cursor(true),
+            KeyCode::Char('n') if ctrl => self.move_cursor(true),
+            KeyCode::Tab => {
+                self.toggle_mark();
+                self.move_cursor(true);
+            }
+            KeyCode::BackTab => {
+                self.toggle_mark();
+                self.move_cursor(false);
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.update_matches();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update_matches();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.query.push(c);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        PickAction::Continue
+    }
+
+    /// Rows picked. Marked rows if there are any, otherwise the highlighted one
+    fn picked(&self) -> Vec<usize> {
+        if self.marked.is_empty() {
+            self.matches.get(self.cursor).into_iter().cloned().collect()
+        } else {
+            self.marked.iter().cloned().collect()
+        }
+    }
+}
+
+// draw the picker, and leave the cursor at the end of the query. returns how many lines were drawn
+// below the query line
+fn render(out: &mut Stdout, picker: &Picker) -> io::Result<u16> {
+    let (width, height) = size()?;
+    let width = width as usize;
+    let list_height = MAX_HEIGHT.min((height as usize).saturating_sub(2)).max(1);
+    let offset = picker.cursor.saturating_sub(list_height - 1);
+
+    queue!(
+        out,
+        MoveToColumn(0),
+        Clear(ClearType::FromCursorDown),
+        Print(format!(
+            "{QUERY_PROMPT}{}  [{}/{}{}]",
+            picker.query,
+            picker.matches.len(),
+            picker.rows.len(),
+            if picker.marked.is_empty() {
+                String::new()
+            } else {
+                format!(", {} marked", picker.marked.len())
+            }
+        ))
+    )?;
+    let mut drawn = 0;
+    for (pos, row) in picker
+        .matches
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(list_height)
+    {
+        let current = pos == picker.cursor;
+        let line: String = format!(
+            "{}{} {:>4}  {}",
+            if current { '>' } else { ' ' },
+            if picker.marked.contains(row) {
+                '*'
+            } else {
+                ' '
+            },
+            row,
+            picker.rows[*row]
+        )
+        .chars()
+        .take(width.saturating_sub(1))
+        .collect();
+        queue!(out, Print("\r\n"))?;
+        if current {
+            queue!(
+                out,
+                SetAttribute(Attribute::Reverse),
+                Print(line),
+                SetAttribute(Attribute::Reset)
+            )?;
+        } else {
+            queue!(out, Print(line))?;
+        }
+        drawn += 1;
+    }
+    if drawn > 0 {
+        queue!(out, MoveUp(drawn))?;
+    }
+    let query_end = QUERY_PROMPT.chars().count() + picker.query.chars().count();
+    queue!(out, MoveToColumn(query_end as u16))?;
+    out.flush()?;
+    Ok(drawn)
+}
+
+// turns raw mode back off, even if drawing fails part way through
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn new() -> io::Result<RawModeGuard> {
+        enable_raw_mode()?;
+        Ok(RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Run the picker over rows. Returns the indexes of the picked rows, or None if cancelled
+fn run_picker(rows: &[String], query: &str) -> Result<Option<Vec<usize>>, ClickError> {
+    let mut out = stdout();
+    let mut picker = Picker::new(rows, query);
+    let _guard = RawModeGuard::new()?;
+    let action = loop {
+        render(&mut out, &picker)?;
+        match read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => match picker.handle_key(key) {
+                PickAction::Continue => {}
+                action => break action,
+            },
+            _ => {} // resize just redraws
+        }
+    };
+    execute!(out, MoveToColumn(0), Clear(ClearType::FromCursorDown))?;
+    Ok(match action {
+        PickAction::Done => Some(picker.picked()),
+        _ => None,
+    })
+}
+
+/// The text of each row of the last list, without the index column
+fn last_rows(env: &Env) -> Vec<String> {
+    let table = match env.get_last_table() {
+        Some(table) => table,
+        None => return vec![],
+    };
+    let skip = match table.header().and_then(|h| h.cell_iter().next()) {
+        Some(cell) if cell.content() == "####" => 1,
+        _ => 0,
+    };
+    table
+        .row_iter()
+        .map(|row| {
+            row.cell_iter()
+                .skip(skip)
+                .map(|cell| cell.content())
+                .collect::<Vec<String>>()
+                .join("  ")
+        })
+        .collect()
+}
+
+command!(
+    Pick,
+    "pick",
+    "Interactively select objects from the last list with a fuzzy finder (also bound to Ctrl-T)",
+    |clap: ClapCommand<'static>| {
+        clap
+        .arg(
+            Arg::new("query")
+                .help("Start with this query")
+                .required(false)
+                .index(1)
+        )
+        .after_help(
+            "Type to filter the rows of the last list. Every column can be matched, and each space \
+             separated word in the query must match. Up/Down (or Ctrl-P/Ctrl-N) move, Tab marks \
+             a row so several can be picked, Enter selects, and Esc cancels.
+
+Picking one row selects that object, picking several selects them as a range."
+        )
+    },
+    vec!["pick"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, _writer| {
+        if !stdout().is_tty() {
+            return Err(ClickError::CommandError(
+                "pick needs to be run in a terminal".to_string(),
+            ));
+        }
+        let rows = last_rows(env);
+        if rows.is_empty() {
+            return Err(ClickError::CommandError(
+                "No active list to pick from, run a list command (like pods) first".to_string(),
+            ));
+        }
+        let query = matches
+            .get_one::<String>("query")
+            .map(|s| s.as_str())
+            .unwrap_or("");
+        let picked = match run_picker(&rows, query)? {
+            Some(picked) => picked,
+            None => return Ok(()),
+        };
+        match picked.as_slice() {
+            [] => {}
+            [index] => env.set_current(*index),
+            indexes => {
+                let objs: Vec<KObj> = indexes
+                    .iter()
+                    .filter_map(|i| env.item_at(*i).cloned())
+                    .collect();
+                env.set_range(objs);
+            }
+        }
+        Ok(())
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert!(fuzzy_score("ngx", "nginx-7f9c").is_some());
+        assert!(fuzzy_score("xng", "nginx-7f9c").is_none());
+        assert!(fuzzy_score("NGINX", "nginx").is_some());
+        // consecutive matches beat scattered ones
+        assert!(fuzzy_score("web", "web-1") > fuzzy_score("web", "w-e-b"));
+        // every term has to match
+        assert!(fuzzy_score("web run", "web-1  Running").is_some());
+        assert!(fuzzy_score("web pend", "web-1  Running").is_none());
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_picker() {
+        let rows: Vec<String> = ["api-1  Running", "web-1  Running", "web-2  Pending"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut picker = Picker::new(&rows, "");
+        assert_eq!(picker.matches, vec![0, 1, 2]);
+        assert_eq!(picker.picked(), vec![0]);
+
+        for c in "web".chars() {
+            picker.handle_key(key(KeyCode::Char(c)));
+        }
+        assert_eq!(picker.matches, vec![1, 2]);
+        picker.handle_key(key(KeyCode::Down));
+        assert_eq!(picker.picked(), vec![2]);
+
+        // tab marks and moves down, so marking both picks both
+        picker.handle_key(key(KeyCode::Up));
+        picker.handle_key(key(KeyCode::Tab));
+        picker.handle_key(key(KeyCode::Tab));
+        assert_eq!(picker.picked(), vec![1, 2]);
+        // marks are kept when the query changes
+        picker.handle_key(key(KeyCode::Backspace));
+        assert_eq!(picker.picked(), vec![1, 2]);
+
+        assert!(matches!(
+            picker.handle_key(key(KeyCode::Enter)),
+            PickAction::Done
+        ));
+        assert!(matches!(
+            picker.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            PickAction::Cancel
+        ));
+    }
+}
@@ -18,7 +18,7 @@ use k8s_openapi::ListOptional;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::{Env, ObjectSelection},
     error::ClickError,
@@ -47,29 +47,29 @@ lazy_static! {
     };
 }
 
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("ready", "Ready"),
-    ("status", "Status"),
-    ("restarts", "Restarts"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("ready", "Ready"),
+    Col::new("status", "Status"),
+    Col::new("restarts", "Restarts").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[
-    ("ip", "IP"),
-    ("labels", "Labels"),
-    ("lastrestart", "Last Restart"),
-    ("namespace", "Namespace"),
-    ("node", "Node"),
-    ("nominatednode", "Nominated Node"),
-    ("readinessgates", "Readiness Gates"),
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("ip", "IP").priority(4),
+    Col::new("labels", "Labels").priority(1).truncate(40),
+    Col::new("lastrestart", "Last Restart").priority(3),
+    Col::new("namespace", "Namespace"),
+    Col::new("node", "Node").priority(4),
+    Col::new("nominatednode", "Nominated Node").priority(3),
+    Col::new("readinessgates", "Readiness Gates").priority(3),
 ];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
 
-fn pod_to_kobj(pod: &api::Pod) -> KObj {
+pub fn pod_to_kobj(pod: &api::Pod) -> KObj {
     let containers = match &pod.spec {
         Some(spec) => spec
             .containers
@@ -83,6 +83,7 @@ fn pod_to_kobj(pod: &api::Pod) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::Pod { containers },
+        context: None,
     }
 }
 
@@ -111,7 +112,7 @@ fn has_waiting(pod: &api::Pod) -> bool {
     }
 }
 
-fn phase_style_color(phase: &str) -> ColorType {
+pub fn phase_style_color(phase: &str) -> ColorType {
     match phase {
         "Running" | "Active" => ColorType::Success,
         "Terminated" | "Terminating" => ColorType::Danger,
@@ -278,7 +279,17 @@ list_command!(
     },
     vec!["pods"],
     noop_complete!(),
-    [].into_iter(),
+    [
+        (
+            "label".to_string(),
+            completer::label_key_completer as fn(&str, &Env) -> Vec<RustlinePair>
+        ),
+        (
+            "regex".to_string(),
+            completer::pod_name_completer as fn(&str, &Env) -> Vec<RustlinePair>
+        )
+    ]
+    .into_iter(),
     |matches, env, writer| {
         let mut opts: ListOptional = ListOptional::<'_> {
             label_selector: matches.get_one::<String>("label").map(|s| s.as_str()),
@@ -304,7 +315,7 @@ list_command!(
             None => api::Pod::list_pod_for_all_namespaces(opts)?,
         };
 
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
@@ -366,12 +377,9 @@ fn print_containers(
         obj.namespace.as_ref().unwrap(),
         Default::default(),
     )?;
-    match env
-        .run_on_context(|c| {
-            c.read::<api::ReadNamespacedPodResponse>(env.get_impersonate_user(), request)
-        })
-        .unwrap()
-    {
+    match env.run_on_context(|c| {
+        c.read::<api::ReadNamespacedPodResponse>(env.get_impersonation(), request)
+    })? {
         api::ReadNamespacedPodResponse::Ok(pod) => match pod
             .status
             .and_then(|status| status.container_statuses)
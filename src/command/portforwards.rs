@@ -83,7 +83,7 @@ Examples:
   port-forward :3456"
         ),
     vec!["pf", "port-forward"],
-    noop_complete!(),
+    vec![&completer::port_completer],
     no_named_complete!(),
     |matches, env, writer| {
         let ports = matches
@@ -92,12 +92,13 @@ Examples:
             .map(|s| s.as_str());
         //let ports: Vec<_> = matches.get_many("ports").unwrap().copied().collect(); // unwrap safe, required
 
-        let (pod, ns) = {
+        let (pod, ns, pod_context) = {
             let epod = env.current_pod();
             match epod {
                 Some(p) => (
                     p.name().to_string(),
                     p.namespace.as_ref().unwrap().to_string(),
+                    p.context.clone(),
                 ),
                 None => {
                     write!(stderr(), "No active pod").unwrap_or(());
@@ -106,7 +107,9 @@ Examples:
             }
         };
 
-        let context = if let Some(ref context) = env.context {
+        let context = if let Some(context) = pod_context {
+            context
+        } else if let Some(ref context) = env.context {
             context.name.clone()
         } else {
             return Err(ClickError::CommandError("No active context".to_string()));
@@ -118,16 +121,11 @@ Examples:
             .as_deref()
             .unwrap_or("kubectl");
         let mut command = Command::new(kubectl_binary);
-        let command = if let Some(user) = env.get_impersonate_user() {
-            command.arg("--as").arg(user)
-        } else {
-            &mut command
-        };
         command
+            .args(env.kubectl_impersonation_args()?)
             .arg("--namespace")
             .arg(ns)
-            .arg("--context")
-            .arg(context)
+            .args(env.config.kubectl_context_args(&context)?)
             .arg("port-forward")
             .arg(&pod)
             .args(ports.clone());
@@ -291,7 +289,7 @@ command!(
 
             if stop {
                 clickwrite!(writer, "  [y/N]? ");
-                io::stdout().flush().expect("Could not flush stdout");
+                writer.flush().expect("Could not flush stdout");
                 let mut conf = String::new();
                 if io::stdin().read_line(&mut conf).is_ok() {
                     if conf.trim() == "y" || conf.trim() == "yes" {
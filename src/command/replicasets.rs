@@ -17,7 +17,7 @@ use k8s_openapi::api::apps::v1 as apps_api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{keyval_string, run_list_command, Extractor},
+    command::{keyval_string, run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -41,22 +41,24 @@ lazy_static! {
         m
     };
 }
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("desired", "Desired"),
-    ("current", "Current"),
-    ("ready", "Ready"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("desired", "Desired").priority(6),
+    Col::new("current", "Current").priority(6),
+    Col::new("ready", "Ready").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[
-    ("namespace", "Namespace"),
-    ("containers", "Containers"),
-    ("images", "Images"),
-    ("selector", "Selector"),
-    ("labels", "Labels"),
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("namespace", "Namespace"),
+    Col::new("containers", "Containers")
+        .priority(3)
+        .truncate(30),
+    Col::new("images", "Images").priority(2).truncate(40),
+    Col::new("selector", "Selector").priority(2).truncate(40),
+    Col::new("labels", "Labels").priority(1).truncate(40),
 ];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
@@ -67,6 +69,7 @@ fn rs_to_kobj(replicaset: &apps_api::ReplicaSet) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::ReplicaSet,
+        context: None,
     }
 }
 
@@ -168,7 +171,7 @@ list_command!(
             Some(ns) => apps_api::ReplicaSet::list_namespaced_replica_set(ns, Default::default())?,
             None => apps_api::ReplicaSet::list_replica_set_for_all_namespaces(Default::default())?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
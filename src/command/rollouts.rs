@@ -23,7 +23,9 @@ use serde_json::{value::from_value, Error, Value};
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{get_list_request_for_url, get_read_request_for_url, run_list_command, Extractor},
+    command::{
+        get_list_request_for_url, get_read_request_for_url, run_list_command, Col, Extractor,
+    },
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -46,18 +48,21 @@ lazy_static! {
         m
     };
 }
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("desired", "Desired"),
-    ("current", "Current"),
-    ("uptodate", "Up To Date"),
-    ("available", "Available"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("desired", "Desired").priority(6),
+    Col::new("current", "Current").priority(6),
+    Col::new("uptodate", "Up To Date").priority(6),
+    Col::new("available", "Available").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[("labels", "Labels"), ("namespace", "Namespace")];
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("labels", "Labels").priority(1).truncate(40),
+    Col::new("namespace", "Namespace"),
+];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
 
@@ -67,6 +72,7 @@ fn ro_to_kobj(rollout: &RolloutValue) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::Rollout,
+        context: None,
     }
 }
 
@@ -124,7 +130,7 @@ list_command!(
             Some(ns) => RolloutValue::list_namespaced_rollout(ns, Default::default())?,
             None => RolloutValue::list_rollout_for_all_namespaces(Default::default())?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
@@ -17,7 +17,7 @@ use k8s_openapi::api::core::v1 as api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -37,16 +37,19 @@ lazy_static! {
         m
     };
 }
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("type", "Type"),
-    ("data", "Data"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("type", "Type").priority(6),
+    Col::new("data", "Data").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[("namespace", "Namespace"), ("labels", "Labels")];
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("namespace", "Namespace"),
+    Col::new("labels", "Labels").priority(1).truncate(40),
+];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
 
@@ -56,6 +59,7 @@ fn secret_to_kobj(secret: &api::Secret) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::Secret,
+        context: None,
     }
 }
 
@@ -105,7 +109,7 @@ list_command!(
             Some(ns) => api::Secret::list_namespaced_secret(ns, Default::default())?,
             None => api::Secret::list_secret_for_all_namespaces(Default::default())?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
@@ -17,7 +17,7 @@ use k8s_openapi::api::core::v1 as api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{keyval_string, run_list_command, Extractor},
+    command::{keyval_string, run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -41,18 +41,21 @@ lazy_static! {
     };
 }
 
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("type", "Type"),
-    ("clusterip", "Cluster IP"),
-    ("externalip", "External IP"),
-    ("ports", "Port(s)"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("type", "Type").priority(6),
+    Col::new("clusterip", "Cluster IP").priority(6),
+    Col::new("externalip", "External IP").priority(6),
+    Col::new("ports", "Port(s)").priority(5).truncate(30),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[("selector", "Selector"), ("labels", "Labels")];
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("selector", "Selector").priority(2).truncate(40),
+    Col::new("labels", "Labels").priority(1).truncate(40),
+];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
 
@@ -62,6 +65,7 @@ fn service_to_kobj(service: &api::Service) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::Service,
+        context: None,
     }
 }
 
@@ -169,9 +173,13 @@ list_command!(
     },
     vec!["services"],
     noop_complete!(),
-    [].into_iter(),
+    [(
+        "regex".to_string(),
+        completer::service_name_completer as fn(&str, &Env) -> Vec<RustlinePair>
+    )]
+    .into_iter(),
     |matches, env, writer| {
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
         let (request, _response_body) = match &env.namespace {
             Some(ns) => api::Service::list_namespaced_service(ns, Default::default())?,
             None => api::Service::list_service_for_all_namespaces(Default::default())?,
@@ -17,7 +17,7 @@ use k8s_openapi::api::apps::v1 as apps_api;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -40,20 +40,22 @@ lazy_static! {
         m
     };
 }
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("desired", "Desired"),
-    ("current", "Current"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("desired", "Desired").priority(6),
+    Col::new("current", "Current").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[
-    ("containers", "Containers"),
-    ("images", "Images"),
-    ("labels", "Labels"),
-    ("namespace", "Namespace"),
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("containers", "Containers")
+        .priority(3)
+        .truncate(30),
+    Col::new("images", "Images").priority(2).truncate(40),
+    Col::new("labels", "Labels").priority(1).truncate(40),
+    Col::new("namespace", "Namespace"),
 ];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
@@ -64,6 +66,7 @@ fn ss_to_kobj(statefulset: &apps_api::StatefulSet) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::StatefulSet,
+        context: None,
     }
 }
 
@@ -159,7 +162,7 @@ list_command!(
                 apps_api::StatefulSet::list_stateful_set_for_all_namespaces(Default::default())?
             }
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
 
         run_list_command(
             matches,
@@ -17,7 +17,7 @@ use k8s_openapi::api::storage::v1 as api_storage;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -37,15 +37,15 @@ lazy_static! {
     };
 }
 
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("provisioner", "Provisioner"),
-    ("age", "Age"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("provisioner", "Provisioner").priority(6),
+    Col::new("age", "Age").priority(7),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[("labels", "Labels")];
+const EXTRA_COL_MAP: &[Col] = &[Col::new("labels", "Labels").priority(1).truncate(40)];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
 
@@ -58,6 +58,7 @@ fn storageclass_to_kobj(node: &api_storage::StorageClass) -> KObj {
             .unwrap_or_else(|| "<Unknown>".into()),
         namespace: None,
         typ: ObjType::StorageClass,
+        context: None,
     }
 }
 
@@ -100,7 +101,7 @@ list_command!(
     noop_complete!(),
     [].into_iter(),
     |matches, env, writer| {
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
         let (request, _response_body) =
             api_storage::StorageClass::list_storage_class(Default::default())?;
 
@@ -0,0 +1,887 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A full screen mode for browsing objects. Each tab runs one of the list commands, so its table
+//! has the same columns as in the repl, and the actions on the highlighted object run the existing
+//! commands (describe, logs, exec, delete and port-forward).
+
+use clap::Command as ClapCommand;
+use comfy_table::Table;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+    tty::IsTty,
+};
+use regex::Regex;
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command_processor::CommandProcessor,
+    completer,
+    env::Env,
+    error::ClickError,
+    kobj::KObj,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, stdin, stdout, Stdout, Write};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The resource tabs, as (title, list command to run)
+const TABS: &[(&str, &str)] = &[
+    ("Pods", "pods"),
+    ("Deployments", "deployments"),
+    ("Services", "services"),
+    ("StatefulSets", "statefulsets"),
+    ("DaemonSets", "daemonsets"),
+    ("Jobs", "jobs"),
+    ("CronJobs", "cronjobs"),
+    ("ConfigMaps", "configmaps"),
+    ("Secrets", "secrets"),
+    ("Nodes", "nodes"),
+    ("Namespaces", "namespaces"),
+];
+
+/// How often the current tab is re-fetched
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often to check if a list being fetched has arrived
+const FETCH_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const KEY_HELP: &str = "q:quit  ←/→:tab  ↵:describe  l:logs  x:exec  p:port-forward  D:delete  \
+                        r:refresh";
+
+lazy_static! {
+    static ref ANSI_ESCAPE: Regex = Regex::new("\x1b\\[[0-9;]*[A-Za-z]").unwrap();
+}
+
+/// What a key press asks the ui to do
+#[derive(Debug, PartialEq)]
+enum UiAction {
+    Continue,
+    Refresh,
+    Quit,
+    /// Leave full screen and run this command on the highlighted object
+    Run(Vec<String>),
+}
+
+/// The list for a tab, fetched on a background thread
+struct Fetched {
+    tab: usize,
+    error: Option<String>,
+    listed: Option<(Vec<KObj>, Table)>,
+}
+
+/// A line of input being typed at the bottom of the screen, which becomes the arguments of command
+struct Input {
+    command: &'static str,
+    text: String,
+}
+
+struct Ui {
+    tab: usize,
+    // highlighted row in each tab
+    cursors: Vec<usize>,
+    // first row shown
+    offset: usize,
+    // rows that fit on the screen, updated on each render
+    page: usize,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+    error: Option<String>,
+    fetched: Option<Instant>,
+    // the tab being fetched, and where its list will arrive
+    pending: Option<(usize, Receiver<Fetched>)>,
+    show_describe: bool,
+    // the object the describe pane is showing, and its output
+    described: Option<KObj>,
+    describe: Vec<String>,
+    describe_scroll: usize,
+    input: Option<Input>,
+    status: Option<String>,
+}
+
+impl Ui {
+    fn new() -> Ui {
+        Ui {
+            tab: 0,
+            cursors: vec![0; TABS.len()],
+            offset: 0,
+            page: 1,
+            header: vec![],
+            rows: vec![],
+            error: None,
+            fetched: None,
+            pending: None,
+            show_describe: false,
+            described: None,
+            describe: vec![],
+            describe_scroll: 0,
+            input: None,
+            status: None,
+        }
+    }
+
+    fn cursor(&self) -> usize {
+        self.cursors[self.tab]
+    }
+
+    fn set_cursor(&mut self, cursor: usize) {
+        self.cursors[self.tab] = cursor.min(self.rows.len().saturating_sub(1));
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let cursor = self.cursor() as isize + delta;
+        self.set_cursor(cursor.max(0) as usize);
+    }
+
+    fn switch_tab(&mut self, tab: usize) -> UiAction {
+        self.tab = tab;
+        self.offset = 0;
+        self.rows.clear();
+        self.header.clear();
+        self.described = None;
+        self.describe.clear();
+        UiAction::Refresh
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent) -> UiAction {
+        let input = match self.input.as_mut() {
+            Some(input) => input,
+            None => return UiAction::Continue,
+        };
+        match key.code {
+            KeyCode::Enter => {
+                let input = self.input.take().unwrap(); // safe, checked above
+                let mut args = vec![input.command.to_string()];
+                args.extend(input.text.split_whitespace().map(|s| s.to_string()));
+                if args.len() > 1 {
+                    return UiAction::Run(args);
+                }
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input = None
+            }
+            KeyCode::Backspace => {
+                input.text.pop();
+            }
+            KeyCode::Char(c) => input.text.push(c),
+            _ => {}
+        }
+        UiAction::Continue
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> UiAction {
+        if self.input.is_some() {
+            return self.handle_input_key(key);
+        }
+        self.status = None;
+        let page = self.page.max(1) as isize;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return UiAction::Quit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return UiAction::Quit
+            }
+            KeyCode::Right | KeyCode::Tab => return self.switch_tab((self.tab + 1) % TABS.len()),
+            KeyCode::Left | KeyCode::BackTab => {
+                return self.switch_tab((self.tab + TABS.len() - 1) % TABS.len())
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let tab = c as usize - '1' as usize;
+                if tab < TABS.len() {
+                    return self.switch_tab(tab);
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1),
+            KeyCode::PageUp => self.move_cursor(-page),
+            KeyCode::PageDown => self.move_cursor(page),
+            KeyCode::Home | KeyCode::Char('g') => self.set_cursor(0),
+            KeyCode::End | KeyCode::Char('G') => self.set_cursor(self.rows.len()),
+            KeyCode::Enter | KeyCode::Char('d') => {
+                self.show_describe = !self.show_describe;
+                self.describe_scroll = 0;
+            }
+            KeyCode::Char('J') if self.describe_scroll + 1 < self.describe.len() => {
+                self.describe_scroll += 1;
+            }
+            KeyCode::Char('K') => self.describe_scroll = self.describe_scroll.saturating_sub(1),
+            KeyCode::Char('r') => return UiAction::Refresh,
+            KeyCode::Char(c @ ('l' | 'x' | 'p' | 'D')) => {
+                if self.rows.is_empty() {
+                    self.status = Some("Nothing selected".to_string());
+                    return UiAction::Continue;
+                }
+                match c {
+                    'l' => return UiAction::Run(vec!["logs".into(), "--follow".into()]),
+                    'D' => return UiAction::Run(vec!["delete".into()]),
+                    'x' => {
+                        self.input = Some(Input {
+                            command: "exec",
+                            text: "/bin/sh".to_string(),
+                        })
+                    }
+                    _ => {
+                        self.input = Some(Input {
+                            command: "port-forward",
+                            text: String::new(),
+                        })
+                    }
+                }
+            }
+            _ => {}
+        }
+        UiAction::Continue
+    }
+
+    /// Start fetching the list for the current tab. Lists are fetched on another thread, so keys
+    /// are still handled while the request runs, and receive puts the list in place once it's
+    /// done. Does nothing if the current tab is already being fetched.
+    fn refresh(&mut self, env: &mut Env, commands: &[Box<dyn Cmd>]) {
+        if matches!(self.pending, Some((tab, _)) if tab == self.tab) {
+            return;
+        }
+        let tab = self.tab;
+        let (sender, receiver) = channel();
+        match env.background() {
+            Some(background) => {
+                thread::spawn(move || {
+                    let mut env = background.into_env();
+                    let commands = CommandProcessor::get_command_vec();
+                    // the ui may have moved on, in which case nobody wants this
+                    let _ = sender.send(fetch(&mut env, &commands, tab));
+                });
+            }
+            // there's no request to wait for without a context, the command just fails
+            None => {
+                let _ = sender.send(fetch(env, commands, tab));
+            }
+        }
+        // any fetch of another tab is dropped, its result is never used
+        self.pending = Some((tab, receiver));
+    }
+
+    /// If a fetched list has arrived, show it. It becomes the last list in env, so the
+    /// highlighted row is always env.item_at(cursor). Returns true if anything changed.
+    fn receive(&mut self, env: &mut Env) -> bool {
+        let fetched = match self
+            .pending
+            .as_ref()
+            .map(|(_, receiver)| receiver.try_recv())
+        {
+            None | Some(Err(TryRecvError::Empty)) => return false,
+            Some(Ok(fetched)) => fetched,
+            Some(Err(TryRecvError::Disconnected)) => Fetched {
+                tab: self.tab,
+                error: Some("Failed to fetch the list".to_string()),
+                listed: None,
+            },
+        };
+        self.pending = None;
+        if fetched.tab != self.tab {
+            return false;
+        }
+        let highlighted = env.item_at(self.cursor()).cloned();
+        // the selection can filter lists (like pods on a node), which would be confusing here
+        env.clear_current();
+        self.error = fetched.error;
+        match fetched.listed {
+            Some((objs, table)) => {
+                let (header, rows) = table_rows(&table);
+                self.header = header;
+                self.rows = rows;
+                env.set_last_objs(objs, Some(table));
+            }
+            None => {
+                self.header.clear();
+                self.rows.clear();
+                env.set_last_objs(vec![], None);
+            }
+        }
+        if self.rows.is_empty() {
+            self.describe.clear();
+        }
+        // stay on the same object if it's still there
+        let cursor = highlighted
+            .and_then(|obj| {
+                (0..self.rows.len()).find(|i| {
+                    env.item_at(*i)
+                        .map(|o| {
+                            o.name() == obj.name()
+                                && o.namespace == obj.namespace
+                                && o.type_str() == obj.type_str()
+                        })
+                        .unwrap_or(false)
+                })
+            })
+            .unwrap_or_else(|| self.cursor());
+        self.set_cursor(cursor);
+        self.described = None;
+        self.fetched = Some(Instant::now());
+        true
+    }
+
+    fn describe_stale(&self, env: &Env) -> bool {
+        // until a new tab's list arrives, env still has the list of the last one
+        self.show_describe
+            && !self.rows.is_empty()
+            && env.item_at(self.cursor()) != self.described.as_ref()
+    }
+
+    fn update_describe(&mut self, env: &mut Env, commands: &[Box<dyn Cmd>]) {
+        self.described = env.item_at(self.cursor()).cloned();
+        self.describe_scroll = 0;
+        if self.described.is_none() {
+            self.describe = vec![];
+            return;
+        }
+        env.set_current(self.cursor());
+        let mut writer = ClickWriter::with_buffer(vec![], false);
+        let result = run_command(env, commands, &["describe"], &mut writer);
+        let output = writer.finish_output().unwrap_or_default();
+        let mut text = ANSI_ESCAPE
+            .replace_all(&String::from_utf8_lossy(&output), "")
+            .into_owned();
+        if let Err(e) = result {
+            text.push_str(&e.to_string());
+        }
+        self.describe = text.lines().map(|l| l.replace('\t', "    ")).collect();
+    }
+
+    /// How long to wait for a key before there's something else to do: check for a fetched list
+    /// if one is on its way, otherwise start the next refresh
+    fn poll_timeout(&self) -> Duration {
+        if self.pending.is_some() {
+            return FETCH_POLL_INTERVAL;
+        }
+        self.fetched
+            .map(|fetched| REFRESH_INTERVAL.saturating_sub(fetched.elapsed()))
+            .unwrap_or_default()
+    }
+}
+
+/// Run the list command for tab, and take the list it made from env
+fn fetch(env: &mut Env, commands: &[Box<dyn Cmd>], tab: usize) -> Fetched {
+    let mut writer = ClickWriter::with_buffer(vec![], false);
+    let error = run_command(env, commands, &[TABS[tab].1], &mut writer)
+        .err()
+        .map(|e| e.to_string());
+    let listed = match env.take_last_objs() {
+        (Some(objs), Some(table)) => Some((objs, table)),
+        _ => None,
+    };
+    Fetched { tab, error, listed }
+}
+
+fn run_command(
+    env: &mut Env,
+    commands: &[Box<dyn Cmd>],
+    args: &[&str],
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    match commands.iter().find(|c| c.is(args[0])) {
+        Some(cmd) => cmd.exec(env, &mut args[1..].iter().cloned(), writer),
+        None => Err(ClickError::CommandError(format!(
+            "Unknown command {}",
+            args[0]
+        ))),
+    }
+}
+
+/// The header and rows of a listed table as text, without the index column. Multi-line cells (like
+/// labels) are joined onto one line
+fn table_rows(table: &Table) -> (Vec<String>, Vec<Vec<String>>) {
+    let header: Vec<String> = table
+        .header()
+        .map(|h| h.cell_iter().map(|cell| cell.content()).collect())
+        .unwrap_or_default();
+    let skip = match header.first() {
+        Some(title) if title == "####" => 1,
+        _ => 0,
+    };
+    let rows = table
+        .row_iter()
+        .map(|row| {
+            row.cell_iter()
+                .skip(skip)
+                .map(|cell| cell.content().replace('\n', ", "))
+                .collect()
+        })
+        .collect();
+    (header.into_iter().skip(skip).collect(), rows)
+}
+
+fn column_widths(header: &[String], rows: &[Vec<String>]) -> Vec<usize> {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows.iter() {
+        for (i, cell) in row.iter().enumerate() {
+            let len = cell.chars().count();
+            match widths.get_mut(i) {
+                Some(width) => *width = (*width).max(len),
+                None => widths.push(len),
+            }
+        }
+    }
+    widths
+}
+
+fn format_row(cells: &[String], widths: &[usize]) -> String {
+    cells
+        .iter()
+        .zip(widths.iter())
+        .map(|(cell, width)| format!("{cell:width$}"))
+        .collect::<Vec<String>>()
+        .join("  ")
+        .trim_end()
+        .to_string()
+}
+
+// print text on the specified line, cut to fit the screen
+fn draw_line(
+    out: &mut Stdout,
+    row: u16,
+    text: &str,
+    width: usize,
+    reverse: bool,
+) -> io::Result<()> {
+    let text: String = text.chars().take(width).collect();
+    queue!(out, MoveTo(0, row))?;
+    if reverse {
+        queue!(
+            out,
+            SetAttribute(Attribute::Reverse),
+            Print(format!("{text:width$}")),
+            SetAttribute(Attribute::Reset)
+        )?;
+    } else {
+        queue!(out, Print(text), Clear(ClearType::UntilNewLine))?;
+    }
+    Ok(())
+}
+
+fn render(out: &mut Stdout, ui: &mut Ui, env: &Env) -> io::Result<()> {
+    let (width, height) = size()?;
+    let width = width as usize;
+    // tab bar, header, and status line take 3 rows
+    let available = (height as usize).saturating_sub(3).max(1);
+    let table_height = if ui.show_describe {
+        (available / 2).max(1)
+    } else {
+        available
+    };
+    ui.page = table_height;
+    if ui.cursor() < ui.offset {
+        ui.offset = ui.cursor();
+    } else if ui.cursor() >= ui.offset + table_height {
+        ui.offset = ui.cursor() + 1 - table_height;
+    }
+
+    let mut x = 0;
+    queue!(out, MoveTo(0, 0), Clear(ClearType::CurrentLine))?;
+    for (i, (title, _)) in TABS.iter().enumerate() {
+        let label = format!(" {}:{} ", i + 1, title);
+        x += label.chars().count();
+        if x > width {
+            break;
+        }
+        if i == ui.tab {
+            queue!(
+                out,
+                SetAttribute(Attribute::Reverse),
+                Print(label),
+                SetAttribute(Attribute::Reset)
+            )?;
+        } else {
+            queue!(out, Print(label))?;
+        }
+    }
+
+    let widths = column_widths(&ui.header, &ui.rows);
+    queue!(out, SetAttribute(Attribute::Bold))?;
+    draw_line(out, 1, &format_row(&ui.header, &widths), width, false)?;
+    queue!(out, SetAttribute(Attribute::Reset))?;
+    let mut line = 2;
+    if let Some(error) = ui.error.as_ref() {
+        draw_line(out, line, &format!("Error: {error}"), width, false)?;
+        line += 1;
+    } else if ui.rows.is_empty() {
+        let message = if ui.pending.is_some() {
+            "Loading..."
+        } else {
+            "No objects found"
+        };
+        draw_line(out, line, message, width, false)?;
+        line += 1;
+    }
+    for (i, row) in ui
+        .rows
+        .iter()
+        .enumerate()
+        .skip(ui.offset)
+        .take(table_height)
+    {
+        draw_line(
+            out,
+            line,
+            &format_row(row, &widths),
+            width,
+            i == ui.cursor(),
+        )?;
+        line += 1;
+    }
+    for l in line..(2 + table_height as u16) {
+        draw_line(out, l, "", width, false)?;
+    }
+
+    if ui.show_describe {
+        let mut line = 2 + table_height as u16;
+        let title = match ui.described.as_ref() {
+            Some(obj) => format!("── describe {} {} ", obj.type_str(), obj.name()),
+            None => "── describe ".to_string(),
+        };
+        draw_line(
+            out,
+            line,
+            &format!("{title}{}", "─".repeat(width)),
+            width,
+            false,
+        )?;
+        line += 1;
+        for text in ui.describe.iter().skip(ui.describe_scroll) {
+            if line + 1 >= height {
+                break;
+            }
+            draw_line(out, line, text, width, false)?;
+            line += 1;
+        }
+        for l in line..height.saturating_sub(1) {
+            draw_line(out, l, "", width, false)?;
+        }
+    }
+
+    let status_line = height.saturating_sub(1);
+    match (ui.input.as_ref(), ui.status.as_ref()) {
+        (Some(input), _) => {
+            let prompt = format!("{}> {}", input.command, input.text);
+            draw_line(out, status_line, &prompt, width, false)?;
+        }
+        (None, Some(status)) => draw_line(out, status_line, status, width, true)?,
+        (None, None) => {
+            let location = format!(
+                "{}/{}",
+                env.context.as_ref().map(|c| c.name.as_str()).unwrap_or(""),
+                env.namespace.as_deref().unwrap_or("[all]")
+            );
+            draw_line(
+                out,
+                status_line,
+                &format!(" {location}  {KEY_HELP}"),
+                width,
+                true,
+            )?;
+        }
+    }
+    out.flush()
+}
+
+// switches to the alternate screen in raw mode, and back when dropped
+struct FullScreen;
+
+impl FullScreen {
+    fn enter() -> io::Result<FullScreen> {
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen, Hide)?;
+        Ok(FullScreen)
+    }
+}
+
+impl Drop for FullScreen {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), LeaveAlternateScreen, Show);
+        let _ = disable_raw_mode();
+    }
+}
+
+fn run_ui(env: &mut Env) -> Result<(), ClickError> {
+    let commands = CommandProcessor::get_command_vec();
+    let mut out = stdout();
+    let mut ui = Ui::new();
+    let mut screen = Some(FullScreen::enter()?);
+    ui.refresh(env, &commands);
+    let mut redraw = true;
+    loop {
+        redraw |= ui.receive(env);
+        // only describe once keys stop arriving, so scrolling through the list stays fast
+        if ui.describe_stale(env) && !poll(Duration::ZERO)? {
+            ui.update_describe(env, &commands);
+            redraw = true;
+        }
+        if redraw {
+            render(&mut out, &mut ui, env)?;
+            redraw = false;
+        }
+        if !poll(ui.poll_timeout())? {
+            if ui.pending.is_none() {
+                ui.refresh(env, &commands);
+            }
+            continue;
+        }
+        redraw = true;
+        let action = match read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => ui.handle_key(key),
+            _ => UiAction::Continue, // resize just redraws
+        };
+        match action {
+            UiAction::Continue => {}
+            UiAction::Refresh => ui.refresh(env, &commands),
+            UiAction::Quit => break,
+            UiAction::Run(args) => {
+                env.set_current(ui.cursor());
+                drop(screen.take());
+                let mut writer = ClickWriter::new();
+                let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+                if let Err(e) = run_command(env, &commands, &args, &mut writer) {
+                    clickwriteln!(writer, "{}", e);
+                }
+                clickwrite!(writer, "\nPress Enter to return to the ui");
+                writer.flush()?;
+                let mut line = String::new();
+                stdin().read_line(&mut line)?;
+                screen = Some(FullScreen::enter()?);
+                ui.refresh(env, &commands);
+            }
+        }
+    }
+    drop(screen);
+    if ui.rows.is_empty() {
+        env.clear_current();
+    } else {
+        env.set_current(ui.cursor());
+    }
+    Ok(())
+}
+
+command!(
+    UiCmd,
+    "ui",
+    "Browse objects in a full screen view. The highlighted object is selected on quit",
+    |clap: ClapCommand<'static>| {
+        clap.after_help(
+            "Each tab shows the output of one of the list commands (pods, deployments, ...) for \
+             the current context and namespace, and is refreshed every few seconds.
+
+Keys:
+  Left/Right, Tab, 1-9  switch tabs
+  Up/Down, j/k          move (PageUp/PageDown, g/G for top/bottom)
+  Enter, d              show/hide describe output for the highlighted object (J/K to scroll it)
+  l                     follow logs of the highlighted pod (stop with ^C)
+  x                     exec a command in the highlighted pod
+  p                     port-forward to the highlighted pod
+  D                     delete the highlighted object (asks for confirmation)
+  r                     refresh now
+  q, Esc                quit, selecting the highlighted object",
+        )
+    },
+    vec!["ui"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_matches, env, _writer| {
+        if !stdout().is_tty() {
+            return Err(ClickError::CommandError(
+                "ui needs to be run in a terminal".to_string(),
+            ));
+        }
+        if env.context.is_none() {
+            return Err(ClickError::CommandError(
+                "Need an active context in order to run the ui".to_string(),
+            ));
+        }
+        run_ui(env)
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comfy_table::Cell;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ui_with_rows(count: usize) -> Ui {
+        let mut ui = Ui::new();
+        ui.header = vec!["Name".to_string()];
+        ui.rows = (0..count).map(|i| vec![format!("obj-{i}")]).collect();
+        ui.page = 2;
+        ui
+    }
+
+    #[test]
+    fn test_table_rows() {
+        let mut table = Table::new();
+        table.set_header(vec!["####", "Name", "Labels"]);
+        table.add_row(vec![Cell::new(0), Cell::new("web"), Cell::new("a=b\nc=d")]);
+        table.add_row(vec![
+            Cell::new(1),
+            Cell::new("api-server"),
+            Cell::new("x=y"),
+        ]);
+        let (header, rows) = table_rows(&table);
+        assert_eq!(header, vec!["Name", "Labels"]);
+        assert_eq!(rows[0], vec!["web", "a=b, c=d"]);
+
+        let widths = column_widths(&header, &rows);
+        assert_eq!(widths, vec![10, 8]);
+        assert_eq!(format_row(&header, &widths), "Name        Labels");
+        assert_eq!(format_row(&rows[1], &widths), "api-server  x=y");
+    }
+
+    #[test]
+    fn test_receive() {
+        let mut env = Env::new(
+            crate::config::get_test_config(),
+            crate::config::ClickConfig::default(),
+            std::path::PathBuf::from("/tmp/click.conf"),
+        );
+        let obj = |name: &str| KObj {
+            name: name.to_string(),
+            namespace: Some("dev".to_string()),
+            typ: crate::kobj::ObjType::Service,
+            context: None,
+        };
+        let fetched = |names: &[&str]| {
+            let mut table = Table::new();
+            table.set_header(vec!["####", "Name"]);
+            for (i, name) in names.iter().enumerate() {
+                table.add_row(vec![Cell::new(i), Cell::new(name)]);
+            }
+            Fetched {
+                tab: 2,
+                error: None,
+                listed: Some((names.iter().map(|name| obj(name)).collect(), table)),
+            }
+        };
+        let mut ui = Ui::new();
+        ui.switch_tab(2);
+        assert!(!ui.receive(&mut env));
+
+        let (sender, receiver) = channel();
+        ui.pending = Some((2, receiver));
+        assert!(!ui.receive(&mut env));
+        assert_eq!(ui.poll_timeout(), FETCH_POLL_INTERVAL);
+        sender.send(fetched(&["api", "web"])).unwrap();
+        assert!(ui.receive(&mut env));
+        assert!(ui.pending.is_none());
+        assert_eq!(ui.rows, vec![vec!["api"], vec!["web"]]);
+        assert_eq!(env.item_at(1), Some(&obj("web")));
+        ui.set_cursor(1);
+
+        // the highlighted object stays highlighted when the list changes
+        let (sender, receiver) = channel();
+        ui.pending = Some((2, receiver));
+        sender.send(fetched(&["api", "db", "web"])).unwrap();
+        assert!(ui.receive(&mut env));
+        assert_eq!(ui.cursor(), 2);
+
+        // a list for a tab that's no longer shown is dropped
+        let (sender, receiver) = channel();
+        ui.pending = Some((2, receiver));
+        ui.tab = 3;
+        sender.send(fetched(&["other"])).unwrap();
+        assert!(!ui.receive(&mut env));
+        assert!(ui.pending.is_none());
+        assert_eq!(env.item_at(0), Some(&obj("api")));
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut ui = ui_with_rows(5);
+        ui.handle_key(key(KeyCode::Down));
+        ui.handle_key(key(KeyCode::Char('j')));
+        assert_eq!(ui.cursor(), 2);
+        ui.handle_key(key(KeyCode::PageDown));
+        assert_eq!(ui.cursor(), 4);
+        ui.handle_key(key(KeyCode::Down));
+        assert_eq!(ui.cursor(), 4);
+        ui.handle_key(key(KeyCode::Char('g')));
+        assert_eq!(ui.cursor(), 0);
+        ui.handle_key(key(KeyCode::Up));
+        assert_eq!(ui.cursor(), 0);
+
+        // each tab remembers its cursor
+        ui.handle_key(key(KeyCode::End));
+        assert_eq!(ui.handle_key(key(KeyCode::Right)), UiAction::Refresh);
+        assert_eq!(ui.tab, 1);
+        assert_eq!(ui.cursor(), 0);
+        assert_eq!(ui.handle_key(key(KeyCode::Left)), UiAction::Refresh);
+        assert_eq!(ui.cursor(), 4);
+        ui.handle_key(key(KeyCode::Left));
+        assert_eq!(ui.tab, TABS.len() - 1);
+        ui.handle_key(key(KeyCode::Char('3')));
+        assert_eq!(ui.tab, 2);
+
+        assert_eq!(ui.handle_key(key(KeyCode::Char('q'))), UiAction::Quit);
+    }
+
+    #[test]
+    fn test_actions() {
+        let mut ui = ui_with_rows(0);
+        assert_eq!(ui.handle_key(key(KeyCode::Char('l'))), UiAction::Continue);
+        assert_eq!(ui.status.as_deref(), Some("Nothing selected"));
+
+        let mut ui = ui_with_rows(2);
+        assert_eq!(
+            ui.handle_key(key(KeyCode::Char('l'))),
+            UiAction::Run(vec!["logs".to_string(), "--follow".to_string()])
+        );
+
+        // port-forward asks for the ports
+        ui.handle_key(key(KeyCode::Char('p')));
+        assert!(ui.input.is_some());
+        // keys go to the input while it's open
+        assert_eq!(ui.handle_key(key(KeyCode::Char('q'))), UiAction::Continue);
+        ui.handle_key(key(KeyCode::Backspace));
+        for c in "8080 9090".chars() {
+            ui.handle_key(key(KeyCode::Char(c)));
+        }
+        assert_eq!(
+            ui.handle_key(key(KeyCode::Enter)),
+            UiAction::Run(vec![
+                "port-forward".to_string(),
+                "8080".to_string(),
+                "9090".to_string()
+            ])
+        );
+        assert!(ui.input.is_none());
+
+        // exec defaults to a shell, and esc cancels
+        ui.handle_key(key(KeyCode::Char('x')));
+        assert_eq!(ui.input.as_ref().unwrap().text, "/bin/sh");
+        ui.handle_key(key(KeyCode::Esc));
+        assert!(ui.input.is_none());
+    }
+}
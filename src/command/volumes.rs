@@ -17,7 +17,7 @@ use k8s_openapi::{api::core::v1 as api, apimachinery::pkg::api::resource::Quanti
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{run_list_command, Col, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -44,21 +44,24 @@ lazy_static! {
     };
 }
 
-const COL_MAP: &[(&str, &str)] = &[
-    ("name", "Name"),
-    ("age", "Age"),
-    ("capacity", "Capacity"),
-    ("accessmodes", "Access Modes"),
-    ("replacepolicy", "Reclaim Policy"),
-    ("status", "Status"),
-    ("cliam", "Claim"),
-    ("storageclass", "Storage Class"),
-    ("reason", "Reason"),
+const COL_MAP: &[Col] = &[
+    Col::new("name", "Name"),
+    Col::new("age", "Age").priority(7),
+    Col::new("capacity", "Capacity").priority(6),
+    Col::new("accessmodes", "Access Modes").priority(6),
+    Col::new("replacepolicy", "Reclaim Policy").priority(6),
+    Col::new("status", "Status"),
+    Col::new("cliam", "Claim").priority(6),
+    Col::new("storageclass", "Storage Class").priority(6),
+    Col::new("reason", "Reason").priority(6),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
-const EXTRA_COL_MAP: &[(&str, &str)] = &[("labels", "Labels"), ("volumemode", "Volume Mode")];
+const EXTRA_COL_MAP: &[Col] = &[
+    Col::new("labels", "Labels").priority(1).truncate(40),
+    Col::new("volumemode", "Volume Mode").priority(3),
+];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
 
@@ -68,6 +71,7 @@ fn pv_to_kobj(volume: &api::PersistentVolume) -> KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
         typ: ObjType::PersistentVolume,
+        context: None,
     }
 }
 
@@ -186,7 +190,7 @@ list_command!(
     |matches, env, writer| {
         let (request, _response_body) =
             api::PersistentVolume::list_persistent_volume(Default::default())?;
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let cols: Vec<&str> = COL_MAP.iter().map(|col| col.title).collect();
         run_list_command(
             matches,
             env,
@@ -14,25 +14,37 @@
 
 use crate::command::command_def::Cmd;
 use crate::completer::ClickHelper;
+use crate::config::Alias;
 use crate::error::ClickError;
+use crate::history::History;
 use crate::kobj::KObj;
-use crate::output::ClickWriter;
-use crate::parser::{try_parse_csl, try_parse_range, Parser};
+use crate::output::{ClickWriter, Pager};
+use crate::parser::{
+    expand_line, split_commands, substitute_alias_params, try_parse_csl, try_parse_range,
+    Expansion, Parser,
+};
 use crate::values::val_str;
 
+use regex::Regex;
 use rustyline::config as rustyconfig;
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
-use rustyline::Editor;
+use rustyline::{
+    Cmd as RustylineCmd, ConditionalEventHandler, Editor, Event, EventContext, EventHandler,
+    KeyEvent, RepeatCount,
+};
 
 use crate::env::Env;
 
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::ops::Range;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::rc::Rc;
 
+// commands whose output is never paged, see CommandProcessor::pages_output
+const UNPAGED_COMMANDS: [&str; 4] = ["exec", "pick", "port-forward", "ui"];
+
 /// Things the can come after a | or > char in input
 #[derive(Debug, PartialEq)]
 enum RightExpr<'a> {
@@ -87,24 +99,60 @@ fn build_parser_expr(line: &str, range: Range<usize>) -> Result<(&str, RightExpr
     }
 }
 
+/// Expand any aliases in the line. Each command in the line (see `split_commands`) has its first
+/// word expanded if it's an alias. The expansion is then itself expanded, but an alias is never
+/// expanded inside its own expansion, which prevents infinite loops.
 pub fn alias_expand_line(env: &Env, line: &str) -> String {
-    let expa = env.try_expand_alias(line, None);
-    let mut alias_stack = vec![expa];
-    #[allow(clippy::while_let_loop)] // needed due to borrow restrictions
-    loop {
-        let expa = match alias_stack.last().unwrap().expansion {
-            Some(prev) => {
-                // previous thing expanded an alias, so try and expand that too
-                env.try_expand_alias(prev.expanded.as_str(), Some(prev.alias.as_str()))
-            }
-            None => break,
-        };
-        alias_stack.push(expa);
+    expand_aliases(env, line, &mut vec![])
+}
+
+fn expand_aliases<'a>(env: &'a Env, line: &str, expanding: &mut Vec<&'a str>) -> String {
+    let cmds = split_commands(line);
+    if cmds.len() > 1 {
+        cmds.iter()
+            .map(|cmd| expand_alias_cmd(env, cmd.trim(), expanding))
+            .collect::<Vec<String>>()
+            .join("; ")
+    } else {
+        expand_alias_cmd(env, line, expanding)
+    }
+}
+
+// expand a single command (i.e. with no ;s)
+fn expand_alias_cmd<'a>(env: &'a Env, cmd: &str, expanding: &mut Vec<&'a str>) -> String {
+    let expa = env.try_expand_alias(cmd, expanding);
+    match expa.expansion {
+        Some(alias) => {
+            let expanded = expand_alias_params(alias, expa.rest);
+            expanding.push(alias.alias.as_str());
+            let ret = expand_aliases(env, &expanded, expanding);
+            expanding.pop();
+            ret
+        }
+        None => cmd.to_string(),
+    }
+}
+
+// substitute the args in rest into the alias. for a plain alias this just appends rest. for a
+// parameterized one, the args up to any pipe or redirect are substituted, and the pipe/redirect is
+// kept at the end.
+fn expand_alias_params(alias: &Alias, rest: &str) -> String {
+    let mut args = vec![];
+    let mut tail = "";
+    for (range, sep, arg) in Parser::new(rest) {
+        if !arg.is_empty() {
+            args.push(arg);
+        }
+        if sep == '|' || sep == '>' {
+            tail = &rest[range.end..];
+            break;
+        }
+    }
+    match substitute_alias_params(alias.expanded.as_str(), &args) {
+        Some(expanded) if tail.is_empty() => expanded,
+        Some(expanded) => format!("{expanded} {tail}"),
+        None => format!("{}{}", alias.expanded, rest),
     }
-    // At this point, all the "real" stuff is in the chain of "rest" memebers of the
-    // alias_stack, let's gather them up
-    let rests: Vec<&str> = alias_stack.iter().rev().map(|ea| ea.rest).collect();
-    rests.concat()
 }
 
 fn parse_line(line: &str) -> Result<(&str, RightExpr<'_>), ClickError> {
@@ -118,11 +166,25 @@ fn parse_line(line: &str) -> Result<(&str, RightExpr<'_>), ClickError> {
     Ok((line, RightExpr::None))
 }
 
+/// Handles the key bound to pick. readline can't run a command itself, so this flags that pick
+/// should be run and interrupts readline, which has the repl run it
+struct PickHandler;
+
+impl ConditionalEventHandler for PickHandler {
+    fn handle(
+        &self,
+        _evt: &Event,
+        _n: RepeatCount,
+        _positive: bool,
+        _ctx: &EventContext,
+    ) -> Option<RustylineCmd> {
+        crate::command::pick::request_pick();
+        Some(RustylineCmd::Interrupt)
+    }
+}
+
 // see comment on ClickCompleter::new for why a raw pointer is needed
-fn get_editor(
-    config: rustyconfig::Config,
-    hist_path: &Path,
-) -> Editor<ClickHelper, DefaultHistory> {
+fn get_editor(config: rustyconfig::Config) -> Editor<ClickHelper, DefaultHistory> {
     let mut rl =
         Editor::<ClickHelper, DefaultHistory>::with_config(config).expect("Could not make editor");
     rl.set_helper(Some(ClickHelper::new(
@@ -134,60 +196,80 @@ fn get_editor(
             "pipes",
             "redirection",
             "ranges",
+            "views",
         ],
     )));
-    rl.load_history(hist_path).unwrap_or_default();
+    rl.bind_sequence(
+        KeyEvent::ctrl('t'),
+        EventHandler::Conditional(Box::new(PickHandler)),
+    );
     rl
 }
 
 pub struct CommandProcessor {
     env: Rc<Env>,
     rl: Editor<ClickHelper, DefaultHistory>,
-    hist_path: PathBuf,
+    // the context whose history is loaded into rl, or None if all history is
+    rl_history_context: Option<String>,
     commands: Vec<Box<dyn Cmd>>,
 }
 
 impl CommandProcessor {
-    pub fn new(env: Env, hist_path: PathBuf) -> CommandProcessor {
+    pub fn new(mut env: Env, hist_path: PathBuf) -> CommandProcessor {
         let commands = CommandProcessor::get_command_vec();
+        env.set_history(History::from_file(&hist_path));
         let env = Rc::new(env);
-        let rl = get_editor(env.get_rustyline_conf(), &hist_path);
-        CommandProcessor {
+        let rl = get_editor(env.get_rustyline_conf());
+        let mut processor = CommandProcessor {
             env,
             rl,
-            hist_path,
+            rl_history_context: None,
             commands,
-        }
+        };
+        processor.load_history();
+        processor
     }
 
     #[cfg(test)]
-    fn new_with_commands(
-        env: Env,
-        hist_path: PathBuf,
-        commands: Vec<Box<dyn Cmd>>,
-    ) -> CommandProcessor {
+    fn new_with_commands(env: Env, commands: Vec<Box<dyn Cmd>>) -> CommandProcessor {
         let env = Rc::new(env);
-        let rl = get_editor(env.get_rustyline_conf(), &hist_path);
+        let rl = get_editor(env.get_rustyline_conf());
         CommandProcessor {
             env,
             rl,
-            hist_path,
+            rl_history_context: None,
             commands,
         }
     }
 
-    fn get_command_vec() -> Vec<Box<dyn Cmd>> {
+    /// Load the history for the current context (or all history if it's not kept per context) into
+    /// the editor, so it's available with up-arrow and search
+    fn load_history(&mut self) {
+        let _ = self.rl.clear_history();
+        let context = self.env.history_context();
+        for (_, entry) in self.env.get_history().iter_for(context) {
+            let _ = self.rl.add_history_entry(entry.line.as_str());
+        }
+        self.rl_history_context = context.map(|c| c.to_string());
+    }
+
+    pub(crate) fn get_command_vec() -> Vec<Box<dyn Cmd>> {
         let commands: Vec<Box<dyn Cmd>> = vec![
+            Box::new(crate::command::across::Across::new()),
             Box::new(crate::command::alias::Alias::new()),
             Box::new(crate::command::alias::Unalias::new()),
+            Box::new(crate::command::auth::CanI::new()),
+            Box::new(crate::command::auth::WhoAmI::new()),
             Box::new(crate::command::click::As::new()),
             Box::new(crate::command::click::Clear::new()),
             Box::new(crate::command::click::Context::new()),
             Box::new(crate::command::click::Contexts::new()),
             Box::new(crate::command::click::EnvCmd::new()),
+            Box::new(crate::command::click::HistoryCmd::new()),
             Box::new(crate::command::click::Quit::new()),
             Box::new(crate::command::click::Range::new()),
             Box::new(crate::command::click::Last::new()),
+            Box::new(crate::command::click::Let::new()),
             Box::new(crate::command::click::SetCmd::new()),
             Box::new(crate::command::click::UnSetCmd::new()),
             Box::new(crate::command::click::UtcCmd::new()),
@@ -201,11 +283,15 @@ impl CommandProcessor {
             Box::new(crate::command::describe::Describe::new()),
             Box::new(crate::command::events::Events::new()),
             Box::new(crate::command::exec::Exec::new()),
+            Box::new(crate::command::find::Find::new()),
             Box::new(crate::command::jobs::Jobs::new()),
+            Box::new(crate::command::kubeconfig::ConfigCmd::new()),
+            Box::new(crate::command::kubeconfig::ExportKubeconfig::new()),
             Box::new(crate::command::logs::Logs::new()),
             Box::new(crate::command::namespaces::Namespace::new()),
             Box::new(crate::command::namespaces::Namespaces::new()),
             Box::new(crate::command::nodes::Nodes::new()),
+            Box::new(crate::command::pick::Pick::new()),
             Box::new(crate::command::pods::Containers::new()),
             Box::new(crate::command::pods::Pods::new()),
             Box::new(crate::command::portforwards::PortForward::new()),
@@ -215,6 +301,7 @@ impl CommandProcessor {
             Box::new(crate::command::services::Services::new()),
             Box::new(crate::command::statefulsets::StatefulSets::new()),
             Box::new(crate::command::storage::StorageClasses::new()),
+            Box::new(crate::command::ui::UiCmd::new()),
             Box::new(crate::command::volumes::PersistentVolumes::new()),
             #[cfg(feature = "argorollouts")]
             Box::new(crate::command::rollouts::Rollouts::new()),
@@ -226,10 +313,17 @@ impl CommandProcessor {
         while !self.env.quit {
             let mut writer = ClickWriter::new();
             if self.env.need_new_editor {
-                self.rl = get_editor(self.env.get_rustyline_conf(), &self.hist_path);
+                self.rl = get_editor(self.env.get_rustyline_conf());
                 Rc::get_mut(&mut self.env).unwrap().need_new_editor = false;
+                self.load_history();
+            } else if self.env.history_context() != self.rl_history_context.as_deref() {
+                // context changed or context_history was toggled
+                self.load_history();
             }
 
+            // refresh the prompt, it can show the time
+            Rc::get_mut(&mut self.env).unwrap().set_prompt();
+
             // we set and unset the pointer to the env in the helper here so the get_mut below works
             let helper_env = Some(self.env.clone());
             if let Some(h) = self.rl.helper_mut() {
@@ -243,7 +337,12 @@ impl CommandProcessor {
                 Ok(line) => {
                     self.process_line(line.as_str(), writer);
                 }
-                Err(ReadlineError::Interrupted) => {} // don't exit on Ctrl-C
+                Err(ReadlineError::Interrupted) => {
+                    // don't exit on Ctrl-C, but the pick key binding interrupts too
+                    if crate::command::pick::take_pick_request() {
+                        self.execute_line("pick", &mut writer);
+                    }
+                }
                 Err(ReadlineError::Eof) => {
                     // Ctrl-D
                     break;
@@ -256,9 +355,6 @@ impl CommandProcessor {
         }
         let env = Rc::get_mut(&mut self.env).unwrap();
         env.save_click_config();
-        if let Err(e) = self.rl.save_history(self.hist_path.as_path()) {
-            println!("Couldn't save command history: {e}");
-        }
         env.stop_all_forwards();
     }
 
@@ -274,110 +370,196 @@ impl CommandProcessor {
             }
             first_non_whitespace += 1;
         }
-        let lstr = if first_non_whitespace == 0 {
+        let lstr = &line[first_non_whitespace..];
+        let expanded = match self.env.expand_history(lstr) {
+            Ok(expanded) => expanded,
+            Err(e) => {
+                clickwriteln!(writer, "{}", e);
+                return writer.finish_output();
+            }
+        };
+        let lstr = match expanded {
+            Some(ref expanded) => {
+                // like bash, show what's actually going to run
+                clickwriteln!(writer, "{}", expanded);
+                expanded.as_str()
+            }
+            None => lstr,
+        };
+        if first_non_whitespace == 0 {
             // bash semantics: don't add to history if start with space
-            if let Err(e) = self.rl.add_history_entry(line) {
+            if let Err(e) = self.rl.add_history_entry(lstr) {
                 println!("Couldn't write history entry: {}", e);
             }
-            line
-        } else {
-            &line[first_non_whitespace..]
-        };
-        let expanded_line = alias_expand_line(&self.env, lstr);
-        match parse_line(&expanded_line) {
+            if let Err(e) = Rc::get_mut(&mut self.env).unwrap().add_history_entry(lstr) {
+                println!("Couldn't write history entry: {}", e);
+            }
+        }
+        self.execute_line(lstr, &mut writer);
+        writer.finish_output()
+    }
+
+    /// Run each command in the line. Variables, command substitutions and aliases are expanded
+    /// in each command just before it runs, so they see the effects of the commands before them
+    fn execute_line(&mut self, line: &str, writer: &mut ClickWriter) {
+        for cmd in split_commands(line) {
+            let var_expanded = match expand_line(cmd, |expansion| match expansion {
+                Expansion::Variable(name) => self.env.get_variable(name).unwrap_or_default(),
+                Expansion::Command(cmd) => self.capture_output(cmd),
+            }) {
+                Ok(expanded) => expanded,
+                Err(e) => {
+                    clickwriteln!(writer, "{}", ClickError::ParseErr(e));
+                    return;
+                }
+            };
+            // an alias can expand to more than one command
+            let alias_expanded = alias_expand_line(&self.env, &var_expanded);
+            for cmd in split_commands(&alias_expanded) {
+                self.process_command(cmd.trim(), writer);
+            }
+        }
+    }
+
+    /// Run the line and return its output as a string, with any colors removed
+    fn capture_output(&mut self, line: &str) -> String {
+        lazy_static! {
+            static ref ANSI_ESCAPE: Regex = Regex::new("\x1b\\[[0-9;]*[A-Za-z]").unwrap();
+        }
+        let mut writer = ClickWriter::with_buffer(vec![], false);
+        self.execute_line(line, &mut writer);
+        let output = writer.finish_output().unwrap_or_default();
+        ANSI_ESCAPE
+            .replace_all(&String::from_utf8_lossy(&output), "")
+            .into_owned()
+    }
+
+    /// Process a single command (after alias expansion). If the command pipes or redirects its
+    /// output, that's handled here, otherwise output goes to writer
+    fn process_command(&mut self, line: &str, writer: &mut ClickWriter) {
+        match parse_line(line) {
             Ok((left, right)) => {
                 // set up output
+                let mut redir_writer = ClickWriter::new();
                 match right {
                     RightExpr::None => {} // do nothing
                     RightExpr::Pipe(cmd) => {
-                        if let Err(e) = writer.setup_pipe(cmd) {
+                        if let Err(e) = redir_writer.setup_pipe(cmd) {
                             println!("{e}");
-                            return writer.finish_output();
+                            return;
                         }
                     }
                     RightExpr::Redir(filename) => match File::create(filename) {
                         Ok(out_file) => {
-                            writer.set_output_file(out_file);
+                            redir_writer.set_output_file(out_file);
                         }
                         Err(ref e) => {
                             println!("Can't open output file: {e}");
-                            return writer.finish_output();
+                            return;
                         }
                     },
                     RightExpr::Append(filename) => {
                         match OpenOptions::new().append(true).create(true).open(filename) {
                             Ok(out_file) => {
-                                writer.set_output_file(out_file);
+                                redir_writer.set_output_file(out_file);
                             }
                             Err(ref e) => {
                                 println!("Can't open output file: {e}");
-                                return writer.finish_output();
+                                return;
                             }
                         }
                     }
                 }
-                let parts_vec: Vec<String> = Parser::new(left).map(|x| x.2).collect();
-                let mut parts = parts_vec.iter().map(|s| &**s);
-                let env = Rc::get_mut(&mut self.env).unwrap();
-                if let Some(cmdstr) = parts.next() {
-                    // There was something typed
-                    if let Ok(num) = (cmdstr as &str).parse::<usize>() {
-                        env.set_current(num);
-                    } else if let Some(range) = try_parse_range(cmdstr) {
-                        // Switch to this when map_while is stable
-                        // let objs: Vec<KObj> =
-                        //     range.map_while(|i| env.item_at(*i).clone()).collect();
-                        let mut objs = vec![];
-                        for i in range {
-                            match env.item_at(i) {
-                                Some(obj) => objs.push(obj.clone()),
-                                None => break,
-                            }
-                        }
-                        if objs.is_empty() {
-                            env.clear_current();
-                        } else {
-                            env.set_range(objs);
-                        }
-                    } else if let Some(range) = try_parse_csl(left) {
-                        // parse whole thing before sep since we might type "1, 2, 3" with spaces
-                        let objs: Vec<KObj> =
-                            range.filter_map(|i| env.item_at(i).cloned()).collect();
-                        if objs.is_empty() {
-                            env.clear_current();
-                        } else {
-                            env.set_range(objs);
-                        }
-                    } else if let Some(cmd) = self.commands.iter().find(|&c| c.is(cmdstr)) {
-                        // found a matching command
-                        if let Err(e) = cmd.exec(env, &mut parts, &mut writer) {
-                            match e {
-                                ClickError::Reqwest(_, Some(val)) => {
-                                    let reason = val_str("/reason", &val, "no reason given");
-                                    let msg = val_str("/message", &val, "no message returned");
-                                    clickwriteln!(
-                                        writer,
-                                        "Error executing request. Reason: {}, Message: {}",
-                                        reason,
-                                        msg
-                                    )
-                                }
-                                _ => clickwriteln!(writer, "{}", e),
-                            };
-                        }
-                    } else if cmdstr == "help" {
-                        self.show_help(&mut parts, &mut writer);
-                    } else {
-                        clickwriteln!(writer, "Unknown command");
-                    }
+                if self.pages_output(left) {
+                    writer
+                        .start_paging(Pager::from_setting(self.env.click_config.pager.as_deref()));
                 }
-
-                // reset output
-                writer.finish_output()
+                if right == RightExpr::None {
+                    self.run_command(left, writer);
+                } else {
+                    self.run_command(left, &mut redir_writer);
+                    // reset output
+                    redir_writer.finish_output_into(writer);
+                }
+                writer.finish_paging();
             }
             Err(err) => {
                 println!("{err}");
-                None
+            }
+        }
+    }
+
+    /// If the output of the command in left can be held back for the pager. Commands that take
+    /// over the terminal or stream output until they're stopped can't be. Commands that only do
+    /// so with some options (like logs -f) call stop_paging themselves.
+    fn pages_output(&self, left: &str) -> bool {
+        match Parser::new(left).next() {
+            Some((_, _, cmdstr)) => !self
+                .commands
+                .iter()
+                .find(|c| c.is(&cmdstr))
+                .map(|c| UNPAGED_COMMANDS.contains(&c.get_name()))
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+
+    fn run_command(&mut self, left: &str, writer: &mut ClickWriter) {
+        let parts_vec: Vec<String> = Parser::new(left).map(|x| x.2).collect();
+        let mut parts = parts_vec.iter().map(|s| &**s);
+        let env = Rc::get_mut(&mut self.env).unwrap();
+        if let Some(cmdstr) = parts.next() {
+            // There was something typed
+            if let Ok(num) = (cmdstr as &str).parse::<usize>() {
+                env.set_current(num);
+            } else if let Some(range) = try_parse_range(cmdstr) {
+                // Switch to this when map_while is stable
+                // let objs: Vec<KObj> =
+                //     range.map_while(|i| env.item_at(*i).clone()).collect();
+                let mut objs = vec![];
+                for i in range {
+                    match env.item_at(i) {
+                        Some(obj) => objs.push(obj.clone()),
+                        None => break,
+                    }
+                }
+                if objs.is_empty() {
+                    env.clear_current();
+                } else {
+                    env.set_range(objs);
+                }
+            } else if let Some(range) = try_parse_csl(left) {
+                // parse whole thing before sep since we might type "1, 2, 3" with spaces
+                let objs: Vec<KObj> = range.filter_map(|i| env.item_at(i).cloned()).collect();
+                if objs.is_empty() {
+                    env.clear_current();
+                } else {
+                    env.set_range(objs);
+                }
+            } else if let Some(cmd) = self.commands.iter().find(|&c| c.is(cmdstr)) {
+                // found a matching command
+                let result = cmd.exec(env, &mut parts, writer);
+                env.set_last_status(result.is_ok());
+                if let Err(e) = result {
+                    match e {
+                        ClickError::Reqwest(_, Some(val)) => {
+                            let reason = val_str("/reason", &val, "no reason given");
+                            let msg = val_str("/message", &val, "no message returned");
+                            clickwriteln!(
+                                writer,
+                                "Error executing request. Reason: {}, Message: {}",
+                                reason,
+                                msg
+                            )
+                        }
+                        _ => clickwriteln!(writer, "{}", e),
+                    };
+                }
+            } else if cmdstr == "help" {
+                self.show_help(&mut parts, writer);
+            } else {
+                env.set_last_status(false);
+                clickwriteln!(writer, "Unknown command");
             }
         }
     }
@@ -402,6 +584,9 @@ impl CommandProcessor {
                     "ranges" => {
                         clickwriteln!(writer, "{}", RANGEHELP);
                     }
+                    "views" => {
+                        clickwriteln!(writer, "{}", VIEWSHELP);
+                    }
                     _ => {
                         if let Some(alias) = self.env.get_alias(hcmd) {
                             clickwriteln!(writer, "{} is an alias for '{}'", hcmd, alias.expanded);
@@ -450,6 +635,11 @@ impl CommandProcessor {
                 "  shell               Redirecting and piping click \
                  output to shell commands"
             );
+            clickwriteln!(
+                writer,
+                "  views               Saving the columns, selectors, and sorting \
+                 for list commands"
+            );
         }
     }
 }
@@ -478,6 +668,42 @@ associated editor.
 - 'emacs' Use standard readline/bash/emacs keybindings (do: 'set edit_mode emacs')";
 
 // TODO: Something better than raw escapes maybe?
+static VIEWSHELP: &str = "\u{001b}[33;1mVIEWS\u{001b}[0m
+Views save the options you'd otherwise pass to a list command (like 'pods') every time. They are
+defined in the 'views' section of your click.config, and used by passing @name to the list command:
+
+pods @team
+
+Options passed on the command line are combined with the view's.
+
+\u{001b}[33;1mDEFINING VIEWS\u{001b}[0m
+Each view has a name and the following fields (only 'type' is required):
+
+type            The resource type the view lists, as it appears in api urls (i.e. pods,
+                deployments, storageclasses, persistentvolumes)
+columns         Extra columns to show, as would be passed to --show
+extra_columns   Columns click doesn't know about. Each has a 'title', and a json 'pointer' to
+                the value in the object
+label_selector  Only list objects whose labels match this selector
+field_selector  Only list objects whose fields match this selector
+regex           Only list objects matching this regex
+sort            The column to sort by
+reverse         Reverse the order of the list
+default         Use this view whenever its type is listed without a view. Pass --no-view to
+                skip it
+
+\u{001b}[33mExample:\u{001b}[0m
+views:
+  team:
+    type: pods
+    columns: [node, ip]
+    extra_columns:
+      - title: QoS
+        pointer: /status/qosClass
+    label_selector: team=foo
+    sort: restarts
+    reverse: true";
+
 static RANGEHELP: &str = "\u{001b}[33;1mRANGES\u{001b}[0m
 Ranges are used to operate on more than one object at a time.
 
@@ -614,7 +840,6 @@ mod tests {
                 ClickConfig::default(),
                 PathBuf::from("/tmp/click.conf"),
             ),
-            PathBuf::from("/tmp/click.test.hist"),
             commands,
         )
     }
@@ -624,6 +849,7 @@ mod tests {
             name: name.to_string(),
             namespace: None,
             typ: ObjType::Node,
+            context: None,
         }
     }
 
@@ -656,7 +882,8 @@ Other help topics (type 'help [TOPIC]' for details)
   completion          Available completion_type values for the 'set' command, and what they mean
   edit_mode           Available edit_mode values for the 'set' command, and what they mean
   ranges              Selecting and operating on multiple objects at once
-  shell               Redirecting and piping click output to shell commands\n"
+  shell               Redirecting and piping click output to shell commands
+  views               Saving the columns, selectors, and sorting for list commands\n"
                 .as_bytes()
         );
     }
@@ -695,11 +922,7 @@ Other help topics (type 'help [TOPIC]' for details)
         );
         let nodelist = vec![make_node_kobj("ns1")];
         env.set_last_objs(nodelist, None);
-        let mut p = CommandProcessor::new_with_commands(
-            env,
-            PathBuf::from("/tmp/click.test.hist"),
-            commands,
-        );
+        let mut p = CommandProcessor::new_with_commands(env, commands);
         p.process_line("0", ClickWriter::new());
         assert_eq!(
             p.env.current_selection(),
@@ -707,6 +930,7 @@ Other help topics (type 'help [TOPIC]' for details)
                 name: "ns1".to_string(),
                 namespace: None,
                 typ: ObjType::Node,
+                context: None,
             })
         );
 
@@ -728,11 +952,7 @@ Other help topics (type 'help [TOPIC]' for details)
         let nodelist = vec![node1, node2, node3];
 
         env.set_last_objs(nodelist, None);
-        let mut p = CommandProcessor::new_with_commands(
-            env,
-            PathBuf::from("/tmp/click.test.hist"),
-            commands,
-        );
+        let mut p = CommandProcessor::new_with_commands(env, commands);
 
         p.process_line("0..=1", ClickWriter::new());
         assert_eq!(
@@ -902,6 +1122,32 @@ Other help topics (type 'help [TOPIC]' for details)
         assert_eq!(p.rl.history().len(), 0);
     }
 
+    #[test]
+    fn history_expansion() {
+        let mut p = get_processor();
+        p.process_line("testcmd a", ClickWriter::with_buffer(vec![], false));
+        p.process_line("testcmd b", ClickWriter::with_buffer(vec![], false));
+
+        let out = p
+            .process_line("!!", ClickWriter::with_buffer(vec![], false))
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "testcmd b\nCalled with b");
+
+        let out = p
+            .process_line("!0", ClickWriter::with_buffer(vec![], false))
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "testcmd a\nCalled with a");
+
+        let out = p
+            .process_line("!nope", ClickWriter::with_buffer(vec![], false))
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "!nope: event not found\n");
+
+        // the expanded command is what goes into history
+        assert_eq!(p.env.get_history().get(3).unwrap().line, "testcmd a");
+        assert!(p.env.get_history().get(4).is_none());
+    }
+
     #[test]
     fn test_alias_expand_line() {
         let mut cc = ClickConfig::default();
@@ -956,5 +1202,136 @@ Other help topics (type 'help [TOPIC]' for details)
         assert_eq!(alias_expand_line(&env, "y x"), "xpand x");
 
         assert_eq!(alias_expand_line(&env, "z x"), "xpand arg x");
+
+        assert_eq!(
+            alias_expand_line(&env, "pn; z"),
+            "pods --sort node; xpand arg"
+        );
+    }
+
+    #[test]
+    fn test_alias_expand_params() {
+        let mut cc = ClickConfig::default();
+        cc.aliases.push(Alias {
+            alias: "lg".to_string(),
+            expanded: "logs -c ${1:-app} -t 100".to_string(),
+        });
+        cc.aliases.push(Alias {
+            alias: "both".to_string(),
+            expanded: "lg $1; lg $2".to_string(),
+        });
+        cc.aliases.push(Alias {
+            alias: "a".to_string(),
+            expanded: "b $@".to_string(),
+        });
+        cc.aliases.push(Alias {
+            alias: "b".to_string(),
+            expanded: "a $@".to_string(),
+        });
+        let env = Env::new(get_test_config(), cc, PathBuf::from("/tmp/click.config"));
+
+        assert_eq!(alias_expand_line(&env, "lg"), "logs -c app -t 100");
+        assert_eq!(alias_expand_line(&env, "lg side"), "logs -c side -t 100");
+        assert_eq!(
+            alias_expand_line(&env, "lg side | grep foo"),
+            "logs -c side -t 100 | grep foo"
+        );
+        assert_eq!(
+            alias_expand_line(&env, "lg side > /tmp/out"),
+            "logs -c side -t 100 > /tmp/out"
+        );
+        assert_eq!(
+            alias_expand_line(&env, "both x y"),
+            "logs -c x -t 100; logs -c y -t 100"
+        );
+        // mutually recursive aliases stop expanding
+        assert_eq!(alias_expand_line(&env, "a 1 2"), "a 1 2");
+    }
+
+    #[test]
+    fn variables() {
+        let mut p = get_processor();
+        Rc::get_mut(&mut p.env)
+            .unwrap()
+            .set_variable("V", "value".to_string());
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p.process_line("testcmd $V", writer).unwrap();
+        assert_eq!(res, "Called with value".as_bytes());
+
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p.process_line("testcmd '$V'", writer).unwrap();
+        assert_eq!(res, "Called with $V".as_bytes());
+
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p.process_line("testcmd $(testcmd $V)", writer).unwrap();
+        assert_eq!(res, "Called with Called".as_bytes());
+
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p.process_line("testcmd \"$(testcmd)\"", writer).unwrap();
+        assert_eq!(res, "Called with Called with no args".as_bytes());
+    }
+
+    #[test]
+    fn variable_set_earlier_in_line() {
+        let commands: Vec<Box<dyn Cmd>> = vec![
+            Box::new(TestCmd),
+            Box::new(crate::command::click::Let::new()),
+        ];
+        let mut p = CommandProcessor::new_with_commands(
+            Env::new(
+                get_test_config(),
+                ClickConfig::default(),
+                PathBuf::from("/tmp/click.conf"),
+            ),
+            commands,
+        );
+        Rc::get_mut(&mut p.env)
+            .unwrap()
+            .set_variable("X", "old".to_string());
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p.process_line("let X = new; testcmd $X", writer).unwrap();
+        assert!(String::from_utf8(res).unwrap().ends_with("Called with new"));
+
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p
+            .process_line("let Y = $(testcmd a; testcmd b); testcmd $Y", writer)
+            .unwrap();
+        assert!(String::from_utf8(res)
+            .unwrap()
+            .ends_with("Called with Called"));
+        assert_eq!(
+            p.env.get_variable("Y").as_deref(),
+            Some("Called with aCalled with b")
+        );
+    }
+
+    #[test]
+    fn unpaged_commands() {
+        let commands: Vec<Box<dyn Cmd>> = vec![
+            Box::new(TestCmd),
+            Box::new(crate::command::exec::Exec::new()),
+        ];
+        let p = CommandProcessor::new_with_commands(
+            Env::new(
+                get_test_config(),
+                ClickConfig::default(),
+                PathBuf::from("/tmp/click.conf"),
+            ),
+            commands,
+        );
+        assert!(p.pages_output("testcmd a"));
+        assert!(p.pages_output("unknown"));
+        assert!(!p.pages_output("exec bash"));
+    }
+
+    #[test]
+    fn multiple_commands() {
+        let mut p = get_processor();
+        let buf = Vec::new();
+        let writer = ClickWriter::with_buffer(buf, false);
+        let res = p
+            .process_line("testcmd one; testcmd 'two;three'", writer)
+            .unwrap();
+        assert_eq!(res, "Called with oneCalled with two;three".as_bytes());
     }
 }
@@ -12,19 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use k8s_openapi::{api::core::v1 as api, List};
 use rustyline::{
     completion::{Completer, Pair},
     highlight::Highlighter,
     hint::Hinter,
-    validate::Validator,
+    history::SearchDirection,
+    validate::{ValidationContext, ValidationResult, Validator},
     Context, Helper, Result,
 };
 
 use crate::command::command_def::Cmd;
+use crate::completion_cache::CacheKey;
 use crate::env::Env;
 use crate::kobj::ObjType;
+use crate::parser::{
+    command_word_ranges, split_commands, try_parse_csl, try_parse_range, unclosed_quote,
+};
 
+use std::borrow::Cow;
 use std::rc::Rc;
 
 pub struct ClickHelper {
@@ -34,16 +39,87 @@ pub struct ClickHelper {
     command_completions: Vec<String>,
 }
 
+/// What the first word of a command typed at the prompt is
+#[derive(Debug, PartialEq)]
+enum CommandWord {
+    Command,
+    Alias,
+    Unknown,
+}
+
 impl Helper for ClickHelper {}
 
-impl Highlighter for ClickHelper {}
+impl Highlighter for ClickHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let env = match self.env.as_ref() {
+            Some(env) => env,
+            None => return Cow::Borrowed(line),
+        };
+        let mut highlighted = String::with_capacity(line.len());
+        let mut last = 0;
+        for range in command_word_ranges(line).into_iter() {
+            highlighted.push_str(&line[last..range.start]);
+            let word = &line[range.clone()];
+            match self.command_word(word) {
+                Some(CommandWord::Command) => {
+                    highlighted.push_str(&env.styles.input_command(word).to_string())
+                }
+                Some(CommandWord::Alias) => {
+                    highlighted.push_str(&env.styles.input_alias(word).to_string())
+                }
+                Some(CommandWord::Unknown) => {
+                    highlighted.push_str(&env.styles.input_unknown(word).to_string())
+                }
+                None => highlighted.push_str(word),
+            }
+            last = range.end;
+        }
+        highlighted.push_str(&line[last..]);
+        Cow::Owned(highlighted)
+    }
 
-impl Validator for ClickHelper {}
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        match self.env.as_ref() {
+            Some(env) => Cow::Owned(env.styles.input_hint(hint).to_string()),
+            None => Cow::Borrowed(hint),
+        }
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        // the command word can change from unknown to known with any character typed
+        true
+    }
+}
+
+impl Validator for ClickHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> Result<ValidationResult> {
+        Ok(match unclosed_quote(ctx.input()) {
+            Some(quote) => ValidationResult::Invalid(Some(format!("  [missing closing {quote}]"))),
+            None => ValidationResult::Valid(None),
+        })
+    }
+}
 
 impl Hinter for ClickHelper {
     type Hint = String;
-    fn hint(&self, _line: &str, _pos: usize, _context: &Context) -> Option<String> {
-        None
+    /// Hint the most recent history entry that starts with line, or if there isn't one, the first
+    /// flag that completes the one being typed
+    fn hint(&self, line: &str, pos: usize, ctx: &Context) -> Option<String> {
+        if line.is_empty() || pos < line.len() {
+            return None;
+        }
+        let history = ctx.history();
+        let start = if ctx.history_index() == history.len() {
+            ctx.history_index().saturating_sub(1)
+        } else {
+            ctx.history_index()
+        };
+        match history.starts_with(line, start, SearchDirection::Reverse) {
+            Ok(Some(found)) if found.entry.len() > line.len() => {
+                Some(found.entry[line.len()..].to_string())
+            }
+            _ => self.flag_hint(line),
+        }
     }
 }
 
@@ -86,6 +162,52 @@ impl ClickHelper {
         self.commands.iter().find(|&cmd| cmd.is(line))
     }
 
+    /// Figure out what the first word of a command is. Returns None for things that aren't
+    /// commands but are valid, like selecting an object by number or a history reference
+    fn command_word(&self, word: &str) -> Option<CommandWord> {
+        if word == "help" || self.get_exact_command(word).is_some() {
+            Some(CommandWord::Command)
+        } else if self
+            .env
+            .as_ref()
+            .map(|env| env.click_config.aliases.iter().any(|a| a.alias == word))
+            .unwrap_or(false)
+        {
+            Some(CommandWord::Alias)
+        } else if word.starts_with('!')
+            || word.parse::<usize>().is_ok()
+            || try_parse_range(word).is_some()
+            || try_parse_csl(word).is_some()
+        {
+            None
+        } else {
+            Some(CommandWord::Unknown)
+        }
+    }
+
+    /// If the last thing in line is a partly typed long flag, return the rest of the first flag
+    /// of the command that it could complete to
+    fn flag_hint(&self, line: &str) -> Option<String> {
+        let prefix = line
+            .rsplit(char::is_whitespace)
+            .next()
+            .and_then(|word| word.strip_prefix("--"))
+            .filter(|prefix| !prefix.is_empty())?;
+        let expanded = self
+            .env
+            .as_ref()
+            .map(|env| crate::command_processor::alias_expand_line(env, line));
+        let last_cmd = split_commands(expanded.as_deref().unwrap_or(line))
+            .pop()?
+            .split_whitespace()
+            .next()?;
+        let cmd = self.get_exact_command(last_cmd)?;
+        cmd.complete_option(prefix)
+            .into_iter()
+            .map(|pair| pair.replacement.trim_end().to_string())
+            .find(|rest| !rest.is_empty())
+    }
+
     /// complete a line that starts with a full command. This should only be called when we know
     /// that the string contains a space. cmd_len is the length of the string the user has typed,
     /// which can be different than `line.len()` due to alias expansion.
@@ -235,11 +357,15 @@ impl Completer for ClickHelper {
             // we do have a space, so now see if the first thing typed is a command, and
             // complete on it
 
-            // if possible, turn an alias into a real command
-            let expanded = self
-                .env
-                .as_ref()
-                .map(|e| crate::command_processor::alias_expand_line(e, line));
+            // if possible, turn an alias into a real command. parameterized aliases consume their
+            // args, so there's no sensible way to complete after expanding them
+            let expanded =
+                self.env
+                    .as_ref()
+                    .and_then(|e| match e.try_expand_alias(line, &[]).expansion {
+                        Some(alias) if alias.is_parameterized() => None,
+                        _ => Some(crate::command_processor::alias_expand_line(e, line)),
+                    });
             Ok(self.complete_exact_command(expanded.as_deref().unwrap_or(line), line.len()))
         } else {
             // no command with space, so just complete commands
@@ -264,30 +390,79 @@ pub fn context_complete(prefix: &str, env: &Env) -> Vec<Pair> {
     v
 }
 
-pub fn namespace_completer(prefix: &str, env: &Env) -> Vec<Pair> {
-    let (request, _response_body) = api::Namespace::list_namespace(Default::default()).unwrap();
-    match env.run_on_context::<_, List<api::Namespace>>(|c| {
-        c.execute_list(env.get_impersonate_user(), request)
-    }) {
-        Ok(nslist) => nslist
-            .items
-            .into_iter()
-            .filter_map(|ns| {
-                ns.metadata.name.and_then(|name| {
-                    if let Some(rep) = name.strip_prefix(prefix) {
-                        let replacement = rep.to_string();
-                        Some(Pair {
-                            display: name,
-                            replacement,
-                        })
-                    } else {
-                        None
-                    }
-                })
+/// Complete saved views, which are passed as @name
+pub fn view_completer(prefix: &str, env: &Env) -> Vec<Pair> {
+    env.click_config
+        .views
+        .keys()
+        .map(|name| format!("@{name}"))
+        .filter_map(|view| {
+            view.strip_prefix(prefix).map(|rest| Pair {
+                replacement: rest.to_string(),
+                display: view.clone(),
             })
-            .collect(),
-        Err(_) => vec![],
-    }
+        })
+        .collect()
+}
+
+// turn cached values that start with prefix into completions
+fn cached_completions(values: Option<Vec<String>>, prefix: &str) -> Vec<Pair> {
+    values
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|value| {
+            value.strip_prefix(prefix).map(|rest| Pair {
+                replacement: rest.to_string(),
+                display: value.clone(),
+            })
+        })
+        .collect()
+}
+
+pub fn namespace_completer(prefix: &str, env: &Env) -> Vec<Pair> {
+    cached_completions(env.completion_values(CacheKey::Namespaces), prefix)
+}
+
+macro_rules! names_completer {
+    ($name: ident, $resource: expr, $group_version: expr) => {
+        /// Complete names of objects in the current namespace
+        pub fn $name(prefix: &str, env: &Env) -> Vec<Pair> {
+            let key = CacheKey::Names {
+                resource: $resource,
+                group_version: $group_version,
+                namespace: env.namespace.clone(),
+            };
+            cached_completions(env.completion_values(key), prefix)
+        }
+    };
+}
+
+names_completer!(pod_name_completer, "pods", "v1");
+names_completer!(deployment_name_completer, "deployments", "apps/v1");
+names_completer!(service_name_completer, "services", "v1");
+
+/// Complete the key of the last label in a selector like `app=web,tier`
+pub fn label_key_completer(prefix: &str, env: &Env) -> Vec<Pair> {
+    let key_prefix = prefix.rsplit(',').next().unwrap_or(prefix);
+    let key = CacheKey::LabelKeys {
+        namespace: env.namespace.clone(),
+    };
+    cached_completions(env.completion_values(key), key_prefix)
+}
+
+/// Complete the ports of the selected pod. If a local port has been given (as in `8080:`), the
+/// pod port after the colon is completed
+pub fn port_completer(prefix: &str, env: &Env) -> Vec<Pair> {
+    let pod = match env.current_pod() {
+        Some(pod) => pod,
+        None => return vec![],
+    };
+    let key = CacheKey::Ports {
+        namespace: pod.namespace.clone().unwrap_or_default(),
+        pod: pod.name.clone(),
+    };
+    let port_prefix = prefix.rsplit(':').next().unwrap_or(prefix);
+    cached_completions(env.completion_values(key), port_prefix)
 }
 
 pub fn container_completer(prefix: &str, env: &Env) -> Vec<Pair> {
@@ -334,3 +509,77 @@ possible_values_completer!(
     portforwardaction_values_completer,
     ["list", "output", "stop"]
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_processor::CommandProcessor;
+    use crate::config::{get_test_config, Alias, ClickConfig};
+
+    use std::path::PathBuf;
+
+    fn test_helper() -> ClickHelper {
+        let mut click_config = ClickConfig::default();
+        click_config.aliases.push(Alias {
+            alias: "pn".to_string(),
+            expanded: "pods --sort node".to_string(),
+        });
+        let env = Env::new(
+            get_test_config(),
+            click_config,
+            PathBuf::from("/tmp/click.config"),
+        );
+        let mut helper = ClickHelper::new(CommandProcessor::get_command_vec(), vec![]);
+        helper.set_env(Some(Rc::new(env)));
+        helper
+    }
+
+    #[test]
+    fn test_command_word() {
+        let helper = test_helper();
+        assert_eq!(helper.command_word("pods"), Some(CommandWord::Command));
+        assert_eq!(helper.command_word("help"), Some(CommandWord::Command));
+        assert_eq!(helper.command_word("pn"), Some(CommandWord::Alias));
+        assert_eq!(helper.command_word("podz"), Some(CommandWord::Unknown));
+        assert_eq!(helper.command_word("3"), None);
+        assert_eq!(helper.command_word("1..4"), None);
+        assert_eq!(helper.command_word("!!"), None);
+    }
+
+    #[test]
+    fn test_flag_hint() {
+        let helper = test_helper();
+        assert_eq!(helper.flag_hint("pods --sho"), Some("w".to_string()));
+        assert_eq!(helper.flag_hint("pn --rev"), Some("erse".to_string()));
+        assert_eq!(helper.flag_hint("nodes; pods --sho"), Some("w".to_string()));
+        assert_eq!(helper.flag_hint("pods --"), None);
+        assert_eq!(helper.flag_hint("pods --show "), None);
+        assert_eq!(helper.flag_hint("podz --sho"), None);
+    }
+
+    #[test]
+    fn test_cached_completions() {
+        let values = Some(vec![
+            "kube-public".to_string(),
+            "kube-system".to_string(),
+            "default".to_string(),
+        ]);
+        let completions = cached_completions(values, "kube-s");
+        assert_eq!(completions.len(), 1);
+        assert_eq!(completions[0].display, "kube-system");
+        assert_eq!(completions[0].replacement, "ystem");
+        assert!(cached_completions(None, "").is_empty());
+    }
+
+    #[test]
+    fn test_highlight() {
+        let helper = test_helper();
+        let styles = &helper.env.as_ref().unwrap().styles;
+        let expected = format!(
+            "{} -l a; {} | grep x",
+            styles.input_command("pods"),
+            styles.input_unknown("podz")
+        );
+        assert_eq!(helper.highlight("pods -l a; podz | grep x", 0), expected);
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A cache of the data completers need from the api server. Fetching happens on a background
+//! thread, so hitting Tab never blocks on a slow cluster for longer than LOOKUP_TIMEOUT. Entries
+//! older than ENTRY_TTL are still returned, but trigger a refresh so the next lookup gets new data.
+//!
+//! The cache belongs to one context (and impersonation). When either changes, everything
+//! cached is dropped, and the background thread exits.
+
+use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::List;
+
+use crate::crd::ListMetadataResponse;
+use crate::env::Env;
+use crate::error::ClickError;
+use crate::k8s::{Context, Impersonation};
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long cached data is considered fresh
+const ENTRY_TTL: Duration = Duration::from_secs(30);
+/// Longest a lookup waits for data that isn't cached yet
+const LOOKUP_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Something that can be cached
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum CacheKey {
+    Namespaces,
+    /// names of objects of the specified resource type, i.e. ("pods", "v1")
+    Names {
+        resource: &'static str,
+        group_version: &'static str,
+        namespace: Option<String>,
+    },
+    /// keys of the labels on pods
+    LabelKeys {
+        namespace: Option<String>,
+    },
+    /// ports exposed by the containers of a pod
+    Ports {
+        namespace: String,
+        pod: String,
+    },
+}
+
+#[derive(Default)]
+struct Entry {
+    values: Option<Vec<String>>,
+    fetched: Option<Instant>,
+    refreshing: bool,
+}
+
+impl Entry {
+    fn is_stale(&self) -> bool {
+        self.fetched
+            .map(|fetched| fetched.elapsed() > ENTRY_TTL)
+            .unwrap_or(true)
+    }
+
+    // still waiting for the first fetch to finish
+    fn is_pending(&self) -> bool {
+        self.refreshing && self.fetched.is_none()
+    }
+}
+
+type Entries = Arc<(Mutex<HashMap<CacheKey, Entry>>, Condvar)>;
+
+/// The cache for one context and impersonation
+struct ContextCache {
+    context: String,
+    impersonation: Option<Impersonation>,
+    entries: Entries,
+    // sends keys to refresh to the background thread, which is started on first use
+    refresher: Option<Sender<CacheKey>>,
+}
+
+impl ContextCache {
+    fn new(context: &str, impersonation: Option<&Impersonation>) -> ContextCache {
+        ContextCache {
+            context: context.to_string(),
+            impersonation: impersonation.cloned(),
+            entries: Arc::new((Mutex::new(HashMap::new()), Condvar::new())),
+            refresher: None,
+        }
+    }
+
+    fn request_refresh(&mut self, context: &Context, key: CacheKey) {
+        let refresher = self.refresher.get_or_insert_with(|| {
+            spawn_refresher(
+                context.background_copy(),
+                self.impersonation.clone(),
+                self.entries.clone(),
+            )
+        });
+        if refresher.send(key).is_err() {
+            // the thread died, start a new one next time
+            self.refresher = None;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CompletionCache {
+    cache: RefCell<Option<ContextCache>>,
+}
+
+impl CompletionCache {
+    /// Get the values for key. Returns cached values right away, even if they are stale (in which
+    /// case they are refreshed in the background). If nothing is cached yet, waits a short time for
+    /// the background fetch. Returns None if there's no current context, or the data couldn't be
+    /// fetched in time.
+    pub fn get(&self, env: &Env, key: CacheKey) -> Option<Vec<String>> {
+        let context = env.context.as_ref()?;
+        let mut cache = self.cache.borrow_mut();
+        let valid = cache
+            .as_ref()
+            .map(|c| {
+                c.context == context.name && c.impersonation.as_ref() == env.get_impersonation()
+            })
+            .unwrap_or(false);
+        if !valid {
+            // dropping the old cache stops its refresh thread
+            *cache = Some(ContextCache::new(&context.name, env.get_impersonation()));
+        }
+        let cache = cache.as_mut().unwrap(); // safe, set above
+        let entries = cache.entries.clone();
+        let (lock, cvar) = &*entries;
+        let mut map = lock.lock().unwrap();
+        let entry = map.entry(key.clone()).or_default();
+        if entry.is_stale() && !entry.refreshing {
+            entry.refreshing = true;
+            cache.request_refresh(context, key.clone());
+        }
+        if map.get(&key).map(|e| e.is_pending()).unwrap_or(false) {
+            map = cvar
+                .wait_timeout_while(map, LOOKUP_TIMEOUT, |map| {
+                    map.get(&key).map(|e| e.is_pending()).unwrap_or(false)
+                })
+                .unwrap()
+                .0;
+        }
+        map.get(&key).and_then(|e| e.values.clone())
+    }
+
+    /// Drop everything that's cached
+    pub fn invalidate(&self) {
+        self.cache.borrow_mut().take();
+    }
+}
+
+// context is a background copy of the current one, so it shares the credentials instead of
+// getting its own
+fn spawn_refresher(
+    context: Context,
+    impersonation: Option<Impersonation>,
+    entries: Entries,
+) -> Sender<CacheKey> {
+    let (sender, receiver) = channel::<CacheKey>();
+    thread::spawn(move || {
+        // exits when the cache is dropped, since that closes the channel
+        while let Ok(key) = receiver.recv() {
+            let values = fetch(&context, impersonation.as_ref(), &key).ok();
+            let (lock, cvar) = &*entries;
+            let mut map = lock.lock().unwrap();
+            let entry = map.entry(key).or_default();
+            if values.is_some() {
+                entry.values = values;
+            }
+            // failures count as fetched, so a broken cluster isn't asked again until the ttl is up
+            entry.fetched = Some(Instant::now());
+            entry.refreshing = false;
+            cvar.notify_all();
+        }
+    });
+    sender
+}
+
+fn fetch(
+    context: &Context,
+    impersonate: Option<&Impersonation>,
+    key: &CacheKey,
+) -> Result<Vec<String>, ClickError> {
+    match key {
+        CacheKey::Namespaces => {
+            let (request, _) = api::Namespace::list_namespace(Default::default())?;
+            let list: List<api::Namespace> = context.execute_list(impersonate, request)?;
+            Ok(list
+                .items
+                .into_iter()
+                .filter_map(|ns| ns.metadata.name)
+                .collect())
+        }
+        CacheKey::Names {
+            resource,
+            group_version,
+            namespace,
+        } => {
+            let names = list_metadata(context, impersonate, resource, group_version, namespace)?
+                .into_iter()
+                .filter_map(|meta| meta.name)
+                .collect::<BTreeSet<String>>();
+            Ok(names.into_iter().collect())
+        }
+        CacheKey::LabelKeys { namespace } => {
+            let keys = list_metadata(context, impersonate, "pods", "v1", namespace)?
+                .into_iter()
+                .flat_map(|meta| meta.labels.unwrap_or_default().into_keys())
+                .collect::<BTreeSet<String>>();
+            Ok(keys.into_iter().collect())
+        }
+        CacheKey::Ports { namespace, pod } => {
+            let (request, _) = api::Pod::read_namespaced_pod(pod, namespace, Default::default())?;
+            match context.read(impersonate, request)? {
+                api::ReadNamespacedPodResponse::Ok(pod) => Ok(pod_ports(&pod)),
+                _ => Err(ClickError::CommandError(format!(
+                    "Could not read pod {pod}"
+                ))),
+            }
+        }
+    }
+}
+
+fn list_metadata(
+    context: &Context,
+    impersonate: Option<&Impersonation>,
+    resource: &str,
+    group_version: &str,
+    namespace: &Option<String>,
+) -> Result<Vec<k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta>, ClickError> {
+    let (request, _) =
+        crate::crd::list_resource_metadata(resource, group_version, namespace.as_deref())?;
+    match context.read(impersonate, request)? {
+        ListMetadataResponse::Ok(list) => Ok(list.items.into_iter().map(|i| i.metadata).collect()),
+        _ => Err(ClickError::CommandError(format!(
+            "Could not list {resource}"
+        ))),
+    }
+}
+
+// the distinct ports exposed by any container in the pod
+fn pod_ports(pod: &api::Pod) -> Vec<String> {
+    let ports: BTreeSet<i32> = pod
+        .spec
+        .iter()
+        .flat_map(|spec| spec.containers.iter())
+        .flat_map(|container| container.ports.iter().flatten())
+        .map(|port| port.container_port)
+        .collect();
+    ports.into_iter().map(|port| port.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pod_ports() {
+        let port = |p: i32| api::ContainerPort {
+            container_port: p,
+            ..Default::default()
+        };
+        let pod = api::Pod {
+            spec: Some(api::PodSpec {
+                containers: vec![
+                    api::Container {
+                        ports: Some(vec![port(8080), port(443)]),
+                        ..Default::default()
+                    },
+                    api::Container {
+                        ports: Some(vec![port(8080)]),
+                        ..Default::default()
+                    },
+                    api::Container::default(),
+                ],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(pod_ports(&pod), vec!["443", "8080"]);
+    }
+
+    #[test]
+    fn test_entry_state() {
+        let mut entry = Entry::default();
+        assert!(entry.is_stale());
+        assert!(!entry.is_pending());
+        entry.refreshing = true;
+        assert!(entry.is_pending());
+        entry.fetched = Some(Instant::now());
+        assert!(!entry.is_stale());
+        assert!(!entry.is_pending());
+    }
+}
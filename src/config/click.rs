@@ -16,6 +16,7 @@
 use atomicwrites::{AllowOverwrite, AtomicFile};
 use rustyline::config as rustyconfig;
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fs::File;
 use std::io::Read;
@@ -28,7 +29,50 @@ pub struct Alias {
     pub expanded: String,
 }
 
-#[derive(Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+impl Alias {
+    /// Does this alias take positional parameters (like $1 or ${2:-default}). If not, it's a plain
+    /// alias that's a prefix substitution
+    pub fn is_parameterized(&self) -> bool {
+        crate::parser::substitute_alias_params(self.expanded.as_str(), &[]).is_some()
+    }
+}
+
+/// A saved set of options for a list command. Used by passing @name to the list command for the
+/// view's type, or automatically if default is set.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct View {
+    /// The resource type this view lists, as it appears in api urls (i.e. pods, storageclasses)
+    #[serde(rename = "type")]
+    pub resource: String,
+    /// Extra columns to show, as would be passed to --show
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+    /// Columns click doesn't know about, extracted from the object with a json pointer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_columns: Vec<ViewColumn>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_selector: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field_selector: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(default)]
+    pub reverse: bool,
+    /// Use this view when listing its type without specifying a view
+    #[serde(default)]
+    pub default: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ViewColumn {
+    pub title: String,
+    /// json pointer (i.e. /status/qosClass) to the value to show
+    pub pointer: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub enum EditMode {
     #[default]
     Emacs,
@@ -54,7 +98,7 @@ impl From<&EditMode> for String {
     }
 }
 
-#[derive(Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
 pub enum CompletionType {
     #[default]
     Circular,
@@ -80,6 +124,40 @@ impl From<&CompletionType> for String {
     }
 }
 
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemePreset {
+    #[default]
+    Dark,
+    Light,
+    Monochrome,
+}
+
+impl fmt::Display for ThemePreset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ThemePreset::Dark => "dark",
+                ThemePreset::Light => "light",
+                ThemePreset::Monochrome => "monochrome",
+            }
+        )
+    }
+}
+
+/// The theme section of the config. Colors override individual styles from the preset, keyed by
+/// style name (like prompt_context or json_key) or object type (like Pod). See styles.rs for the
+/// format of the values
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub preset: ThemePreset,
+    #[serde(default)]
+    pub colors: BTreeMap<String, String>,
+}
+
 pub fn default_range_sep() -> String {
     "--- {name} ---".to_string()
 }
@@ -96,13 +174,14 @@ fn default_describe_include_events() -> bool {
     true
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ClickConfig {
     pub namespace: Option<String>,
     pub context: Option<String>,
     pub editor: Option<String>,
     pub terminal: Option<String>,
     pub kubectl_binary: Option<String>,
+    pub pager: Option<String>,
     #[serde(default = "EditMode::default")]
     pub editmode: EditMode,
     #[serde(default = "CompletionType::default")]
@@ -119,6 +198,22 @@ pub struct ClickConfig {
 
     #[serde(default = "default_describe_include_events")]
     pub describe_include_events: bool,
+
+    #[serde(default)]
+    pub context_history: bool,
+
+    /// keep exec plugin credentials in an encrypted cache across sessions
+    #[serde(default)]
+    pub exec_credential_cache: bool,
+
+    pub prompt_template: Option<String>,
+    pub production_context_pattern: Option<String>,
+
+    #[serde(default)]
+    pub theme: ThemeConfig,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub views: BTreeMap<String, View>,
 }
 
 impl Default for ClickConfig {
@@ -129,6 +224,7 @@ impl Default for ClickConfig {
             editor: None,
             terminal: None,
             kubectl_binary: None,
+            pager: None,
             editmode: EditMode::default(),
             completiontype: CompletionType::default(),
             aliases: vec![],
@@ -136,6 +232,12 @@ impl Default for ClickConfig {
             connect_timeout_secs: default_connect_timeout(),
             read_timeout_secs: default_read_timeout(),
             describe_include_events: true,
+            context_history: false,
+            exec_credential_cache: false,
+            prompt_template: None,
+            production_context_pattern: None,
+            theme: ThemeConfig::default(),
+            views: BTreeMap::new(),
         }
     }
 }
@@ -168,6 +270,28 @@ impl ClickConfig {
         config.build()
     }
 
+    /// Get the view with the specified name, checking that it lists resource
+    pub fn get_view(&self, name: &str, resource: &str) -> Result<&View, ClickError> {
+        let view = self
+            .views
+            .get(name)
+            .ok_or_else(|| ClickError::CommandError(format!("No view named '{name}'")))?;
+        if view.resource != resource {
+            return Err(ClickError::CommandError(format!(
+                "View '{name}' is for {}, not {resource}",
+                view.resource
+            )));
+        }
+        Ok(view)
+    }
+
+    /// The view to use when listing resource without specifying one
+    pub fn default_view(&self, resource: &str) -> Option<&View> {
+        self.views
+            .values()
+            .find(|view| view.default && view.resource == resource)
+    }
+
     /// Save this config to specified path.  It's safe to call this from multiple running instances
     /// of Click, since we use an AtomicFile
     pub fn save_to_file(&self, path: &str) -> Result<(), ClickError> {
@@ -193,7 +317,20 @@ editmode: Vi
 completiontype: List
 aliases:
   - alias: pn
-    expanded: pods --sort node";
+    expanded: pods --sort node
+views:
+  team:
+    type: pods
+    columns: [node, ip]
+    extra_columns:
+      - title: QoS
+        pointer: /status/qosClass
+    label_selector: team=foo
+    sort: restarts
+    reverse: true
+  big:
+    type: persistentvolumes
+    default: true";
 
     pub fn get_parsed_test_click_config() -> ClickConfig {
         ClickConfig::from_reader(TEST_CONFIG.as_bytes()).unwrap()
@@ -220,6 +357,32 @@ aliases:
         assert_eq!(config.read_timeout_secs, default_read_timeout());
     }
 
+    #[test]
+    fn test_views() {
+        let config = get_parsed_test_click_config();
+        let view = config.get_view("team", "pods").unwrap();
+        assert_eq!(view.columns, vec!["node", "ip"]);
+        assert_eq!(
+            view.extra_columns,
+            vec![ViewColumn {
+                title: "QoS".to_string(),
+                pointer: "/status/qosClass".to_string(),
+            }]
+        );
+        assert_eq!(view.label_selector.as_deref(), Some("team=foo"));
+        assert_eq!(view.field_selector, None);
+        assert_eq!(view.sort.as_deref(), Some("restarts"));
+        assert!(view.reverse);
+        assert!(!view.default);
+        assert!(config.get_view("team", "nodes").is_err());
+        assert!(config.get_view("nope", "pods").is_err());
+        assert_eq!(config.default_view("pods"), None);
+        assert_eq!(
+            config.default_view("persistentvolumes"),
+            config.views.get("big")
+        );
+    }
+
     #[test]
     fn test_default_config() {
         let config = ClickConfig::default();
@@ -231,6 +394,26 @@ aliases:
         assert_eq!(config.range_separator, default_range_sep());
     }
 
+    #[test]
+    fn test_parse_theme() {
+        let config = ClickConfig::from_reader(
+            r##"---
+theme:
+  preset: monochrome
+  colors:
+    prompt_context: "red bold"
+    Pod: "#ff8700""##
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(config.theme.preset, ThemePreset::Monochrome);
+        assert_eq!(config.theme.colors.len(), 2);
+        assert_eq!(config.theme.colors["Pod"], "#ff8700");
+        // theme section is optional
+        let config = get_parsed_test_click_config();
+        assert_eq!(config.theme, ThemeConfig::default());
+    }
+
     #[test]
     fn test_invalid_conf() {
         let config = ClickConfig::from_reader("not valid".as_bytes());
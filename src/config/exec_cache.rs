@@ -0,0 +1,250 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An on-disk cache of the credentials exec plugins return. Some plugins (SSO flows in particular)
+//! are slow or need a browser, so when `exec_credential_cache` is set, credentials that come with
+//! an expiration are kept and reused by later click sessions until they expire.
+//!
+//! The cache is encrypted with AES-256-GCM. The key lives in its own file (mode 0600) under the
+//! config dir, away from the cache file under the cache dir, so a copy of the cache alone doesn't
+//! give away any credentials.
+
+use atomicwrites::{AllowOverwrite, AtomicFile};
+use chrono::{DateTime, Local};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::path::PathBuf;
+
+use crate::error::ClickError;
+
+const KEY_LEN: usize = 32;
+
+#[derive(Deserialize, Serialize)]
+struct Entry {
+    expiration: DateTime<Local>,
+    status: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExecCache {
+    path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl ExecCache {
+    pub fn new(path: PathBuf, key_path: PathBuf) -> ExecCache {
+        ExecCache { path, key_path }
+    }
+
+    /// The cache in the user's cache dir, with its key in the user's config dir
+    pub fn default_location() -> Option<ExecCache> {
+        let path = dirs::cache_dir()?.join("click").join("exec-credentials");
+        let key_path = dirs::config_dir()?
+            .join("click")
+            .join("exec-credentials.key");
+        Some(ExecCache::new(path, key_path))
+    }
+
+    /// Get the unexpired status cached for key, if any. A missing, unreadable or undecryptable
+    /// cache is treated as empty.
+    pub fn get(&self, key: &str) -> Option<Value> {
+        let cipher = self.read_key().ok()??;
+        let mut entries = self.read_entries(&cipher).ok()?;
+        entries
+            .remove(key)
+            .filter(|entry| entry.expiration > Local::now())
+            .map(|entry| entry.status)
+    }
+
+    /// Store status under key until expiration. Expired entries are dropped while we're at it.
+    pub fn put(
+        &self,
+        key: &str,
+        status: Value,
+        expiration: DateTime<Local>,
+    ) -> Result<(), ClickError> {
+        let cipher = match self.read_key()? {
+            Some(cipher) => cipher,
+            None => self.create_key()?,
+        };
+        // a cache from an old key can't be read anymore, so just start over
+        let mut entries = self.read_entries(&cipher).unwrap_or_default();
+        let now = Local::now();
+        entries.retain(|_, entry| entry.expiration > now);
+        entries.insert(key.to_string(), Entry { expiration, status });
+        self.write_entries(&cipher, &entries)
+    }
+
+    fn read_key(&self) -> Result<Option<LessSafeKey>, ClickError> {
+        let mut bytes = vec![];
+        match File::open(&self.key_path) {
+            Ok(mut f) => f.read_to_end(&mut bytes)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        make_cipher(&bytes).map(Some)
+    }
+
+    fn create_key(&self) -> Result<LessSafeKey, ClickError> {
+        let mut bytes = [0u8; KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| cache_error("could not generate a key"))?;
+        if let Some(dir) = self.key_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&self.key_path)?.write_all(&bytes)?;
+        make_cipher(&bytes)
+    }
+
+    fn read_entries(&self, cipher: &LessSafeKey) -> Result<BTreeMap<String, Entry>, ClickError> {
+        let mut data = vec![];
+        match File::open(&self.path) {
+            Ok(mut f) => f.read_to_end(&mut data)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let plain = open(cipher, &mut data)?;
+        serde_json::from_slice(plain).map_err(ClickError::from)
+    }
+
+    fn write_entries(
+        &self,
+        cipher: &LessSafeKey,
+        entries: &BTreeMap<String, Entry>,
+    ) -> Result<(), ClickError> {
+        let data = seal(cipher, serde_json::to_vec(entries)?)?;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        AtomicFile::new(&self.path, AllowOverwrite)
+            .write(|f| f.write_all(&data))
+            .map_err(|e| cache_error(&format!("could not write {}: {e}", self.path.display())))
+    }
+}
+
+/// A stable key for the cache, derived from whatever identifies a credential
+pub fn cache_key(identity: &Value) -> String {
+    digest(&SHA256, identity.to_string().as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn cache_error(msg: &str) -> ClickError {
+    ClickError::CommandError(format!("Exec credential cache: {msg}"))
+}
+
+fn make_cipher(bytes: &[u8]) -> Result<LessSafeKey, ClickError> {
+    UnboundKey::new(&AES_256_GCM, bytes)
+        .map(LessSafeKey::new)
+        .map_err(|_| cache_error("invalid key"))
+}
+
+// the sealed data is the random nonce followed by the ciphertext and tag
+fn seal(cipher: &LessSafeKey, mut data: Vec<u8>) -> Result<Vec<u8>, ClickError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| cache_error("could not generate a nonce"))?;
+    cipher
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| cache_error("could not encrypt"))?;
+    let mut sealed = nonce.to_vec();
+    sealed.append(&mut data);
+    Ok(sealed)
+}
+
+fn open<'a>(cipher: &LessSafeKey, data: &'a mut [u8]) -> Result<&'a [u8], ClickError> {
+    if data.len() < NONCE_LEN {
+        return Err(cache_error("cache file is truncated"));
+    }
+    let (nonce, sealed) = data.split_at_mut(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| cache_error("bad nonce"))?;
+    cipher
+        .open_in_place(nonce, Aad::empty(), sealed)
+        .map(|plain| &*plain)
+        .map_err(|_| cache_error("could not decrypt"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempdir::TempDir;
+
+    fn test_cache(dir: &TempDir) -> ExecCache {
+        ExecCache::new(
+            dir.path().join("cache").join("exec-credentials"),
+            dir.path().join("config").join("exec-credentials.key"),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = TempDir::new("click_exec_cache").unwrap();
+        let cache = test_cache(&dir);
+        assert_eq!(cache.get("a"), None);
+
+        let status = json!({"token": "secret-token"});
+        let later = Local::now() + chrono::Duration::hours(1);
+        let earlier = Local::now() - chrono::Duration::hours(1);
+        cache.put("a", status.clone(), later).unwrap();
+        cache.put("b", json!({"token": "old"}), earlier).unwrap();
+        assert_eq!(cache.get("a"), Some(status));
+        assert_eq!(cache.get("b"), None);
+
+        // nothing readable ends up on disk
+        let raw = fs::read(&cache.path).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("secret-token"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&cache.key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // a different key can't read the cache
+        fs::remove_file(&cache.key_path).unwrap();
+        cache.create_key().unwrap();
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let a = cache_key(&json!({"command": "aws", "server": "https://a"}));
+        let b = cache_key(&json!({"command": "aws", "server": "https://b"}));
+        assert_eq!(a.len(), 64);
+        assert_ne!(a, b);
+        assert_eq!(
+            a,
+            cache_key(&json!({"command": "aws", "server": "https://a"}))
+        );
+    }
+}
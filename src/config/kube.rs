@@ -15,6 +15,7 @@
 //! Code to represent the data found in .kube/config files after it's deserialized, validated, and
 //! so on.  Data in here is what gets passed around to the rest of Click.
 
+use atomicwrites::{AllowOverwrite, AtomicFile};
 use base64::engine::{general_purpose::STANDARD, Engine};
 
 use std::collections::BTreeMap;
@@ -22,20 +23,24 @@ use std::collections::HashMap;
 use std::convert::From;
 use std::env;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
 
 //use crate::certs::{get_cert, get_cert_from_pem, get_key_from_str, get_private_key};
-use super::kubefile::{AuthProvider, ExecProvider};
+use super::exec_cache::ExecCache;
+use super::kubefile::{AuthProvider, ExecCluster, ExecProvider};
+use super::proxy::effective_proxy;
 use crate::config::ClickConfig;
 use crate::error::{ClickErrNo, ClickError};
 use crate::k8s::UserAuth as K8SUserAuth;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ClusterConf {
     pub cert: Option<String>,
     pub server: String,
     pub tls_server_name: Option<String>,
     pub insecure_skip_tls_verify: bool,
+    pub proxy_url: Option<String>,
 }
 
 impl ClusterConf {
@@ -49,9 +54,15 @@ impl ClusterConf {
             server,
             tls_server_name,
             insecure_skip_tls_verify: true,
+            proxy_url: None,
         }
     }
 
+    fn with_proxy_url(mut self, proxy_url: Option<String>) -> ClusterConf {
+        self.proxy_url = proxy_url;
+        self
+    }
+
     fn new_secure(
         cert: Option<String>,
         server: String,
@@ -62,6 +73,7 @@ impl ClusterConf {
             server,
             tls_server_name,
             insecure_skip_tls_verify: false,
+            proxy_url: None,
         }
     }
 }
@@ -70,22 +82,29 @@ impl ClusterConf {
 
 /// KeyCert can be either raw data from a "client-*-data" field, or a path to a file with the data
 /// from a "client-*" field.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum UserAuth {
     Token(String),
+    TokenFile(String),
     KeyCertPath(String, String),
     KeyCertData(String, String),
     UserPass(String, String),
     AuthProvider(Box<AuthProvider>),
-    ExecProvider(ExecProvider),
+    ExecProvider(Box<ExecProvider>),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct UserConf {
     impersonate_user: Option<String>,
     auths: Vec<UserAuth>,
 }
 
+impl UserConf {
+    pub(super) fn auths(&self) -> &[UserAuth] {
+        &self.auths
+    }
+}
+
 impl From<super::kubefile::UserConf> for UserConf {
     fn from(conf: super::kubefile::UserConf) -> UserConf {
         let mut auth_vec = vec![];
@@ -93,6 +112,9 @@ impl From<super::kubefile::UserConf> for UserConf {
         if let Some(token) = conf.token {
             auth_vec.push(UserAuth::Token(token))
         }
+        if let Some(token_file) = conf.token_file {
+            auth_vec.push(UserAuth::TokenFile(token_file))
+        }
         if let (Some(username), Some(password)) = (conf.username, conf.password) {
             auth_vec.push(UserAuth::UserPass(username, password))
         }
@@ -108,7 +130,9 @@ impl From<super::kubefile::UserConf> for UserConf {
             auth_vec.push(UserAuth::AuthProvider(Box::new(auth_provider)))
         }
         if let Some(exec_conf) = conf.exec {
-            auth_vec.push(UserAuth::ExecProvider(ExecProvider::new(exec_conf)))
+            auth_vec.push(UserAuth::ExecProvider(Box::new(ExecProvider::new(
+                exec_conf,
+            ))))
         }
         UserConf {
             impersonate_user: conf.impersonate_user,
@@ -119,19 +143,43 @@ impl From<super::kubefile::UserConf> for UserConf {
 
 /// A kubernetes config
 // This is actual config we expose
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Config {
     pub source_file: String,
     pub clusters: HashMap<String, ClusterConf>,
     pub contexts: BTreeMap<String, super::kubefile::ContextConf>,
     pub users: HashMap<String, UserConf>,
+    pub sources: ConfigSources,
+}
+
+/// Which files a config was loaded from, and which file each entry came from, so changes can be
+/// written back to the right place
+#[derive(Clone, Debug, Default)]
+pub struct ConfigSources {
+    pub files: Vec<String>,
+    pub clusters: HashMap<String, String>,
+    pub contexts: HashMap<String, String>,
+    pub users: HashMap<String, String>,
+}
+
+impl ConfigSources {
+    /// The file new entries go in, like kubectl this is the first one
+    pub fn primary_file(&self) -> Option<&str> {
+        self.files.first().map(|f| f.as_str())
+    }
 }
 
+/// The name of the context click makes when running in a pod
+pub const IN_CLUSTER_CONTEXT: &str = "in-cluster";
+
+/// Where a pod's service account token, namespace and CA are mounted
+pub const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
 // some utility functions
-fn get_full_path(path: String) -> Result<String, ClickError> {
+pub(super) fn get_full_path(path: String) -> Result<String, ClickError> {
     if path.is_empty() {
         return Err(ClickError::ConfigFileError(
-            "Empty certificate/key path".to_owned(),
+            "Empty certificate/key/token path".to_owned(),
         ));
     }
     // unwrap okay, validated above
@@ -159,7 +207,27 @@ impl Config {
             Ok(srcs) => srcs,
             Err(_) => "[config paths contain non-utf8 characters, cannot be displayed]".to_string(),
         };
-        Config::from_configs(iconfs, sources)
+        let mut config = Config::from_configs(iconfs, sources)?;
+        config.sources.files = paths.to_vec();
+        Ok(config)
+    }
+
+    /// Load the same files again, i.e. after they've been changed. Includes the in-cluster
+    /// context if running in a pod.
+    pub fn reload(&self) -> Result<Config, ClickError> {
+        let mut config = match Config::from_files(&self.sources.files) {
+            Ok(config) => config,
+            Err(_) if self.contexts.contains_key(IN_CLUSTER_CONTEXT) => {
+                let mut config = Config::empty(self.source_file.clone());
+                config.sources.files = self.sources.files.clone();
+                config
+            }
+            Err(e) => return Err(e),
+        };
+        if Config::in_cluster_available() {
+            config.add_in_cluster_context(Path::new(SERVICE_ACCOUNT_DIR))?;
+        }
+        Ok(config)
     }
 
     fn from_configs(
@@ -197,7 +265,8 @@ impl Config {
                                         Some(s),
                                         cluster.conf.server.clone(),
                                         cluster.conf.tls_server_name.clone(),
-                                    ),
+                                    )
+                                    .with_proxy_url(cluster.conf.proxy_url.clone()),
                                 );
                             }
                             Err(e) => {
@@ -224,7 +293,8 @@ impl Config {
                                     Some(cert_pem),
                                     cluster.conf.server.clone(),
                                     cluster.conf.tls_server_name.clone(),
-                                ),
+                                )
+                                .with_proxy_url(cluster.conf.proxy_url.clone()),
                             );
                         }
                         Err(e) => {
@@ -238,12 +308,14 @@ impl Config {
                                 cluster.conf.server.clone(),
                                 cluster.conf.tls_server_name.clone(),
                             )
+                            .with_proxy_url(cluster.conf.proxy_url.clone())
                         } else {
                             ClusterConf::new_secure(
                                 None,
                                 cluster.conf.server.clone(),
                                 cluster.conf.tls_server_name.clone(),
                             )
+                            .with_proxy_url(cluster.conf.proxy_url.clone())
                         };
 
                         cluster_map.insert(cluster.name.clone(), conf);
@@ -268,14 +340,205 @@ impl Config {
             }
         }
 
+        // remember where everything came from. later files win, as they do above
+        let mut sources = ConfigSources::default();
+        for iconf in iconfs.iter() {
+            if let Some(path) = &iconf.path {
+                for cluster in iconf.clusters.iter() {
+                    sources.clusters.insert(cluster.name.clone(), path.clone());
+                }
+                for context in iconf.contexts.iter() {
+                    sources.contexts.insert(context.name.clone(), path.clone());
+                }
+                for user in iconf.users.iter() {
+                    sources.users.insert(user.name.clone(), path.clone());
+                }
+            }
+        }
+
         Ok(Config {
             source_file,
             clusters: cluster_map,
             contexts: context_map,
             users: user_map,
+            sources,
         })
     }
 
+    /// A config with nothing in it, for when there's no kubeconfig to load
+    pub fn empty(source_file: String) -> Config {
+        Config {
+            source_file,
+            clusters: HashMap::new(),
+            contexts: BTreeMap::new(),
+            users: HashMap::new(),
+            sources: ConfigSources::default(),
+        }
+    }
+
+    /// True if click is running in a pod, so an in-cluster context can be made
+    pub fn in_cluster_available() -> bool {
+        env::var_os("KUBERNETES_SERVICE_HOST").is_some()
+            && env::var_os("KUBERNETES_SERVICE_PORT").is_some()
+    }
+
+    /// When running in a pod, add an "in-cluster" context that talks to the api server as the
+    /// pod's service account, whose credentials are read from sa_dir. If the kubeconfig already has
+    /// a context, cluster or user named "in-cluster" nothing is added. Returns true if the context
+    /// was added.
+    pub fn add_in_cluster_context(&mut self, sa_dir: &Path) -> Result<bool, ClickError> {
+        match (
+            env::var("KUBERNETES_SERVICE_HOST"),
+            env::var("KUBERNETES_SERVICE_PORT"),
+        ) {
+            (Ok(host), Ok(port)) => self.add_in_cluster(&host, &port, sa_dir),
+            _ => Ok(false),
+        }
+    }
+
+    fn add_in_cluster(
+        &mut self,
+        host: &str,
+        port: &str,
+        sa_dir: &Path,
+    ) -> Result<bool, ClickError> {
+        if self.contexts.contains_key(IN_CLUSTER_CONTEXT)
+            || self.clusters.contains_key(IN_CLUSTER_CONTEXT)
+            || self.users.contains_key(IN_CLUSTER_CONTEXT)
+        {
+            return Ok(false);
+        }
+        let token_path = sa_dir.join("token");
+        if !token_path.exists() {
+            return Err(ClickError::ConfigFileError(format!(
+                "No service account token at {}",
+                token_path.display()
+            )));
+        }
+        // ipv6 addresses need brackets in urls
+        let server = if host.contains(':') {
+            format!("https://[{host}]:{port}")
+        } else {
+            format!("https://{host}:{port}")
+        };
+        let cert = std::fs::read_to_string(sa_dir.join("ca.crt")).ok();
+        let namespace = std::fs::read_to_string(sa_dir.join("namespace"))
+            .ok()
+            .map(|ns| ns.trim().to_string())
+            .filter(|ns| !ns.is_empty());
+
+        self.clusters.insert(
+            IN_CLUSTER_CONTEXT.to_string(),
+            ClusterConf::new_secure(cert, server, None),
+        );
+        self.users.insert(
+            IN_CLUSTER_CONTEXT.to_string(),
+            UserConf {
+                impersonate_user: None,
+                auths: vec![UserAuth::TokenFile(
+                    token_path.to_string_lossy().into_owned(),
+                )],
+            },
+        );
+        self.contexts.insert(
+            IN_CLUSTER_CONTEXT.to_string(),
+            super::kubefile::ContextConf {
+                cluster: IN_CLUSTER_CONTEXT.to_string(),
+                namespace,
+                user: IN_CLUSTER_CONTEXT.to_string(),
+            },
+        );
+        Ok(true)
+    }
+
+    /// The arguments that point kubectl at context. The in-cluster context isn't in any kubeconfig
+    /// file, so for it a kubeconfig is written to click's cache dir and passed with --kubeconfig.
+    pub fn kubectl_context_args(&self, context: &str) -> Result<Vec<String>, ClickError> {
+        let dir = dirs::cache_dir()
+            .map(|dir| dir.join("click"))
+            .ok_or_else(|| {
+                ClickError::ConfigFileError(
+                    "No cache dir to write the in-cluster kubeconfig to".to_string(),
+                )
+            })?;
+        self.kubectl_context_args_in(context, &dir)
+    }
+
+    /// As kubectl_context_args, but the in-cluster kubeconfig is written to dir. The kubeconfig
+    /// refers to the service account's token file rather than holding the token, so the token
+    /// never ends up on kubectl's command line, where any local user could read it.
+    fn kubectl_context_args_in(
+        &self,
+        context: &str,
+        dir: &Path,
+    ) -> Result<Vec<String>, ClickError> {
+        if context != IN_CLUSTER_CONTEXT || self.sources.contexts.contains_key(context) {
+            return Ok(vec!["--context".to_string(), context.to_string()]);
+        }
+        let cluster = self.clusters.get(IN_CLUSTER_CONTEXT).ok_or_else(|| {
+            ClickError::ConfigFileError("No cluster for in-cluster context".to_string())
+        })?;
+        let token_path = self
+            .users
+            .get(IN_CLUSTER_CONTEXT)
+            .and_then(|user| {
+                user.auths.iter().find_map(|auth| match auth {
+                    UserAuth::TokenFile(path) => Some(path),
+                    _ => None,
+                })
+            })
+            .ok_or_else(|| {
+                ClickError::ConfigFileError("No token for in-cluster context".to_string())
+            })?;
+        let mut cluster_entry = serde_json::json!({ "server": cluster.server });
+        if let Some(ca_path) = Path::new(token_path)
+            .parent()
+            .map(|dir| dir.join("ca.crt"))
+            .filter(|path| path.exists())
+        {
+            cluster_entry["certificate-authority"] = ca_path.to_string_lossy().into();
+        }
+        let mut context_entry =
+            serde_json::json!({ "cluster": IN_CLUSTER_CONTEXT, "user": IN_CLUSTER_CONTEXT });
+        if let Some(namespace) = self
+            .contexts
+            .get(IN_CLUSTER_CONTEXT)
+            .and_then(|context| context.namespace.as_ref())
+        {
+            context_entry["namespace"] = namespace.as_str().into();
+        }
+        let kubeconfig = serde_json::json!({
+            "apiVersion": "v1",
+            "kind": "Config",
+            "clusters": [{ "name": IN_CLUSTER_CONTEXT, "cluster": cluster_entry }],
+            "users": [{ "name": IN_CLUSTER_CONTEXT, "user": { "tokenFile": token_path } }],
+            "contexts": [{ "name": IN_CLUSTER_CONTEXT, "context": context_entry }],
+            "current-context": IN_CLUSTER_CONTEXT,
+        });
+        let yaml = serde_yaml::to_string(&kubeconfig)?;
+
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join("in-cluster.kubeconfig");
+        AtomicFile::new(&path, AllowOverwrite)
+            .write(|f| {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    f.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+                }
+                f.write_all(yaml.as_bytes())
+            })
+            .map_err(|e| {
+                ClickError::ConfigFileError(format!("Failed to write {}: {e}", path.display()))
+            })?;
+        Ok(vec![
+            "--kubeconfig".to_string(),
+            path.to_string_lossy().into_owned(),
+            "--context".to_string(),
+            IN_CLUSTER_CONTEXT.to_string(),
+        ])
+    }
+
     pub fn get_context(
         &self,
         context_name: &str,
@@ -302,6 +565,8 @@ impl Config {
             endpoint.set_host(Some(tls_server_name))?;
         }
 
+        let proxy = effective_proxy(cluster.proxy_url.as_deref(), &cluster.server)?;
+
         let ca_certs = match &cluster.cert {
             Some(cert) => {
                 let reqwest_certs = get_reqwest_certs(cert)?;
@@ -322,6 +587,9 @@ impl Config {
                 UserAuth::Token(token) => {
                     k8suser = K8SUserAuth::with_token(token.to_string());
                 }
+                UserAuth::TokenFile(path) => {
+                    k8suser = K8SUserAuth::with_token_file(get_full_path(path.clone())?);
+                }
                 UserAuth::UserPass(username, password) => {
                     k8suser =
                         K8SUserAuth::with_user_pass(username.to_string(), password.to_string());
@@ -330,7 +598,22 @@ impl Config {
                     k8suser = K8SUserAuth::with_auth_provider(*provider.clone());
                 }
                 UserAuth::ExecProvider(provider) => {
-                    k8suser = K8SUserAuth::with_exec_provider(provider.clone());
+                    let mut provider = *provider.clone();
+                    provider.set_cluster(ExecCluster {
+                        server: cluster.server.clone(),
+                        tls_server_name: cluster.tls_server_name.clone(),
+                        insecure_skip_tls_verify: cluster.insecure_skip_tls_verify,
+                        certificate_authority_data: cluster
+                            .cert
+                            .as_ref()
+                            .map(|cert| STANDARD.encode(cert)),
+                    });
+                    if click_conf.exec_credential_cache {
+                        if let Some(cache) = ExecCache::default_location() {
+                            provider.set_cache(cache);
+                        }
+                    }
+                    k8suser = K8SUserAuth::with_exec_provider(provider);
                 }
                 UserAuth::KeyCertData(cert_data, key_data) => {
                     k8suser = K8SUserAuth::from_key_cert_data(key_data.clone(), cert_data.clone());
@@ -354,6 +637,7 @@ impl Config {
                 click_conf.read_timeout_secs,
                 cluster.server.clone(),
                 cluster.tls_server_name.clone(),
+                proxy,
             )
         })
     }
@@ -436,6 +720,7 @@ bW1EDp3zdHSo1TRJ6V6e6bR64eVaH4QwnNOfpSXY
             clusters: HashMap::new(),
             contexts: BTreeMap::new(),
             users: HashMap::new(),
+            sources: ConfigSources::default(),
         }
     }
 
@@ -489,4 +774,108 @@ bW1EDp3zdHSo1TRJ6V6e6bR64eVaH4QwnNOfpSXY
         // When TLS server name is provided, the endpoint should use the TLS server name -- custom resolver will resolve to the correct IP
         assert!(ctx.endpoint.host_str() == Some("api.example.com"));
     }
+
+    #[test]
+    fn in_cluster_context() {
+        let dir = tempdir::TempDir::new("click_in_cluster").unwrap();
+        std::fs::write(dir.path().join("token"), "sa-token\n").unwrap();
+        std::fs::write(dir.path().join("namespace"), "tools").unwrap();
+
+        let mut conf = Config::empty("".to_string());
+        assert!(conf.add_in_cluster("10.0.0.1", "443", dir.path()).unwrap());
+        // only added once
+        assert!(!conf.add_in_cluster("10.0.0.1", "443", dir.path()).unwrap());
+
+        let context = conf.contexts.get(IN_CLUSTER_CONTEXT).unwrap();
+        assert_eq!(context.namespace.as_deref(), Some("tools"));
+        assert_eq!(
+            conf.clusters.get(IN_CLUSTER_CONTEXT).unwrap().server,
+            "https://10.0.0.1:443"
+        );
+        let click_conf = crate::config::click::tests::get_parsed_test_click_config();
+        let ctx = conf.get_context(IN_CLUSTER_CONTEXT, &click_conf).unwrap();
+        assert_eq!(ctx.endpoint.as_str(), "https://10.0.0.1/");
+
+        let mut conf = Config::empty("".to_string());
+        conf.add_in_cluster("fd00::1", "6443", dir.path()).unwrap();
+        assert_eq!(
+            conf.clusters.get(IN_CLUSTER_CONTEXT).unwrap().server,
+            "https://[fd00::1]:6443"
+        );
+
+        // kubectl gets a kubeconfig for the context, that names the token file rather than
+        // holding the token
+        let kubeconfig_dir = dir.path().join("cache");
+        let args = conf
+            .kubectl_context_args_in(IN_CLUSTER_CONTEXT, &kubeconfig_dir)
+            .unwrap();
+        let kubeconfig_path = kubeconfig_dir.join("in-cluster.kubeconfig");
+        assert_eq!(
+            args,
+            vec![
+                "--kubeconfig",
+                kubeconfig_path.to_str().unwrap(),
+                "--context",
+                IN_CLUSTER_CONTEXT
+            ]
+        );
+        assert!(!args.iter().any(|arg| arg.contains("sa-token")));
+        let kubeconfig = std::fs::read_to_string(&kubeconfig_path).unwrap();
+        assert!(!kubeconfig.contains("sa-token"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&kubeconfig_path)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let written =
+            Config::from_files(&[kubeconfig_path.to_string_lossy().into_owned()]).unwrap();
+        assert_eq!(
+            written.clusters.get(IN_CLUSTER_CONTEXT).unwrap().server,
+            "https://[fd00::1]:6443"
+        );
+        assert!(matches!(
+            written.users.get(IN_CLUSTER_CONTEXT).unwrap().auths.as_slice(),
+            [UserAuth::TokenFile(path)] if path == dir.path().join("token").to_str().unwrap()
+        ));
+        assert!(!kubeconfig.contains("certificate-authority"));
+
+        std::fs::write(dir.path().join("ca.crt"), "cert").unwrap();
+        conf.kubectl_context_args_in(IN_CLUSTER_CONTEXT, &kubeconfig_dir)
+            .unwrap();
+        let kubeconfig = std::fs::read_to_string(&kubeconfig_path).unwrap();
+        assert!(kubeconfig.contains(&format!(
+            "certificate-authority: {}",
+            dir.path().join("ca.crt").display()
+        )));
+        assert_eq!(
+            conf.kubectl_context_args("prod").unwrap(),
+            vec!["--context", "prod"]
+        );
+
+        // existing entries with the name are never overwritten
+        for existing in ["cluster", "user"] {
+            let mut conf = Config::empty("".to_string());
+            conf.add_in_cluster("10.0.0.1", "443", dir.path()).unwrap();
+            conf.contexts.clear();
+            if existing == "cluster" {
+                conf.users.clear();
+            } else {
+                conf.clusters.clear();
+            }
+            assert!(!conf.add_in_cluster("10.0.0.1", "443", dir.path()).unwrap());
+            assert!(conf.contexts.is_empty());
+        }
+
+        // no token, no context
+        let empty = tempdir::TempDir::new("click_in_cluster_empty").unwrap();
+        let mut conf = Config::empty("".to_string());
+        assert!(conf
+            .add_in_cluster("10.0.0.1", "443", empty.path())
+            .is_err());
+        assert!(conf.contexts.is_empty());
+    }
 }
@@ -0,0 +1,701 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Changing kubeconfig files. Edits are made to the yaml as it was read, rather than to the parsed
+//! config, so fields click doesn't know about and the order of keys are kept. Files are written
+//! atomically, so a failed write never leaves a half written kubeconfig behind.
+
+use atomicwrites::{AllowOverwrite, AtomicFile};
+use base64::engine::{general_purpose::STANDARD, Engine};
+use serde_yaml::{Mapping, Value};
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+
+use super::kube::get_full_path;
+use crate::error::ClickError;
+
+// fields that name a file, and the field that holds the same data inline (base64 encoded)
+const CLUSTER_FILES: [(&str, &str); 1] = [("certificate-authority", "certificate-authority-data")];
+const USER_FILES: [(&str, &str); 2] = [
+    ("client-certificate", "client-certificate-data"),
+    ("client-key", "client-key-data"),
+];
+
+/// The named lists in a kubeconfig
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Section {
+    Clusters,
+    Contexts,
+    Users,
+}
+
+impl Section {
+    fn list_key(self) -> &'static str {
+        match self {
+            Section::Clusters => "clusters",
+            Section::Contexts => "contexts",
+            Section::Users => "users",
+        }
+    }
+
+    // the key each entry keeps its settings under
+    fn entry_key(self) -> &'static str {
+        match self {
+            Section::Clusters => "cluster",
+            Section::Contexts => "context",
+            Section::Users => "user",
+        }
+    }
+
+    /// The name of one entry, i.e. "context"
+    pub fn singular(self) -> &'static str {
+        self.entry_key()
+    }
+}
+
+/// What merging another kubeconfig in did
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MergeResult {
+    pub added: Vec<String>,
+    /// entries that were already present, and so were left alone
+    pub skipped: Vec<String>,
+    /// contexts that weren't added because they use a cluster or user that was skipped, and so
+    /// would have used the existing one with that name instead
+    pub conflicts: Vec<String>,
+}
+
+pub struct KubeconfigFile {
+    pub path: String,
+    doc: Value,
+}
+
+impl KubeconfigFile {
+    /// Read the file at path. A file that doesn't exist yet is treated as an empty kubeconfig.
+    pub fn load(path: &str) -> Result<KubeconfigFile, ClickError> {
+        let doc = match fs::read_to_string(path) {
+            Ok(contents) => serde_yaml::from_str(&contents)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Value::Null,
+            Err(e) => return Err(e.into()),
+        };
+        KubeconfigFile::from_value(path, doc)
+    }
+
+    fn from_value(path: &str, doc: Value) -> Result<KubeconfigFile, ClickError> {
+        let doc = match doc {
+            Value::Mapping(_) => doc,
+            Value::Null => {
+                let mut map = Mapping::new();
+                map.insert("apiVersion".into(), "v1".into());
+                map.insert("kind".into(), "Config".into());
+                // click (and kubectl) need all the lists to be present
+                for section in [Section::Clusters, Section::Contexts, Section::Users] {
+                    map.insert(section.list_key().into(), Value::Sequence(vec![]));
+                }
+                Value::Mapping(map)
+            }
+            _ => {
+                return Err(ClickError::ConfigFileError(format!(
+                    "{path} is not a kubeconfig file"
+                )))
+            }
+        };
+        Ok(KubeconfigFile {
+            path: path.to_string(),
+            doc,
+        })
+    }
+
+    /// Write the file back, keeping its permissions. New files are only readable by their owner,
+    /// since kubeconfigs usually hold credentials.
+    pub fn save(&self) -> Result<(), ClickError> {
+        let permissions = fs::metadata(&self.path).map(|m| m.permissions()).ok();
+        let yaml = self.to_yaml()?;
+        AtomicFile::new(&self.path, AllowOverwrite)
+            .write(|f| {
+                // set on the temporary file before anything is written to it, so the credentials
+                // are never readable by anyone else, even before the rename
+                match permissions {
+                    Some(permissions) => f.set_permissions(permissions)?,
+                    None => {
+                        #[cfg(unix)]
+                        {
+                            use std::os::unix::fs::PermissionsExt;
+                            f.set_permissions(fs::Permissions::from_mode(0o600))?;
+                        }
+                    }
+                }
+                f.write_all(yaml.as_bytes())
+            })
+            .map_err(|e| {
+                ClickError::ConfigFileError(format!("Failed to write {}: {e}", self.path))
+            })?;
+        Ok(())
+    }
+
+    pub fn to_yaml(&self) -> Result<String, ClickError> {
+        serde_yaml::to_string(&self.doc).map_err(ClickError::from)
+    }
+
+    fn entries(&self, section: Section) -> &[Value] {
+        self.doc
+            .get(section.list_key())
+            .and_then(|list| list.as_sequence())
+            .map(|list| list.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // the list for section, created if it's missing
+    fn entries_mut(&mut self, section: Section) -> Result<&mut Vec<Value>, ClickError> {
+        let doc = self.doc.as_mapping_mut().unwrap(); // safe, checked in from_value
+        let list = doc
+            .entry(section.list_key().into())
+            .or_insert(Value::Sequence(vec![]));
+        if list.is_null() {
+            *list = Value::Sequence(vec![]);
+        }
+        list.as_sequence_mut().ok_or_else(|| {
+            ClickError::ConfigFileError(format!(
+                "{} in {} is not a list",
+                section.list_key(),
+                self.path
+            ))
+        })
+    }
+
+    fn position(&self, section: Section, name: &str) -> Option<usize> {
+        self.entries(section)
+            .iter()
+            .position(|entry| entry.get("name").and_then(|n| n.as_str()) == Some(name))
+    }
+
+    pub fn contains(&self, section: Section, name: &str) -> bool {
+        self.position(section, name).is_some()
+    }
+
+    fn not_found(&self, section: Section, name: &str) -> ClickError {
+        ClickError::ConfigFileError(format!(
+            "No {} named {name} in {}",
+            section.singular(),
+            self.path
+        ))
+    }
+
+    /// The settings of the named entry, i.e. the "cluster" mapping of a cluster
+    pub fn settings(&self, section: Section, name: &str) -> Option<&Mapping> {
+        let index = self.position(section, name)?;
+        self.entries(section)[index]
+            .get(section.entry_key())
+            .and_then(|settings| settings.as_mapping())
+    }
+
+    // the settings of the named entry, i.e. the "context" mapping of a context
+    fn settings_mut(&mut self, section: Section, name: &str) -> Result<&mut Mapping, ClickError> {
+        let index = self
+            .position(section, name)
+            .ok_or_else(|| self.not_found(section, name))?;
+        let path = self.path.clone();
+        // safe, position found a "name" in it
+        let entry = self.entries_mut(section)?[index].as_mapping_mut().unwrap();
+        let settings = entry
+            .entry(section.entry_key().into())
+            .or_insert(Value::Mapping(Mapping::new()));
+        if settings.is_null() {
+            *settings = Value::Mapping(Mapping::new());
+        }
+        settings.as_mapping_mut().ok_or_else(|| {
+            ClickError::ConfigFileError(format!(
+                "{} {name} in {path} is not a mapping",
+                section.singular()
+            ))
+        })
+    }
+
+    fn current_context(&self) -> Option<&str> {
+        self.doc.get("current-context").and_then(|c| c.as_str())
+    }
+
+    fn set_current_context(&mut self, context: Option<&str>) {
+        let doc = self.doc.as_mapping_mut().unwrap(); // safe, checked in from_value
+        match context {
+            Some(context) => {
+                doc.insert("current-context".into(), context.into());
+            }
+            None => {
+                doc.remove("current-context");
+            }
+        }
+    }
+
+    /// Set (or with None, remove) the default namespace of a context
+    pub fn set_namespace(
+        &mut self,
+        context: &str,
+        namespace: Option<&str>,
+    ) -> Result<(), ClickError> {
+        let settings = self.settings_mut(Section::Contexts, context)?;
+        match namespace {
+            Some(namespace) => {
+                settings.insert("namespace".into(), namespace.into());
+            }
+            None => {
+                settings.remove("namespace");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rename_context(&mut self, old: &str, new: &str) -> Result<(), ClickError> {
+        if self.contains(Section::Contexts, new) {
+            return Err(ClickError::ConfigFileError(format!(
+                "A context named {new} already exists in {}",
+                self.path
+            )));
+        }
+        let index = self
+            .position(Section::Contexts, old)
+            .ok_or_else(|| self.not_found(Section::Contexts, old))?;
+        self.entries_mut(Section::Contexts)?[index]
+            .as_mapping_mut()
+            .unwrap() // safe, position found a "name" in it
+            .insert("name".into(), new.into());
+        if self.current_context() == Some(old) {
+            self.set_current_context(Some(new));
+        }
+        Ok(())
+    }
+
+    /// Delete a context. The cluster and user it refers to are left alone, since other contexts
+    /// might use them.
+    pub fn delete_context(&mut self, name: &str) -> Result<(), ClickError> {
+        let index = self
+            .position(Section::Contexts, name)
+            .ok_or_else(|| self.not_found(Section::Contexts, name))?;
+        self.entries_mut(Section::Contexts)?.remove(index);
+        if self.current_context() == Some(name) {
+            self.set_current_context(None);
+        }
+        Ok(())
+    }
+
+    pub fn add_context(
+        &mut self,
+        name: &str,
+        cluster: &str,
+        user: &str,
+        namespace: Option<&str>,
+    ) -> Result<(), ClickError> {
+        if self.contains(Section::Contexts, name) {
+            return Err(ClickError::ConfigFileError(format!(
+                "A context named {name} already exists in {}",
+                self.path
+            )));
+        }
+        let mut settings = Mapping::new();
+        settings.insert("cluster".into(), cluster.into());
+        settings.insert("user".into(), user.into());
+        if let Some(namespace) = namespace {
+            settings.insert("namespace".into(), namespace.into());
+        }
+        let mut entry = Mapping::new();
+        entry.insert("name".into(), name.into());
+        entry.insert("context".into(), Value::Mapping(settings));
+        self.entries_mut(Section::Contexts)?
+            .push(Value::Mapping(entry));
+        Ok(())
+    }
+
+    /// Set fields of a user's credentials, i.e. ("token", "abc"). The user is created if it
+    /// doesn't exist. Fields not mentioned are left as they were.
+    pub fn set_credentials(
+        &mut self,
+        user: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<(), ClickError> {
+        if !self.contains(Section::Users, user) {
+            let mut entry = Mapping::new();
+            entry.insert("name".into(), user.into());
+            entry.insert("user".into(), Value::Mapping(Mapping::new()));
+            self.entries_mut(Section::Users)?
+                .push(Value::Mapping(entry));
+        }
+        let settings = self.settings_mut(Section::Users, user)?;
+        for (key, value) in fields.iter() {
+            settings.insert((*key).into(), (*value).into());
+        }
+        Ok(())
+    }
+
+    /// Add the clusters, contexts and users from other. Entries whose name is already taken, here
+    /// or according to exists, are skipped rather than overwritten. Contexts using a skipped
+    /// cluster or user are left out too, since they'd end up using the existing one.
+    pub fn merge<F>(&mut self, other: &KubeconfigFile, exists: F) -> Result<MergeResult, ClickError>
+    where
+        F: Fn(Section, &str) -> bool,
+    {
+        let mut result = MergeResult::default();
+        let mut skipped: Vec<(Section, &str)> = vec![];
+        for section in [Section::Clusters, Section::Users, Section::Contexts] {
+            for entry in other.entries(section) {
+                let name = match entry.get("name").and_then(|n| n.as_str()) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let desc = format!("{} {name}", section.singular());
+                if self.contains(section, name) || exists(section, name) {
+                    result.skipped.push(desc);
+                    skipped.push((section, name));
+                    continue;
+                }
+                let conflict = [(Section::Clusters, "cluster"), (Section::Users, "user")]
+                    .into_iter()
+                    .filter(|_| section == Section::Contexts)
+                    .filter_map(|(used, key)| {
+                        let used_name = entry.get("context")?.get(key)?.as_str()?;
+                        skipped
+                            .contains(&(used, used_name))
+                            .then(|| format!("{} {used_name}", used.singular()))
+                    })
+                    .collect::<Vec<String>>();
+                if !conflict.is_empty() {
+                    result.conflicts.push(format!(
+                        "{desc}, it uses {} which wasn't merged",
+                        conflict.join(" and ")
+                    ));
+                } else {
+                    self.entries_mut(section)?.push(entry.clone());
+                    result.added.push(desc);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// A kubeconfig with just one context, and the cluster and user it uses, which is also the
+    /// current context. path is only used in messages, this isn't meant to be saved.
+    pub fn single_context(
+        path: &str,
+        context: &str,
+        namespace: Option<&str>,
+        cluster: (&str, Mapping),
+        user: (&str, Mapping),
+    ) -> Result<KubeconfigFile, ClickError> {
+        let mut file = KubeconfigFile::from_value(path, Value::Null)?;
+        for (section, name, settings) in [
+            (Section::Clusters, cluster.0, cluster.1),
+            (Section::Users, user.0, user.1),
+        ] {
+            let mut entry = Mapping::new();
+            entry.insert("name".into(), name.into());
+            entry.insert(section.entry_key().into(), Value::Mapping(settings));
+            file.entries_mut(section)?.push(Value::Mapping(entry));
+        }
+        file.add_context(context, cluster.0, user.0, namespace)?;
+        file.set_current_context(Some(context));
+        Ok(file)
+    }
+}
+
+/// Make the settings of a cluster or user usable away from the kubeconfig they came from. Paths to
+/// files are made absolute or, with flatten, replaced by the contents of the files.
+pub fn inline_files(
+    section: Section,
+    settings: &mut Mapping,
+    flatten: bool,
+) -> Result<(), ClickError> {
+    let fields: &[(&str, &str)] = match section {
+        Section::Clusters => &CLUSTER_FILES,
+        Section::Users => &USER_FILES,
+        Section::Contexts => &[],
+    };
+    let read = |path: &str| {
+        fs::read(path)
+            .map_err(|e| ClickError::ConfigFileError(format!("Could not read {path}: {e}")))
+    };
+    for (path_field, data_field) in fields.iter() {
+        if let Some(path) = settings.get(*path_field).and_then(|p| p.as_str()) {
+            let path = get_full_path(path.to_string())?;
+            if flatten {
+                let data = STANDARD.encode(read(&path)?);
+                settings.remove(*path_field);
+                settings.insert((*data_field).into(), data.into());
+            } else {
+                settings.insert((*path_field).into(), path.into());
+            }
+        }
+    }
+    // a token file is read into a plain token, which isn't base64 encoded
+    if let Some(path) = settings.get("tokenFile").and_then(|p| p.as_str()) {
+        let path = get_full_path(path.to_string())?;
+        if flatten {
+            let token = String::from_utf8_lossy(&read(&path)?).trim().to_string();
+            settings.remove("tokenFile");
+            settings.insert("token".into(), token.into());
+        } else {
+            settings.insert("tokenFile".into(), path.into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    static TEST_FILE: &str = r#"apiVersion: v1
+kind: Config
+preferences:
+  colors: true
+clusters:
+- name: prod
+  cluster:
+    server: https://prod.example.com
+    extensions:
+    - name: something-click-ignores
+contexts:
+- name: prod-admin
+  context:
+    user: admin
+    cluster: prod
+    namespace: default
+- name: prod-ro
+  context:
+    cluster: prod
+    user: viewer
+current-context: prod-admin
+users:
+- name: admin
+  user:
+    token: abc
+"#;
+
+    fn test_file() -> KubeconfigFile {
+        KubeconfigFile::from_value("/tmp/test", serde_yaml::from_str(TEST_FILE).unwrap()).unwrap()
+    }
+
+    fn yaml(file: &KubeconfigFile) -> String {
+        serde_yaml::to_string(&file.doc).unwrap()
+    }
+
+    #[test]
+    fn test_unchanged_round_trip() {
+        assert_eq!(yaml(&test_file()), TEST_FILE);
+    }
+
+    #[test]
+    fn test_set_namespace() {
+        let mut file = test_file();
+        file.set_namespace("prod-ro", Some("kube-system")).unwrap();
+        file.set_namespace("prod-admin", None).unwrap();
+        assert!(file.set_namespace("nope", None).is_err());
+        let expected = TEST_FILE.replace("    namespace: default\n", "").replace(
+            "    user: viewer\n",
+            "    user: viewer\n    namespace: kube-system\n",
+        );
+        assert_eq!(yaml(&file), expected);
+    }
+
+    #[test]
+    fn test_rename_and_delete() {
+        let mut file = test_file();
+        assert!(file.rename_context("prod-admin", "prod-ro").is_err());
+        file.rename_context("prod-admin", "admin").unwrap();
+        assert_eq!(
+            yaml(&file),
+            TEST_FILE
+                .replace("- name: prod-admin", "- name: admin")
+                .replace("current-context: prod-admin", "current-context: admin")
+        );
+
+        file.delete_context("admin").unwrap();
+        assert!(!file.contains(Section::Contexts, "admin"));
+        assert!(file.contains(Section::Contexts, "prod-ro"));
+        assert_eq!(file.current_context(), None);
+        // the user and cluster stay
+        assert!(file.contains(Section::Users, "admin"));
+        assert!(file.contains(Section::Clusters, "prod"));
+    }
+
+    #[test]
+    fn test_add_context_and_credentials() {
+        let mut file = KubeconfigFile::from_value("/tmp/new", Value::Null).unwrap();
+        file.add_context("dev", "dev-cluster", "dev-user", Some("dev"))
+            .unwrap();
+        assert!(file.add_context("dev", "x", "y", None).is_err());
+        file.set_credentials("dev-user", &[("token", "t1")])
+            .unwrap();
+        file.set_credentials("dev-user", &[("username", "u")])
+            .unwrap();
+        assert_eq!(
+            yaml(&file),
+            "apiVersion: v1
+kind: Config
+clusters: []
+contexts:
+- name: dev
+  context:
+    cluster: dev-cluster
+    user: dev-user
+    namespace: dev
+users:
+- name: dev-user
+  user:
+    token: t1
+    username: u
+"
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut file = test_file();
+        let other = KubeconfigFile::from_value(
+            "/tmp/other",
+            serde_yaml::from_str(
+                "clusters:
+- name: prod
+  cluster:
+    server: https://other.example.com
+- name: staging
+  cluster:
+    server: https://staging.example.com
+contexts:
+- name: staging
+  context:
+    cluster: staging
+    user: admin
+- name: elsewhere
+  context:
+    cluster: staging
+    user: admin
+- name: other-prod
+  context:
+    cluster: prod
+    user: admin
+",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let result = file
+            .merge(&other, |section, name| {
+                section == Section::Contexts && name == "elsewhere"
+            })
+            .unwrap();
+        assert_eq!(result.added, vec!["cluster staging", "context staging"]);
+        assert_eq!(result.skipped, vec!["cluster prod", "context elsewhere"]);
+        // would have used this file's prod cluster, not the one it was written for
+        assert_eq!(
+            result.conflicts,
+            vec!["context other-prod, it uses cluster prod which wasn't merged"]
+        );
+        assert!(!file.contains(Section::Contexts, "other-prod"));
+        assert_eq!(
+            file.entries(Section::Clusters)[0]["cluster"]["server"],
+            "https://prod.example.com"
+        );
+    }
+
+    #[test]
+    fn test_single_context() {
+        let dir = TempDir::new("click_kubeedit").unwrap();
+        let ca = dir.path().join("ca.crt");
+        let token = dir.path().join("token");
+        fs::write(&ca, "CA").unwrap();
+        fs::write(&token, "secret\n").unwrap();
+        let ca = ca.to_str().unwrap();
+        let token = token.to_str().unwrap();
+
+        let mut cluster = Mapping::new();
+        cluster.insert("server".into(), "https://k8s".into());
+        cluster.insert("certificate-authority".into(), ca.into());
+        let mut user = Mapping::new();
+        user.insert("tokenFile".into(), token.into());
+
+        let mut kept = user.clone();
+        inline_files(Section::Users, &mut kept, false).unwrap();
+        assert_eq!(kept, user);
+
+        inline_files(Section::Clusters, &mut cluster, true).unwrap();
+        inline_files(Section::Users, &mut user, true).unwrap();
+        let file = KubeconfigFile::single_context(
+            "export",
+            "ctx",
+            Some("ns"),
+            ("cluster", cluster),
+            ("user", user),
+        )
+        .unwrap();
+        assert_eq!(
+            file.to_yaml().unwrap(),
+            "apiVersion: v1
+kind: Config
+clusters:
+- name: cluster
+  cluster:
+    server: https://k8s
+    certificate-authority-data: Q0E=
+contexts:
+- name: ctx
+  context:
+    cluster: cluster
+    user: user
+    namespace: ns
+users:
+- name: user
+  user:
+    token: secret
+current-context: ctx
+"
+        );
+        assert_eq!(
+            file.settings(Section::Users, "user").unwrap()["token"],
+            "secret"
+        );
+
+        let mut missing = Mapping::new();
+        missing.insert("client-key".into(), "/nonexistent/key".into());
+        assert!(inline_files(Section::Users, &mut missing, true).is_err());
+    }
+
+    #[test]
+    fn test_save() {
+        let dir = TempDir::new("click_kubeedit").unwrap();
+        let path = dir.path().join("config");
+        let path = path.to_str().unwrap();
+        let mut file = KubeconfigFile::load(path).unwrap();
+        file.add_context("dev", "c", "u", None).unwrap();
+        file.save().unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let file = KubeconfigFile::load(path).unwrap();
+        assert!(file.contains(Section::Contexts, "dev"));
+        // existing files keep their permissions
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o640)).unwrap();
+            file.save().unwrap();
+            let mode = fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+        // and click can read it
+        assert!(crate::config::Config::from_files(&[path.to_string()]).is_ok());
+    }
+}
@@ -12,20 +12,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod check;
 mod click;
+mod exec_cache;
 mod kube;
+mod kubeedit;
 mod kubefile;
+mod proxy;
 
+pub use self::check::{on_path, overall_status, CheckStatus, Finding};
 pub use self::click::default_range_sep;
 pub use self::click::Alias;
 pub use self::click::ClickConfig;
 pub use self::click::CompletionType;
 pub use self::click::EditMode;
+pub use self::click::ThemeConfig;
+pub use self::click::ThemePreset;
+pub use self::click::{View, ViewColumn};
 
 #[cfg(test)]
 pub use self::kube::tests::get_test_config;
 pub use self::kube::Config;
+pub use self::kube::{IN_CLUSTER_CONTEXT, SERVICE_ACCOUNT_DIR};
+pub use self::kubeedit::{inline_files, KubeconfigFile, Section};
 
 pub use self::kubefile::AuthProvider;
 pub use self::kubefile::ContextConf;
 pub use self::kubefile::{ExecAuth, ExecProvider};
+pub use self::proxy::ProxyConfig;
+#[cfg(test)]
+pub use self::proxy::ProxySource;
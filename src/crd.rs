@@ -15,7 +15,7 @@
 // code to deal with discovering and quering endpoints created by crds
 
 use k8s_openapi::{
-    apimachinery::pkg::apis::meta::v1::{APIGroup, APIResourceList},
+    apimachinery::pkg::apis::meta::v1::{APIGroup, APIResourceList, ObjectMeta},
     http::{Request, StatusCode},
     GetAPIVersionsResponse, RequestError, Response, ResponseBody, ResponseError,
 };
@@ -24,9 +24,9 @@ use crate::{env::Env, error::ClickError};
 
 pub fn get_api_groups(env: &mut Env) -> Result<Vec<APIGroup>, ClickError> {
     let (request, _) = k8s_openapi::get_api_versions()?;
-    match env.run_on_context::<_, GetAPIVersionsResponse>(|c| {
-        c.read(env.get_impersonate_user(), request)
-    })? {
+    match env
+        .run_on_context::<_, GetAPIVersionsResponse>(|c| c.read(env.get_impersonation(), request))?
+    {
         GetAPIVersionsResponse::Ok(groups) => Ok(groups.groups),
         GetAPIVersionsResponse::Other(_) => Err(ClickError::CommandError(
             "Could not fetch api groups".to_string(),
@@ -34,6 +34,30 @@ pub fn get_api_groups(env: &mut Env) -> Result<Vec<APIGroup>, ClickError> {
     }
 }
 
+/// Get the preferred group version of every api group the server knows about, including the core
+/// "v1" group which isn't returned by get_api_groups
+pub fn get_api_group_versions(env: &mut Env) -> Result<Vec<String>, ClickError> {
+    let mut versions = vec!["v1".to_string()];
+    for group in get_api_groups(env)?.into_iter() {
+        let version = match group.preferred_version {
+            Some(pv) => Some(pv.group_version),
+            None => group.versions.into_iter().next().map(|v| v.group_version),
+        };
+        versions.extend(version);
+    }
+    Ok(versions)
+}
+
+/// The path to the api for a group version. Core resources live under /api, everything else
+/// under /apis
+pub fn api_path(group_version: &str) -> String {
+    if group_version.contains('/') {
+        format!("/apis/{group_version}")
+    } else {
+        format!("/api/{group_version}")
+    }
+}
+
 #[allow(clippy::type_complexity)] // type from k8s_openapi
 pub fn get_api_group_resources(
     group_version: &str,
@@ -44,7 +68,7 @@ pub fn get_api_group_resources(
     ),
     RequestError,
 > {
-    let url = format!("/apis/{group_version}");
+    let url = api_path(group_version);
     let request = Request::get(url);
     let body = vec![];
     match request.body(body) {
@@ -88,9 +112,9 @@ impl Response for GetAPIGroupResourcesResponse {
 }
 
 #[allow(clippy::type_complexity)] // type from k8s_openapi
-pub fn read_namespaced_resource(
+pub fn read_resource(
     name: &str,
-    namespace: &str,
+    namespace: Option<&str>,
     _type: &str,
     group_version: &str,
 ) -> Result<
@@ -100,7 +124,13 @@ pub fn read_namespaced_resource(
     ),
     RequestError,
 > {
-    let url = format!("/apis/{group_version}/namespaces/{namespace}/{_type}/{name}");
+    let url = match namespace {
+        Some(namespace) => format!(
+            "{}/namespaces/{namespace}/{_type}/{name}",
+            api_path(group_version)
+        ),
+        None => format!("{}/{_type}/{name}", api_path(group_version)),
+    };
     let request = Request::get(url);
     let body = vec![];
     match request.body(body) {
@@ -141,3 +171,77 @@ impl Response for ReadResourceValueResponse {
         }
     }
 }
+
+/// An object with only its metadata, as returned by a metadata-only list
+#[derive(Debug, Deserialize)]
+pub struct PartialObjectMetadata {
+    pub metadata: ObjectMeta,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartialObjectMetadataList {
+    pub items: Vec<PartialObjectMetadata>,
+}
+
+/// List the resources of type _type in namespace, or all namespaces if it's None, asking the server
+/// to only send back their metadata
+#[allow(clippy::type_complexity)] // type from k8s_openapi
+pub fn list_resource_metadata(
+    _type: &str,
+    group_version: &str,
+    namespace: Option<&str>,
+) -> Result<
+    (
+        Request<Vec<u8>>,
+        fn(k8s_openapi::http::StatusCode) -> ResponseBody<ListMetadataResponse>,
+    ),
+    RequestError,
+> {
+    let url = match namespace {
+        Some(ns) => format!("{}/namespaces/{ns}/{_type}", api_path(group_version)),
+        None => format!("{}/{_type}", api_path(group_version)),
+    };
+    let request = Request::get(url).header(
+        k8s_openapi::http::header::ACCEPT,
+        "application/json;as=PartialObjectMetadataList;g=meta.k8s.io;v=v1,application/json",
+    );
+    let body = vec![];
+    match request.body(body) {
+        Ok(request) => Ok((request, ResponseBody::new)),
+        Err(err) => Err(RequestError::Http(err)),
+    }
+}
+
+#[derive(Debug)]
+pub enum ListMetadataResponse {
+    Ok(PartialObjectMetadataList),
+    #[allow(dead_code)]
+    Other(Result<Option<serde_json::Value>, serde_json::Error>),
+}
+
+impl Response for ListMetadataResponse {
+    fn try_from_parts(status_code: StatusCode, buf: &[u8]) -> Result<(Self, usize), ResponseError> {
+        match status_code {
+            StatusCode::OK => {
+                let result = match serde_json::from_slice(buf) {
+                    Ok(value) => value,
+                    Err(ref err) if err.is_eof() => return Err(ResponseError::NeedMoreData),
+                    Err(err) => return Err(ResponseError::Json(err)),
+                };
+                Ok((ListMetadataResponse::Ok(result), buf.len()))
+            }
+            _ => {
+                let (result, read) = if buf.is_empty() {
+                    (Ok(None), 0)
+                } else {
+                    match crate::serde_json::from_slice(buf) {
+                        Ok(value) => (Ok(Some(value)), buf.len()),
+                        Err(ref err) if err.is_eof() => return Err(ResponseError::NeedMoreData),
+                        Err(err) => (Err(err), 0),
+                    }
+                };
+                Ok((ListMetadataResponse::Other(result), read))
+            }
+        }
+    }
+}
@@ -19,7 +19,7 @@ use std::io::Write;
 // crd is a bit more complex, so handle it here
 pub fn crd_describe(
     name: &str,
-    namespace: &str,
+    namespace: Option<&str>,
     _type: &str,
     group_version: &str,
     matches: &ArgMatches,
@@ -27,15 +27,12 @@ pub fn crd_describe(
     writer: &mut ClickWriter,
 ) -> Result<(), ClickError> {
     //let ns = self.namespace.as_ref().unwrap();
-    let (request, _) = crate::crd::read_namespaced_resource(name, namespace, _type, group_version)?;
-    match env
-        .run_on_context(|c| {
-            c.read::<crate::crd::ReadResourceValueResponse>(env.get_impersonate_user(), request)
-        })
-        .unwrap()
-    {
+    let (request, _) = crate::crd::read_resource(name, namespace, _type, group_version)?;
+    match env.run_on_context(|c| {
+        c.read::<crate::crd::ReadResourceValueResponse>(env.get_impersonation(), request)
+    })? {
         crate::crd::ReadResourceValueResponse::Ok(t) => {
-            if !super::maybe_full_describe_output(matches, &t, writer) {
+            if !super::maybe_full_describe_output(matches, &t, &env.styles, writer) {
                 clickwriteln!(writer, "{} {}", _type, super::NOTSUPPORTED);
             }
         }
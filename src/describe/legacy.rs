@@ -16,13 +16,14 @@
 //!  information for supported kubernetes object types
 
 use crate::error::ClickError;
+use crate::styles::Styles;
+use crate::table::ColorType;
 use crate::values::{val_str, val_str_opt, val_u64};
 
 use base64::engine::{general_purpose::STANDARD, Engine};
 use chrono::offset::Local;
 use chrono::offset::Utc;
 use chrono::DateTime;
-use crossterm::style::Stylize;
 use k8s_openapi::api::{apps::v1 as api_apps, core::v1 as api};
 use serde_json::Value;
 
@@ -144,8 +145,10 @@ where
 pub fn describe_format_pod(
     pod: &api::Pod,
     table: &mut comfy_table::Table,
+    styles: &Styles,
 ) -> Result<(), ClickError> {
     let v = serde_json::value::to_value(pod).unwrap();
+    let phase: &dyn Fn(&Value) -> Cow<str> = &|v| pod_phase(v, styles);
     let fields = vec![
         (
             "Name:",
@@ -180,7 +183,7 @@ pub fn describe_format_pod(
             "Status:",
             DescItem::CustomFunc {
                 path: None,
-                func: &pod_phase,
+                func: phase,
                 default: "<No Phase>",
             },
         ),
@@ -355,15 +358,14 @@ fn get_volume_str(v: &'_ Value) -> Cow<'_, str> {
     buf.into()
 }
 
-fn pod_phase(v: &'_ Value) -> Cow<'_, str> {
-    // TODO: How to get an env in here for the colors
+fn pod_phase<'a>(v: &'a Value, styles: &Styles) -> Cow<'a, str> {
     let phase_str = val_str("/status/phase", v, "<No Phase>");
-    match &*phase_str {
-        "Pending" | "Unknown" => phase_str.yellow().to_string().into(),
-        "Running" | "Succeeded" => phase_str.green().to_string().into(),
-        "Failed" => phase_str.red().to_string().into(),
-        _ => phase_str.yellow().to_string().into(),
-    }
+    let color_type = match &*phase_str {
+        "Running" | "Succeeded" => ColorType::Success,
+        "Failed" => ColorType::Danger,
+        _ => ColorType::Warn,
+    };
+    styles.status(&phase_str, &color_type).to_string().into()
 }
 
 /// Utility function for describe to print out value
@@ -37,25 +37,26 @@ pub fn service_describe(
 ) -> Result<(), ClickError> {
     let (request, _) =
         api::Endpoints::read_namespaced_endpoints(name, namespace, Default::default()).unwrap();
-    let epval = match env
-        .run_on_context(|c| c.read(env.get_impersonate_user(), request))
-        .unwrap()
-    {
-        api::ReadNamespacedEndpointsResponse::Ok(resp) => serde_json::value::to_value(resp).ok(),
-        _ => {
+    // the service is still worth describing if the endpoints can't be read, i.e. when rbac forbids it
+    let epval = match env.run_on_context(|c| c.read(env.get_impersonation(), request)) {
+        Ok(api::ReadNamespacedEndpointsResponse::Ok(resp)) => {
+            serde_json::value::to_value(resp).ok()
+        }
+        Ok(_) => {
             clickwriteln!(writer, "Error fetching endpoints");
             None
         }
+        Err(e) => {
+            clickwriteln!(writer, "Error fetching endpoints: {e}");
+            None
+        }
     };
 
     let (request, _) =
         api::Service::read_namespaced_service(name, namespace, Default::default()).unwrap();
-    match env
-        .run_on_context(|c| c.read(env.get_impersonate_user(), request))
-        .unwrap()
-    {
+    match env.run_on_context(|c| c.read(env.get_impersonation(), request))? {
         api::ReadNamespacedServiceResponse::Ok(service) => {
-            if !super::maybe_full_describe_output(matches, &service, writer) {
+            if !super::maybe_full_describe_output(matches, &service, &env.styles, writer) {
                 super::describe_metadata(&service, table)?;
                 let val = serde_json::value::to_value(&service).unwrap();
                 describe_format_service(&service, val, epval, table);
@@ -12,16 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::completion_cache::{CacheKey, CompletionCache};
 use crate::config::{self, Alias, ClickConfig, Config};
 use crate::error::ClickError;
+use crate::history::{History, HistoryEntry};
+use crate::k8s::Impersonation;
 use crate::kobj::{KObj, ObjType};
 use crate::output::ClickWriter;
 use crate::styles::Styles;
+use crate::table::ExportTable;
 
+use chrono::offset::Local;
+use regex::Regex;
 use rustyline::config as rustyconfig;
 use strfmt::strfmt;
 use tempdir::TempDir;
 
+use std::cell::RefCell;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::io::{self, Write};
@@ -41,9 +48,9 @@ pub struct PortForward {
 }
 
 #[derive(Debug)]
-pub struct ExpandedAlias<'a> {
+pub struct ExpandedAlias<'a, 'b> {
     pub expansion: Option<&'a Alias>,
-    pub rest: &'a str,
+    pub rest: &'b str,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -66,14 +73,75 @@ pub struct Env {
     current_selection: ObjectSelection,
     last_objs: Option<Vec<KObj>>,
     last_table: Option<comfy_table::Table>,
+    // raw values of last_table, if the command that built it provided them
+    last_export: Option<ExportTable>,
     pub ctrlcbool: Arc<AtomicBool>,
     port_forwards: Vec<PortForward>,
     pub prompt: String,
     range_str: Option<String>,
     pub tempdir: std::io::Result<TempDir>,
-    impersonate_user: Option<String>,
+    impersonation: Option<Impersonation>,
+    variables: BTreeMap<String, String>,
+    history: History,
+    // contexts that objects listed by `across` live in, keyed by name
+    fanout_contexts: HashMap<String, super::k8s::Context>,
+    // while applying an operation to a selected object from another context, this holds the name
+    // of that context so run_on_context uses it
+    selection_context: RefCell<Option<String>>,
+    last_status_ok: bool,
+    completion_cache: CompletionCache,
+    // production_context_pattern compiled, so it isn't compiled again every time the prompt is
+    // rendered
+    production_context_re: Option<Regex>,
 }
 
+/// The prompt used if prompt_template isn't set, or can't be rendered
+pub const DEFAULT_PROMPT_TEMPLATE: &str = "[{context}] [{namespace}] [{selection}] > ";
+
+/// Everything needed to build an Env that runs commands against another context. Unlike Env this
+/// can be sent to another thread, where `into_env` turns it into an Env.
+pub struct FanoutEnv {
+    config: Config,
+    click_config: ClickConfig,
+    click_config_path: PathBuf,
+    context: super::k8s::Context,
+    namespace: Option<String>,
+    selection: ObjectSelection,
+    impersonation: Option<Impersonation>,
+    variables: BTreeMap<String, String>,
+    styles: Styles,
+}
+
+impl FanoutEnv {
+    pub fn context_name(&self) -> &str {
+        &self.context.name
+    }
+
+    pub fn into_env(self) -> Env {
+        let mut env = Env::build(
+            self.config,
+            self.click_config,
+            self.click_config_path,
+            self.styles,
+        );
+        env.context = Some(self.context);
+        env.namespace = self.namespace;
+        env.impersonation = self.impersonation;
+        env.variables = self.variables;
+        match self.selection {
+            ObjectSelection::Range(range) => env.set_range(range),
+            selection => {
+                env.current_selection = selection;
+                env.set_prompt();
+            }
+        }
+        env
+    }
+}
+
+/// Variables that are always defined, and get their value from the Env
+pub const BUILTIN_VARIABLES: [&str; 3] = ["CONTEXT", "NAMESPACE", "SELECTED"];
+
 lazy_static! {
     static ref CTC_BOOL: Arc<AtomicBool> = {
         let b = Arc::new(AtomicBool::new(false));
@@ -88,14 +156,29 @@ lazy_static! {
 
 impl Env {
     pub fn new(config: Config, click_config: ClickConfig, click_config_path: PathBuf) -> Env {
-        let namespace = click_config.namespace.clone();
         let context = click_config.context.clone();
-        let styles = Styles::new();
-        let nones = (
-            styles.prompt_context("none"),
-            styles.prompt_namespace("none"),
-            styles.prompt_select_none("none"),
-        );
+        let styles = Styles::from_theme(&click_config.theme).unwrap_or_else(|e| {
+            println!("Invalid theme in click config, using the default: {e}");
+            Styles::new()
+        });
+        let mut env = Env::build(config, click_config, click_config_path, styles);
+        env.set_context(context.as_deref());
+        env
+    }
+
+    // build an env with no context set
+    fn build(
+        config: Config,
+        click_config: ClickConfig,
+        click_config_path: PathBuf,
+        styles: Styles,
+    ) -> Env {
+        let namespace = click_config.namespace.clone();
+        // an invalid pattern in the config file just never matches
+        let production_context_re = click_config
+            .production_context_pattern
+            .as_deref()
+            .and_then(|pattern| Regex::new(pattern).ok());
         let mut env = Env {
             config,
             click_config,
@@ -108,17 +191,85 @@ impl Env {
             current_selection: ObjectSelection::None,
             last_objs: None,
             last_table: None,
+            last_export: None,
             ctrlcbool: CTC_BOOL.clone(),
             port_forwards: Vec::new(),
-            prompt: format!("[{}] [{}] [{}] > ", nones.0, nones.1, nones.2,),
+            prompt: String::new(),
             range_str: None,
             tempdir: TempDir::new("click"),
-            impersonate_user: None,
+            impersonation: None,
+            variables: BTreeMap::new(),
+            history: History::default(),
+            fanout_contexts: HashMap::new(),
+            selection_context: RefCell::new(None),
+            last_status_ok: true,
+            completion_cache: CompletionCache::default(),
+            production_context_re,
         };
-        env.set_context(context.as_deref());
+        env.set_prompt();
         env
     }
 
+    /// Get what's needed to run a command against the named context. The returned FanoutEnv has
+    /// the current namespace, selection, impersonation and variables of this env.
+    pub fn fanout(&self, context_name: &str) -> Result<FanoutEnv, ClickError> {
+        let context = self.config.get_context(context_name, &self.click_config)?;
+        // selected objects are looked up in the fanned out context, not the one they came from
+        let clear_context = |obj: &KObj| KObj {
+            context: None,
+            ..obj.clone()
+        };
+        let selection = match self.current_selection {
+            ObjectSelection::Single(ref obj) => ObjectSelection::Single(clear_context(obj)),
+            ObjectSelection::Range(ref range) => {
+                ObjectSelection::Range(range.iter().map(clear_context).collect())
+            }
+            ObjectSelection::None => ObjectSelection::None,
+        };
+        Ok(FanoutEnv {
+            config: self.config.clone(),
+            click_config: self.click_config.clone(),
+            click_config_path: self.click_config_path.clone(),
+            context,
+            namespace: self.namespace.clone(),
+            selection,
+            impersonation: self.impersonation.clone(),
+            variables: self.variables.clone(),
+            styles: self.styles.clone(),
+        })
+    }
+
+    /// Get what's needed to run a command against the current context on another thread, with
+    /// no selection. The context shares its clients and credentials with the current one, and
+    /// never asks for input. None if there is no current context.
+    pub fn background(&self) -> Option<FanoutEnv> {
+        let context = self.context.as_ref()?.background_copy();
+        Some(FanoutEnv {
+            config: self.config.clone(),
+            click_config: self.click_config.clone(),
+            click_config_path: self.click_config_path.clone(),
+            context,
+            namespace: self.namespace.clone(),
+            selection: ObjectSelection::None,
+            impersonation: self.impersonation.clone(),
+            variables: self.variables.clone(),
+            styles: self.styles.clone(),
+        })
+    }
+
+    /// Keep a context that was used by `across`, so operations on objects listed from it go to
+    /// the right cluster
+    pub fn add_fanout_context(&mut self, context: super::k8s::Context) {
+        self.fanout_contexts.insert(context.name.clone(), context);
+    }
+
+    /// The name of the context obj lives in
+    pub fn context_name_for<'a>(&'a self, obj: &'a KObj) -> Option<&'a str> {
+        obj.context
+            .as_deref()
+            .or_else(|| self.context.as_ref().map(|c| c.name.as_str()))
+    }
+
     pub fn current_selection(&self) -> &ObjectSelection {
         &self.current_selection
     }
@@ -131,37 +282,199 @@ impl Env {
             .unwrap();
     }
 
-    pub fn set_impersonate_user(&mut self, user: Option<String>) {
-        self.impersonate_user = user;
+    pub fn set_impersonation(&mut self, impersonation: Option<Impersonation>) {
+        self.impersonation = impersonation;
+        self.set_prompt();
     }
 
-    pub fn get_impersonate_user(&self) -> Option<&str> {
-        self.impersonate_user.as_deref()
+    pub fn get_impersonation(&self) -> Option<&Impersonation> {
+        self.impersonation.as_ref()
     }
 
-    // sets the prompt string based on current settings
-    fn set_prompt(&mut self) {
-        self.prompt = format!(
-            "[{}] [{}] [{}] > ",
-            if let Some(ref c) = self.context {
-                self.styles.prompt_context(c.name.as_str())
-            } else {
-                self.styles.prompt_context("none")
-            },
-            if let Some(ref n) = self.namespace {
-                self.styles.prompt_namespace(n.as_str())
-            } else {
-                self.styles.prompt_namespace("none")
-            },
+    /// The arguments that make kubectl impersonate like click currently does, if it is
+    pub fn kubectl_impersonation_args(&self) -> Result<Vec<String>, ClickError> {
+        match self.impersonation.as_ref() {
+            Some(impersonation) => impersonation.kubectl_args(),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Set a user variable. Returns false if name is a builtin variable, which can't be set
+    pub fn set_variable(&mut self, name: &str, value: String) -> bool {
+        if BUILTIN_VARIABLES.contains(&name) {
+            false
+        } else {
+            self.variables.insert(name.to_string(), value);
+            true
+        }
+    }
+
+    pub fn remove_variable(&mut self, name: &str) -> bool {
+        self.variables.remove(name).is_some()
+    }
+
+    pub fn get_variables(&self) -> &BTreeMap<String, String> {
+        &self.variables
+    }
+
+    /// Get the value of a variable. Builtin variables come from the current state of the env,
+    /// anything else is looked up in the user variables
+    pub fn get_variable(&self, name: &str) -> Option<String> {
+        match name {
+            "CONTEXT" => Some(
+                self.context
+                    .as_ref()
+                    .map(|c| c.name.clone())
+                    .unwrap_or_default(),
+            ),
+            "NAMESPACE" => Some(self.namespace.clone().unwrap_or_default()),
+            "SELECTED" => Some(match self.current_selection {
+                ObjectSelection::Single(ref obj) => obj.name().to_string(),
+                ObjectSelection::Range(ref range) => range
+                    .iter()
+                    .map(|obj| obj.name())
+                    .collect::<Vec<&str>>()
+                    .join(" "),
+                ObjectSelection::None => String::new(),
+            }),
+            _ => self.variables.get(name).cloned(),
+        }
+    }
+
+    pub fn set_history(&mut self, history: History) {
+        self.history = history;
+    }
+
+    pub fn get_history(&self) -> &History {
+        &self.history
+    }
+
+    /// If history is kept per context, the context whose history should be used, otherwise None
+    pub fn history_context(&self) -> Option<&str> {
+        if self.click_config.context_history {
+            Some(self.context.as_ref().map(|c| c.name.as_str()).unwrap_or(""))
+        } else {
+            None
+        }
+    }
+
+    /// Record that line was run in the current context and namespace
+    pub fn add_history_entry(&mut self, line: &str) -> Result<(), ClickError> {
+        let entry = HistoryEntry {
+            line: line.to_string(),
+            time: Some(chrono::Utc::now()),
+            context: self.context.as_ref().map(|c| c.name.clone()),
+            namespace: self.namespace.clone(),
+        };
+        self.history.add(entry)
+    }
+
+    /// Expand a `!` history reference at the start of line, see History::expand
+    pub fn expand_history(&self, line: &str) -> Result<Option<String>, String> {
+        self.history.expand(line, self.history_context())
+    }
+
+    /// Record if the last command succeeded, for the {status} prompt placeholder
+    pub fn set_last_status(&mut self, ok: bool) {
+        self.last_status_ok = ok;
+    }
+
+    /// Does the current context match production_context_pattern
+    pub fn is_production_context(&self) -> bool {
+        match (self.context.as_ref(), self.production_context_re.as_ref()) {
+            (Some(context), Some(re)) => re.is_match(&context.name),
+            _ => false,
+        }
+    }
+
+    /// Render a prompt template. Placeholders are: context, namespace, selection, user (the
+    /// impersonated user), server (the api server host), time, forwards (the number of active port
+    /// forwards) and status (0 if the last command succeeded, 1 otherwise)
+    pub fn render_prompt(&self, template: &str) -> Result<String, strfmt::FmtError> {
+        let mut vars: HashMap<String, String> = HashMap::new();
+        let context = self
+            .context
+            .as_ref()
+            .map(|c| c.name.as_str())
+            .unwrap_or("none");
+        let context = if self.is_production_context() {
+            self.styles.prompt_production_context(context)
+        } else {
+            self.styles.prompt_context(context)
+        };
+        vars.insert("context".to_string(), context.to_string());
+        vars.insert(
+            "namespace".to_string(),
+            self.styles
+                .prompt_namespace(self.namespace.as_deref().unwrap_or("none"))
+                .to_string(),
+        );
+        vars.insert(
+            "selection".to_string(),
             match self.current_selection {
-                ObjectSelection::Single(ref obj) =>
-                    self.styles.prompt_object(obj.name.as_str(), obj.type_str()),
+                ObjectSelection::Single(ref obj) => self
+                    .styles
+                    .prompt_object(obj.name.as_str(), obj.type_str())
+                    .to_string(),
                 ObjectSelection::Range(_) => self
                     .styles
-                    .prompt_range(self.range_str.as_ref().unwrap().as_str()),
-                ObjectSelection::None => self.styles.prompt_select_none("none"),
-            }
+                    .prompt_range(self.range_str.as_ref().unwrap().as_str())
+                    .to_string(),
+                ObjectSelection::None => self.styles.prompt_select_none("none").to_string(),
+            },
+        );
+        vars.insert(
+            "user".to_string(),
+            self.get_impersonation()
+                .map(|imp| self.styles.prompt_user(&imp.prompt_str()).to_string())
+                .unwrap_or_default(),
+        );
+        vars.insert(
+            "server".to_string(),
+            self.styles
+                .prompt_server(
+                    self.context
+                        .as_ref()
+                        .and_then(|c| c.endpoint.host_str())
+                        .unwrap_or("none"),
+                )
+                .to_string(),
         );
+        vars.insert(
+            "time".to_string(),
+            self.styles
+                .prompt_time(&Local::now().format("%H:%M:%S").to_string())
+                .to_string(),
+        );
+        vars.insert(
+            "forwards".to_string(),
+            self.styles
+                .prompt_forwards(&self.port_forwards.len().to_string())
+                .to_string(),
+        );
+        vars.insert(
+            "status".to_string(),
+            if self.last_status_ok {
+                self.styles.prompt_status_ok("0").to_string()
+            } else {
+                self.styles.prompt_status_err("1").to_string()
+            },
+        );
+        strfmt(template, &vars)
+    }
+
+    /// Set the prompt string based on current settings. This is called whenever something shown
+    /// in the prompt changes, and before each line is read so the time is up to date
+    pub fn set_prompt(&mut self) {
+        let template = self
+            .click_config
+            .prompt_template
+            .as_deref()
+            .unwrap_or(DEFAULT_PROMPT_TEMPLATE);
+        self.prompt = self
+            .render_prompt(template)
+            .or_else(|_| self.render_prompt(DEFAULT_PROMPT_TEMPLATE))
+            .unwrap(); // safe, the default template is valid
     }
 
     pub fn get_rustyline_conf(&self) -> rustyconfig::Config {
@@ -174,12 +487,13 @@ impl Env {
 
     pub fn set_context(&mut self, ctx: Option<&str>) {
         if let Some(cname) = ctx {
+            self.completion_cache.invalidate();
             self.context = match self.config.get_context(cname, &self.click_config) {
                 Ok(context) => Some(context),
                 Err(e) => {
                     println!(
                         "[WARN] Couldn't find/load context {cname}, now no current context. \
-                         Error: {e}"
+                         Error: {e}\n'config check' can help find the problem"
                     );
                     None
                 }
@@ -189,6 +503,27 @@ impl Env {
         }
     }
 
+    /// Switch to a new config, i.e. after the kubeconfig files were changed, and make context the
+    /// current context. With None, there is no current context. If context was already current, the
+    /// namespace and selection are kept.
+    pub fn replace_config(&mut self, config: Config, context: Option<&str>) {
+        self.config = config;
+        let same_context =
+            context.is_some() && self.context.as_ref().map(|c| c.name.as_str()) == context;
+        if !same_context {
+            self.clear_current();
+        }
+        match context {
+            Some(_) => self.set_context(context),
+            None => {
+                self.completion_cache.invalidate();
+                self.context = None;
+                self.save_click_config();
+                self.set_prompt();
+            }
+        }
+    }
+
     // a lowercase RFC 1123 label must consist of lower case alphanumeric characters or '-', and must start and end with an alphanumeric character. Max length is 63.
     fn validate_rfc_1123_label(label: &str) -> bool {
         if label.is_empty() || label.len() > 63 {
@@ -236,6 +571,26 @@ impl Env {
         self.click_config.kubectl_binary = kubectl_binary.map(|s| s.to_string());
     }
 
+    pub fn set_prompt_template(&mut self, template: Option<&str>) -> Result<(), String> {
+        if let Some(template) = template {
+            self.render_prompt(template)
+                .map_err(|e| format!("Invalid prompt template: {e}"))?;
+        }
+        self.click_config.prompt_template = template.map(|s| s.to_string());
+        self.set_prompt();
+        Ok(())
+    }
+
+    pub fn set_production_context_pattern(&mut self, pattern: Option<&str>) -> Result<(), String> {
+        self.production_context_re = pattern
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| format!("Invalid production_context_pattern: {e}"))?;
+        self.click_config.production_context_pattern = pattern.map(|s| s.to_string());
+        self.set_prompt();
+        Ok(())
+    }
+
     pub fn set_completion_type(&mut self, comptype: config::CompletionType) {
         self.click_config.completiontype = comptype;
         self.need_new_editor = true;
@@ -246,6 +601,15 @@ impl Env {
         self.need_new_editor = true;
     }
 
+    pub fn set_theme_preset(&mut self, preset: config::ThemePreset) -> Result<(), String> {
+        let mut theme = self.click_config.theme.clone();
+        theme.preset = preset;
+        self.styles = Styles::from_theme(&theme)?;
+        self.click_config.theme = theme;
+        self.set_prompt();
+        Ok(())
+    }
+
     // Return the current position of the specified alias in the Vec, or None if it's not there
     fn alias_position(&self, alias: &str) -> Option<usize> {
         self.click_config
@@ -284,17 +648,39 @@ impl Env {
     ) {
         self.last_objs = Some(objs.into());
         self.last_table = table;
+        self.last_export = None;
+    }
+
+    /// Set the raw values for the last table. Must be called after set_last_objs
+    pub fn set_last_export(&mut self, export: ExportTable) {
+        self.last_export = Some(export);
     }
 
     pub fn clear_last_objs(&mut self) {
         self.last_objs = None;
         self.last_table = None;
+        self.last_export = None;
+    }
+
+    pub fn take_last_objs(&mut self) -> (Option<Vec<KObj>>, Option<comfy_table::Table>) {
+        self.last_export = None;
+        (self.last_objs.take(), self.last_table.take())
     }
 
     pub fn get_last_table(&self) -> Option<&comfy_table::Table> {
         self.last_table.as_ref()
     }
 
+    /// The last table for exporting. This has raw values if they were provided, otherwise it's
+    /// built from what was displayed
+    pub fn get_last_export(&self) -> Option<ExportTable> {
+        match (&self.last_export, &self.last_table) {
+            (Some(export), _) => Some(export.clone()),
+            (None, Some(table)) => Some(ExportTable::from_table(table)),
+            (None, None) => None,
+        }
+    }
+
     pub fn clear_current(&mut self) {
         self.current_selection = ObjectSelection::None;
         self.range_str = None;
@@ -361,7 +747,7 @@ impl Env {
             clickwriteln!(writer, "  a = all: continue over all future errors");
             clickwriteln!(writer, "  n/N = no: abort range operation (default)");
             clickwrite!(writer, "Continue? [o/a/N]? ");
-            io::stdout().flush().expect("Could not flush stdout");
+            writer.flush().expect("Could not flush stdout");
             let mut conf = String::new();
             if io::stdin().read_line(&mut conf).is_ok() {
                 match conf.trim() {
@@ -391,6 +777,13 @@ impl Env {
     where
         F: FnMut(&KObj, &mut ClickWriter) -> Result<(), ClickError>,
     {
+        // run operations on objects from other contexts against that context
+        let mut f = |obj: &KObj, writer: &mut ClickWriter| {
+            *self.selection_context.borrow_mut() = obj.context.clone();
+            let res = f(obj, writer);
+            *self.selection_context.borrow_mut() = None;
+            res
+        };
         match self.current_selection() {
             ObjectSelection::Single(obj) => f(obj, writer),
             ObjectSelection::Range(range) => {
@@ -450,15 +843,33 @@ impl Env {
     where
         F: FnOnce(&crate::k8s::Context) -> Result<R, ClickError>,
     {
-        match self.context {
-            Some(ref c) => f(c),
-            None => Err(ClickError::CommandError("No active context".to_string())),
+        let selection_context = self.selection_context.borrow().clone();
+        match selection_context.as_deref() {
+            Some(name) if self.context.as_ref().map(|c| c.name.as_str()) != Some(name) => {
+                match self.fanout_contexts.get(name) {
+                    Some(c) => f(c),
+                    None => Err(ClickError::CommandError(format!(
+                        "Context {name} is not loaded"
+                    ))),
+                }
+            }
+            _ => match self.context {
+                Some(ref c) => f(c),
+                None => Err(ClickError::CommandError("No active context".to_string())),
+            },
         }
     }
 
+    /// Get data for completion from the cache. This never blocks for long, and returns None if the
+    /// data isn't available yet
+    pub fn completion_values(&self, key: CacheKey) -> Option<Vec<String>> {
+        self.completion_cache.get(self, key)
+    }
+
     /// Add a new task for the env to keep track of
     pub fn add_port_forward(&mut self, pf: PortForward) {
         self.port_forwards.push(pf);
+        self.set_prompt();
     }
 
     pub fn get_port_forwards(&mut self) -> std::slice::IterMut<'_, PortForward> {
@@ -472,6 +883,7 @@ impl Env {
     pub fn stop_port_forward(&mut self, i: usize) -> Result<(), std::io::Error> {
         if i < self.port_forwards.len() {
             let mut pf = self.port_forwards.remove(i);
+            self.set_prompt();
             pf.child.kill()
         } else {
             Ok(())
@@ -489,18 +901,19 @@ impl Env {
     /// This function looks at the first word (whitespace delimited) of the
     /// line, checks if it matches an alias, if it does it returns and ExpandedAlias with the
     /// expansion and the rest of the line, otherwise the expansion field will be None and rest will
-    /// contain the whole line
-    pub fn try_expand_alias<'a>(
+    /// contain the whole line. Aliases in `expanding` are currently being expanded, and so will not
+    /// be expanded again.
+    pub fn try_expand_alias<'a, 'b>(
         &'a self,
-        line: &'a str,
-        prev_word: Option<&'a str>,
-    ) -> ExpandedAlias<'a> {
+        line: &'b str,
+        expanding: &[&str],
+    ) -> ExpandedAlias<'a, 'b> {
         let pos = line.find(char::is_whitespace).unwrap_or(line.len());
         let word = &line[0..pos];
-        // don't expand if prev_word is Some, and is equal to my word
-        // this means an alias maps to itself, and we want to stop expanding
-        // to avoid an infinite loop
-        if prev_word.filter(|pw| *pw == word).is_none() {
+        // don't expand if word is an alias we're already expanding. this means an alias maps to
+        // itself (possibly via other aliases), and we want to stop expanding to avoid an infinite
+        // loop
+        if !expanding.contains(&word) {
             for alias in self.click_config.aliases.iter() {
                 if word == alias.alias.as_str() {
                     return ExpandedAlias {
@@ -547,6 +960,8 @@ impl fmt::Display for Env {
             f,
             "Env {{
   Current Context: {}
+  Impersonating: {}
+  Proxy: {}
   Availble Contexts: {:?}
   Kubernetes Config File(s): {}
   Completion Type: {}
@@ -554,14 +969,29 @@ impl fmt::Display for Env {
   Editor: {}
   Terminal: {}
   kubectl Binary: {}
+  Pager: {}
   Range Separator: {}
   Describe Shows Events: {}
+  Per-Context History: {}
+  Cache Exec Credentials: {}
+  Prompt Template: {}
+  Production Context Pattern: {}
+  Theme: {}
+  Variables: {}
 }}",
             if let Some(ref c) = self.context {
                 self.styles.config_val(c.name.as_str())
             } else {
                 self.styles.config_val("none")
             },
+            match self.get_impersonation() {
+                Some(impersonation) => self.styles.config_val_string(impersonation.to_string()),
+                None => self.styles.config_val_string("<none>".to_string()),
+            },
+            match self.context.as_ref().and_then(|c| c.proxy.as_ref()) {
+                Some(proxy) => self.styles.config_val_string(proxy.to_string()),
+                None => self.styles.config_val_string("<none>".to_string()),
+            },
             self.config.contexts.keys(),
             self.styles.config_val(self.config.source_file.as_str()),
             {
@@ -585,6 +1015,12 @@ impl fmt::Display for Env {
                     .unwrap_or("<unset, will use xterm>")
             ),
             self.styles.config_val(&kubectl_path),
+            self.styles.config_val(
+                self.click_config
+                    .pager
+                    .as_deref()
+                    .unwrap_or("<unset, will use internal>")
+            ),
             self.styles
                 .config_val(self.click_config.range_separator.as_str()),
             self.styles.config_val(
@@ -593,6 +1029,39 @@ impl fmt::Display for Env {
                     .to_string()
                     .as_str()
             ),
+            self.styles
+                .config_val(self.click_config.context_history.to_string().as_str()),
+            self.styles
+                .config_val(self.click_config.exec_credential_cache.to_string().as_str()),
+            self.styles.config_val(
+                self.click_config
+                    .prompt_template
+                    .as_deref()
+                    .unwrap_or(DEFAULT_PROMPT_TEMPLATE)
+            ),
+            self.styles.config_val(
+                self.click_config
+                    .production_context_pattern
+                    .as_deref()
+                    .unwrap_or("<unset>")
+            ),
+            self.styles
+                .config_val_string(match crate::styles::color_env_override() {
+                    Some(var) =>
+                        format!("{} (overridden by {var})", self.click_config.theme.preset),
+                    None => self.click_config.theme.preset.to_string(),
+                }),
+            if self.variables.is_empty() {
+                self.styles.config_val_string("<none set>".to_string())
+            } else {
+                self.styles.config_val_string(
+                    self.variables
+                        .iter()
+                        .map(|(name, value)| format!("{name}={value:?}"))
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                )
+            },
         )
     }
 }
@@ -617,25 +1086,110 @@ mod tests {
         cc.aliases.push(x_alias.clone());
         let env = Env::new(get_test_config(), cc, PathBuf::from("/tmp/click.config"));
 
-        let exp1 = env.try_expand_alias("pn", None);
+        let exp1 = env.try_expand_alias("pn", &[]);
         assert_eq!(exp1.expansion, Some(&pn_alias));
         assert_eq!(exp1.rest, "");
 
-        let exp2 = env.try_expand_alias("x", None);
+        let exp2 = env.try_expand_alias("x", &[]);
         assert_eq!(exp2.expansion, Some(&x_alias));
         assert_eq!(exp2.rest, "");
 
-        let exp2 = env.try_expand_alias("x rest is this", None);
+        let exp2 = env.try_expand_alias("x rest is this", &[]);
         assert_eq!(exp2.expansion, Some(&x_alias));
         assert_eq!(exp2.rest, " rest is this");
 
-        let exp3 = env.try_expand_alias("no alias", None);
+        let exp3 = env.try_expand_alias("no alias", &[]);
         assert_eq!(exp3.expansion, None);
         assert_eq!(exp3.rest, "no alias");
 
-        let exp4 = env.try_expand_alias("x", Some("x"));
+        let exp4 = env.try_expand_alias("x", &["x"]);
         assert_eq!(exp4.expansion, None);
         assert_eq!(exp4.rest, "x");
+
+        let exp5 = env.try_expand_alias("x", &["pn", "x"]);
+        assert_eq!(exp5.expansion, None);
+        assert_eq!(exp5.rest, "x");
+    }
+
+    #[test]
+    fn variables() {
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        assert!(env.set_variable("FOO", "bar".to_string()));
+        assert!(!env.set_variable("NAMESPACE", "bar".to_string()));
+        assert_eq!(env.get_variable("FOO"), Some("bar".to_string()));
+        assert_eq!(env.get_variable("BAR"), None);
+
+        env.set_namespace(Some("ns1"));
+        assert_eq!(env.get_variable("NAMESPACE"), Some("ns1".to_string()));
+        assert_eq!(env.get_variable("SELECTED"), Some(String::new()));
+        env.set_last_objs(
+            vec![
+                KObj {
+                    name: "n1".to_string(),
+                    namespace: None,
+                    typ: ObjType::Node,
+                    context: None,
+                },
+                KObj {
+                    name: "n2".to_string(),
+                    namespace: None,
+                    typ: ObjType::Node,
+                    context: None,
+                },
+            ],
+            None,
+        );
+        env.set_current(1);
+        assert_eq!(env.get_variable("SELECTED"), Some("n2".to_string()));
+        env.set_range(vec![
+            env.item_at(0).unwrap().clone(),
+            env.item_at(1).unwrap().clone(),
+        ]);
+        assert_eq!(env.get_variable("SELECTED"), Some("n1 n2".to_string()));
+
+        assert!(env.remove_variable("FOO"));
+        assert!(!env.remove_variable("FOO"));
+        assert_eq!(env.get_variable("FOO"), None);
+    }
+
+    #[test]
+    fn prompt_template() {
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        env.set_namespace(Some("ns1"));
+        env.set_impersonation(Some(Impersonation::user("alice")));
+        assert!(env
+            .set_prompt_template(Some("{namespace}|{user}|{status}|{forwards} > "))
+            .is_ok());
+        let strip = |s: &str| {
+            Regex::new("\x1b\\[[0-9;]*[A-Za-z]")
+                .unwrap()
+                .replace_all(s, "")
+                .into_owned()
+        };
+        assert_eq!(strip(&env.prompt), "ns1|alice|0|0 > ");
+        env.set_last_status(false);
+        env.set_prompt();
+        assert_eq!(strip(&env.prompt), "ns1|alice|1|0 > ");
+        let mut impersonation = Impersonation::user("alice");
+        impersonation.groups = vec!["dev".to_string()];
+        env.set_impersonation(Some(impersonation));
+        assert_eq!(strip(&env.prompt), "ns1|alice[dev]|1|0 > ");
+        env.set_impersonation(Some(Impersonation::user("alice")));
+
+        // bad templates are rejected, and leave the old one in place
+        assert!(env.set_prompt_template(Some("{nope} > ")).is_err());
+        assert_eq!(strip(&env.prompt), "ns1|alice|1|0 > ");
+
+        assert!(env.set_prompt_template(None).is_ok());
+        assert_eq!(strip(&env.prompt), "[none] [ns1] [none] > ");
     }
 
     #[test]
@@ -23,7 +23,11 @@ pub enum ClickErrNo {
     InvalidCluster,
     InvalidUser,
     NoTokenAvailable,
-    Unauthorized,
+    /// the credentials from the named auth method were rejected
+    Unauthorized(String),
+    /// the server refused the request. holds the server's message, and a can-i command that checks
+    /// the permission the request needed, if click could work it out
+    Forbidden(String, Option<String>),
     Unknown,
 }
 
@@ -38,10 +42,22 @@ impl fmt::Display for ClickErrNo {
             ClickErrNo::InvalidCluster => write!(f, "Invalid Cluster Name"),
             ClickErrNo::InvalidUser => write!(f, "Invalid User Name"),
             ClickErrNo::NoTokenAvailable => write!(f, "{NO_TOKEN_STR}"),
-            ClickErrNo::Unauthorized => write!(
+            ClickErrNo::Unauthorized(method) => write!(
                 f,
-                "Not authorized to talk to cluster, check credentials in config"
+                "Not authorized to talk to cluster, the credentials from {method} were rejected. \
+                 Check credentials in config"
             ),
+            ClickErrNo::Forbidden(message, suggestion) => {
+                write!(f, "Forbidden: {message}")?;
+                match suggestion {
+                    Some(can_i) => write!(
+                        f,
+                        "\nRun '{can_i}' to check that permission, or 'whoami' to see who the \
+                         cluster thinks you are"
+                    ),
+                    None => write!(f, "\nRun 'whoami' to see who the cluster thinks you are"),
+                }
+            }
             ClickErrNo::Unknown => write!(f, "Unknown error talking to cluster"),
         }
     }
@@ -54,9 +70,10 @@ impl error::Error for ClickErrNo {
             ClickErrNo::InvalidCluster => "Invalid Cluster Name",
             ClickErrNo::InvalidUser => "Invalid User Name",
             ClickErrNo::NoTokenAvailable => NO_TOKEN_STR,
-            ClickErrNo::Unauthorized => {
+            ClickErrNo::Unauthorized(_) => {
                 "Not authorized to talk to cluster, check credentials in config"
             }
+            ClickErrNo::Forbidden(_, _) => "Not allowed to make that request",
             ClickErrNo::Unknown => "Unknown error talking to cluster",
         }
     }
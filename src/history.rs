@@ -0,0 +1,288 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command history. Each entry remembers when it was run, and the context and namespace that were
+//! active at the time. History is stored one json entry per line, and appended to as commands are
+//! run, so nothing is lost if click exits abnormally.
+
+use atomicwrites::{AllowOverwrite, AtomicFile};
+use chrono::{DateTime, Utc};
+
+use crate::error::ClickError;
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Max number of entries to keep in the history file
+const MAX_ENTRIES: usize = 10000;
+
+/// The first line of history files rustyline writes, whose entries have newlines and backslashes
+/// escaped
+const RUSTYLINE_V2_HEADER: &str = "#V2";
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct HistoryEntry {
+    pub line: String,
+    pub time: Option<DateTime<Utc>>,
+    pub context: Option<String>,
+    pub namespace: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Parse a line of a history file. History files written before entries were json are
+    /// rustyline's, and just have the command on each line, escaped if escaped is set.
+    fn from_file_line(line: &str, escaped: bool) -> HistoryEntry {
+        serde_json::from_str(line).unwrap_or_else(|_| HistoryEntry {
+            line: if escaped {
+                unescape_rustyline(line)
+            } else {
+                line.to_string()
+            },
+            time: None,
+            context: None,
+            namespace: None,
+        })
+    }
+}
+
+// undo the escaping of newlines and backslashes in rustyline's v2 history files. Like rustyline, a
+// line with any other escape is kept as is.
+fn unescape_rustyline(line: &str) -> String {
+    let mut unescaped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('\\') => unescaped.push('\\'),
+                _ => return line.to_string(),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+#[derive(Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// Load history from path. Entries added to the returned History will be appended to path.
+    pub fn from_file(path: &Path) -> History {
+        let entries: Vec<HistoryEntry> = match File::open(path) {
+            Ok(file) => {
+                let mut lines = BufReader::new(file)
+                    .lines()
+                    .map_while(Result::ok)
+                    .peekable();
+                let escaped = lines.next_if(|line| line == RUSTYLINE_V2_HEADER).is_some();
+                lines
+                    .filter(|line| !line.is_empty())
+                    .map(|line| HistoryEntry::from_file_line(&line, escaped))
+                    .collect()
+            }
+            Err(_) => vec![],
+        };
+        let mut history = History {
+            entries,
+            path: Some(path.to_path_buf()),
+        };
+        if history.entries.len() > MAX_ENTRIES {
+            history
+                .entries
+                .drain(0..(history.entries.len() - MAX_ENTRIES));
+            if let Err(e) = history.rewrite() {
+                println!("Couldn't truncate command history: {e}");
+            }
+        }
+        history
+    }
+
+    // write all entries out to the history file
+    fn rewrite(&self) -> Result<(), ClickError> {
+        if let Some(path) = self.path.as_ref() {
+            let af = AtomicFile::new(path, AllowOverwrite);
+            af.write(|f| {
+                for entry in self.entries.iter() {
+                    writeln!(f, "{}", serde_json::to_string(entry)?)?;
+                }
+                Ok::<(), ClickError>(())
+            })
+            .map_err(|e| ClickError::CommandError(format!("Failed to write history: {e}")))?;
+        }
+        Ok(())
+    }
+
+    /// Add an entry, appending it to the history file if there is one
+    pub fn add(&mut self, entry: HistoryEntry) -> Result<(), ClickError> {
+        if let Some(path) = self.path.as_ref() {
+            let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+            writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub fn get(&self, index: usize) -> Option<&HistoryEntry> {
+        self.entries.get(index)
+    }
+
+    /// Iterate over (index, entry) for all entries. If context is Some, only entries run in that
+    /// context are returned. Some("") matches entries run with no context
+    pub fn iter_for<'a>(
+        &'a self,
+        context: Option<&'a str>,
+    ) -> impl DoubleEndedIterator<Item = (usize, &'a HistoryEntry)> + 'a {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(move |(_, entry)| match context {
+                Some(context) => entry.context.as_deref().unwrap_or("") == context,
+                None => true,
+            })
+    }
+
+    /// Expand a history reference at the start of line. `!!` is the last command, `!n` is the
+    /// command at index n, and `!prefix` is the most recent command starting with prefix. Anything
+    /// after the reference is appended to the expanded command. context limits which entries are
+    /// considered for `!!` and `!prefix`, as in iter_for. Returns Ok(None) if line doesn't start
+    /// with a history reference.
+    pub fn expand(&self, line: &str, context: Option<&str>) -> Result<Option<String>, String> {
+        let designator = match line.strip_prefix('!') {
+            Some(rest) => rest.split(char::is_whitespace).next().unwrap_or(""),
+            None => return Ok(None),
+        };
+        if designator.is_empty() {
+            return Ok(None);
+        }
+        let rest = &line[(designator.len() + 1)..];
+        let entry = if designator == "!" {
+            self.iter_for(context).next_back().map(|(_, entry)| entry)
+        } else if let Ok(index) = designator.parse::<usize>() {
+            self.get(index)
+        } else {
+            self.iter_for(context)
+                .rev()
+                .map(|(_, entry)| entry)
+                .find(|entry| entry.line.starts_with(designator))
+        };
+        match entry {
+            Some(entry) => Ok(Some(format!("{}{}", entry.line, rest))),
+            None => Err(format!("!{designator}: event not found")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: &str, context: &str) -> HistoryEntry {
+        HistoryEntry {
+            line: line.to_string(),
+            time: Some(Utc::now()),
+            context: Some(context.to_string()),
+            namespace: None,
+        }
+    }
+
+    fn test_history() -> History {
+        let mut history = History::default();
+        history.add(entry("pods", "c1")).unwrap();
+        history.add(entry("logs -f", "c2")).unwrap();
+        history.add(entry("pods --sort node", "c2")).unwrap();
+        history.add(entry("nodes", "c1")).unwrap();
+        history
+    }
+
+    #[test]
+    fn expand() {
+        let history = test_history();
+        assert_eq!(history.expand("pods", None), Ok(None));
+        assert_eq!(history.expand("!", None), Ok(None));
+        assert_eq!(history.expand("!!", None), Ok(Some("nodes".to_string())));
+        assert_eq!(
+            history.expand("!! | grep foo", None),
+            Ok(Some("nodes | grep foo".to_string()))
+        );
+        assert_eq!(history.expand("!1", None), Ok(Some("logs -f".to_string())));
+        assert_eq!(
+            history.expand("!po", None),
+            Ok(Some("pods --sort node".to_string()))
+        );
+        assert_eq!(
+            history.expand("!po", Some("c1")),
+            Ok(Some("pods".to_string()))
+        );
+        assert_eq!(
+            history.expand("!!", Some("c2")),
+            Ok(Some("pods --sort node".to_string()))
+        );
+        assert!(history.expand("!10", None).is_err());
+        assert!(history.expand("!xyz", None).is_err());
+    }
+
+    #[test]
+    fn iter_for() {
+        let history = test_history();
+        assert_eq!(history.iter_for(None).count(), 4);
+        let c1: Vec<usize> = history.iter_for(Some("c1")).map(|(i, _)| i).collect();
+        assert_eq!(c1, vec![0, 3]);
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let dir = tempdir::TempDir::new("click_history").unwrap();
+        let path = dir.path().join("history");
+        std::fs::write(&path, "old command\n").unwrap();
+        let mut history = History::from_file(&path);
+        assert_eq!(history.get(0).unwrap().line, "old command");
+        assert_eq!(history.get(0).unwrap().time, None);
+        history.add(entry("pods", "c1")).unwrap();
+        let history = History::from_file(&path);
+        assert_eq!(history.iter_for(None).count(), 2);
+        assert_eq!(history.get(1).unwrap().context.as_deref(), Some("c1"));
+    }
+
+    #[test]
+    fn rustyline_file() {
+        use rustyline::history::{FileHistory, History as _};
+
+        let dir = tempdir::TempDir::new("click_history").unwrap();
+        let path = dir.path().join("history");
+        let mut rustyline_history = FileHistory::new();
+        rustyline_history.add("pods").unwrap();
+        rustyline_history.add("exec -- echo a\\b").unwrap();
+        rustyline_history.add("exec -- sh -c 'one\ntwo'").unwrap();
+        rustyline_history.save(&path).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("#V2\n"));
+
+        let history = History::from_file(&path);
+        let lines: Vec<&str> = history
+            .iter_for(None)
+            .map(|(_, entry)| entry.line.as_str())
+            .collect();
+        assert_eq!(
+            lines,
+            vec!["pods", "exec -- echo a\\b", "exec -- sh -c 'one\ntwo'"]
+        );
+
+        assert_eq!(unescape_rustyline("bad \\t escape"), "bad \\t escape");
+    }
+}
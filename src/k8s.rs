@@ -16,21 +16,23 @@ use base64::engine::{general_purpose::STANDARD, Engine};
 use bytes::Bytes;
 use hickory_resolver::{config::*, Resolver};
 use k8s_openapi::{http, List, ListableResource};
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, RequestBuilder};
 use reqwest::{Certificate, Identity, Url};
 use serde::Deserialize;
 use std::net::{IpAddr, SocketAddr};
 
-use std::cell::RefCell;
-use std::fmt::Debug;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::{
-    config::{AuthProvider, ExecAuth, ExecProvider},
+    config::{AuthProvider, ExecAuth, ExecProvider, ProxyConfig},
     error::{ClickErrNo, ClickError},
+    values::val_str_opt,
 };
 
 // Helper function to create custom DNS mapping from server URL and TLS server name
@@ -53,6 +55,7 @@ pub enum UserAuth {
     ExecProvider(Box<ExecProvider>),
     Ident(Identity),
     Token(String),
+    TokenFile(TokenFile),
     UserPass(String, String),
     //KeyCert(PathBuf, PathBuf),
 }
@@ -74,6 +77,13 @@ impl UserAuth {
         Ok(UserAuth::Token(token))
     }
 
+    pub fn with_token_file<P>(path: P) -> Result<UserAuth, ClickError>
+    where
+        PathBuf: From<P>,
+    {
+        Ok(UserAuth::TokenFile(TokenFile::new(PathBuf::from(path))?))
+    }
+
     pub fn with_user_pass(user: String, pass: String) -> Result<UserAuth, ClickError> {
         Ok(UserAuth::UserPass(user, pass))
     }
@@ -96,6 +106,76 @@ impl UserAuth {
         let id = get_id_from_data(key_decoded, cert_decoded)?;
         Ok(UserAuth::Ident(id))
     }
+
+    /// same as above, but from pems that aren't base64 encoded, as exec plugins return them
+    pub fn from_key_cert_pem(key: String, cert: String) -> Result<UserAuth, ClickError> {
+        let id = get_id_from_data(key.into_bytes(), cert.into_bytes())?;
+        Ok(UserAuth::Ident(id))
+    }
+    /// What kind of credentials these are, for messages
+    pub fn describe(&self) -> String {
+        match self {
+            UserAuth::AuthProvider(provider) => format!("auth-provider {}", provider.name()),
+            UserAuth::ExecProvider(provider) => format!("exec plugin {}", provider.command()),
+            UserAuth::Ident(_) => "client certificate".to_string(),
+            UserAuth::Token(_) => "token".to_string(),
+            UserAuth::TokenFile(token_file) => {
+                format!("token file {}", token_file.path.display())
+            }
+            UserAuth::UserPass(user, _) => format!("username/password for {user}"),
+        }
+    }
+}
+
+/// How often a token file is read again. Projected service account tokens rotate, and a rotated
+/// token should be picked up before the old one is rejected.
+const TOKEN_FILE_REREAD: Duration = Duration::from_secs(60);
+
+/// A bearer token read from a file, which is read again every TOKEN_FILE_REREAD, and when the
+/// server rejects the token, in case whatever writes the file has rotated it
+#[derive(Clone)]
+pub struct TokenFile {
+    path: PathBuf,
+    token: RefCell<String>,
+    read_at: Cell<Instant>,
+}
+
+impl TokenFile {
+    fn new(path: PathBuf) -> Result<TokenFile, ClickError> {
+        let token = TokenFile::read(&path)?;
+        Ok(TokenFile {
+            path,
+            token: RefCell::new(token),
+            read_at: Cell::new(Instant::now()),
+        })
+    }
+
+    fn read(path: &PathBuf) -> Result<String, ClickError> {
+        let token = std::fs::read_to_string(path).map_err(|e| {
+            ClickError::ConfigFileError(format!(
+                "Could not read token file {}: {e}",
+                path.display()
+            ))
+        })?;
+        Ok(token.trim().to_string())
+    }
+
+    fn token(&self) -> String {
+        if self.read_at.get().elapsed() > TOKEN_FILE_REREAD {
+            // if the file is briefly unreadable (i.e. mid-rotation) keep using the old token
+            let _ = self.reload();
+        }
+        self.token.borrow().clone()
+    }
+
+    /// Read the file again, returning true if the token changed
+    fn reload(&self) -> Result<bool, ClickError> {
+        let token = TokenFile::read(&self.path)?;
+        self.read_at.set(Instant::now());
+        let changed = *self.token.borrow() != token;
+        *self.token.borrow_mut() = token;
+        Ok(changed)
+    }
 }
 
 fn get_id_from_paths(key: PathBuf, cert: PathBuf) -> Result<Identity, ClickError> {
@@ -111,6 +191,175 @@ fn get_id_from_data(mut key: Vec<u8>, mut cert: Vec<u8>) -> Result<Identity, Cli
     Identity::from_pem(&key).map_err(|e| e.into())
 }
 
+/// Who to act as for requests, sent as the Impersonate-* headers
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Impersonation {
+    pub user: String,
+    pub groups: Vec<String>,
+    pub uid: Option<String>,
+    pub extra: BTreeMap<String, Vec<String>>,
+}
+
+impl Impersonation {
+    pub fn user<S: Into<String>>(user: S) -> Impersonation {
+        Impersonation {
+            user: user.into(),
+            ..Default::default()
+        }
+    }
+
+    fn add_headers(&self, req: RequestBuilder) -> RequestBuilder {
+        let mut req = req.header("Impersonate-User", &self.user);
+        for group in self.groups.iter() {
+            req = req.header("Impersonate-Group", group);
+        }
+        if let Some(uid) = self.uid.as_ref() {
+            req = req.header("Impersonate-Uid", uid);
+        }
+        for (key, values) in self.extra.iter() {
+            let name = format!("Impersonate-Extra-{}", encode_extra_key(key));
+            for value in values.iter() {
+                req = req.header(name.as_str(), value);
+            }
+        }
+        req
+    }
+
+    /// The arguments that make kubectl impersonate the same way. kubectl has no flag for extra
+    /// fields, and leaving them out could mean acting with different permissions, so that's an
+    /// error.
+    pub fn kubectl_args(&self) -> Result<Vec<String>, ClickError> {
+        if !self.extra.is_empty() {
+            let keys: Vec<&str> = self.extra.keys().map(|key| key.as_str()).collect();
+            return Err(ClickError::CommandError(format!(
+                "This command runs kubectl, which can't impersonate with extra fields ({}). \
+                 Run `as <user>` without --extra to use it.",
+                keys.join(", ")
+            )));
+        }
+        let mut args = vec![format!("--as={}", self.user)];
+        args.extend(
+            self.groups
+                .iter()
+                .map(|group| format!("--as-group={group}")),
+        );
+        if let Some(uid) = self.uid.as_ref() {
+            args.push(format!("--as-uid={uid}"));
+        }
+        Ok(args)
+    }
+
+    /// A short form for the prompt, i.e. alice[dev,ops]
+    pub fn prompt_str(&self) -> String {
+        if self.groups.is_empty() {
+            self.user.clone()
+        } else {
+            format!("{}[{}]", self.user, self.groups.join(","))
+        }
+    }
+}
+
+impl fmt::Display for Impersonation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.user)?;
+        if !self.groups.is_empty() {
+            write!(f, ", groups: {}", self.groups.join(", "))?;
+        }
+        if let Some(uid) = self.uid.as_ref() {
+            write!(f, ", uid: {uid}")?;
+        }
+        for (key, values) in self.extra.iter() {
+            write!(f, ", {key}: {}", values.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// extra keys go in a header name, so anything that isn't allowed there is percent encoded, as
+// kubectl does
+fn encode_extra_key(key: &str) -> String {
+    key.bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"!#$&'*+-.^_`|~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{b:02X}")
+            }
+        })
+        .collect()
+}
+
+/// The can-i command that checks whether a request like this one is allowed, or None if the uri
+/// isn't one click knows how to ask about
+pub fn can_i_suggestion(method: &http::Method, uri: &http::Uri) -> Option<String> {
+    let segments: Vec<&str> = uri.path().split('/').filter(|s| !s.is_empty()).collect();
+    let (group, rest) = match segments.as_slice() {
+        ["api", _version, rest @ ..] => ("", rest),
+        ["apis", group, _version, rest @ ..] => (*group, rest),
+        // anything else is a non-resource url, where the verb is just the http method
+        _ => {
+            let verb = method.as_str().to_lowercase();
+            return Some(format!("can-i {verb} {}", uri.path()));
+        }
+    };
+    let (namespace, rest) = match rest {
+        ["namespaces", namespace, rest @ ..] if !rest.is_empty() => (Some(*namespace), rest),
+        _ => (None, rest),
+    };
+    let (resource, name, subresource) = match rest {
+        [resource] => (*resource, None, None),
+        [resource, name] => (*resource, Some(*name), None),
+        [resource, name, subresource] => (*resource, Some(*name), Some(*subresource)),
+        _ => return None,
+    };
+    let watch = uri
+        .query()
+        .map(|q| q.split('&').any(|p| p == "watch=true" || p == "watch=1"))
+        .unwrap_or(false);
+    let verb = match *method {
+        http::Method::GET if watch => "watch",
+        http::Method::GET if name.is_some() => "get",
+        http::Method::GET => "list",
+        http::Method::POST => "create",
+        http::Method::PUT => "update",
+        http::Method::PATCH => "patch",
+        http::Method::DELETE if name.is_some() => "delete",
+        http::Method::DELETE => "deletecollection",
+        _ => return None,
+    };
+
+    let mut cmd = format!("can-i {verb} {resource}");
+    if !group.is_empty() {
+        cmd.push('.');
+        cmd.push_str(group);
+    }
+    if let Some(subresource) = subresource {
+        cmd.push('/');
+        cmd.push_str(subresource);
+    }
+    if let Some(name) = name {
+        cmd.push(' ');
+        cmd.push_str(name);
+    }
+    match namespace {
+        Some(namespace) => cmd.push_str(&format!(" -n {namespace}")),
+        None => cmd.push_str(" -A"),
+    }
+    Some(cmd)
+}
+
+// the error for a 403, with the server's message and how to check the permission that was missing
+fn forbidden(method: &http::Method, uri: &http::Uri, body: &[u8]) -> ClickError {
+    let message = serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|val| val_str_opt("/message", &val))
+        .unwrap_or_else(|| "the server refused the request".to_string());
+    ClickError::Kube(ClickErrNo::Forbidden(
+        message,
+        can_i_suggestion(method, uri),
+    ))
+}
+
 pub struct Context {
     pub name: String,
     pub endpoint: Url,
@@ -123,6 +372,9 @@ pub struct Context {
     read_timeout_secs: u32,
     server_url: String,
     tls_server_name: Option<String>,
+    // describes auth, which get_client takes for client certificates
+    auth_method: String,
+    pub proxy: Option<ProxyConfig>,
 }
 
 impl Context {
@@ -137,15 +389,20 @@ impl Context {
         read_timeout_secs: u32,
         server_url: String,
         tls_server_name: Option<String>,
+        proxy: Option<ProxyConfig>,
     ) -> Context {
+        let auth_method = auth
+            .as_ref()
+            .map(UserAuth::describe)
+            .unwrap_or_else(|| "no credentials".to_string());
         let (client, client_auth) = Context::get_client(
             root_cas.clone(),
             auth.clone(),
-            None,
             connect_timeout_secs,
             read_timeout_secs,
             &server_url,
             &tls_server_name,
+            &proxy,
         );
         // have to create a special client for logs until
         // https://github.com/seanmonstar/reqwest/issues/1380
@@ -153,11 +410,11 @@ impl Context {
         let (log_client, _) = Context::get_client(
             root_cas.clone(),
             auth,
-            None,
             u32::MAX,
             u32::MAX,
             &server_url,
             &tls_server_name,
+            &proxy,
         );
         let client = RefCell::new(client);
         let log_client = RefCell::new(log_client);
@@ -174,6 +431,33 @@ impl Context {
             read_timeout_secs,
             server_url,
             tls_server_name,
+            auth_method,
+            proxy,
+        }
+    }
+
+    /// A copy of this context for making requests on another thread. It shares the credentials
+    /// and clients, so exec plugins and token refreshes the original already ran aren't run again.
+    /// Exec plugins the copy does have to run never prompt, since nobody can answer them.
+    pub fn background_copy(&self) -> Context {
+        let mut auth = self.auth.borrow().clone();
+        if let Some(UserAuth::ExecProvider(exec_provider)) = auth.as_mut() {
+            exec_provider.set_non_interactive();
+        }
+        Context {
+            name: self.name.clone(),
+            endpoint: self.endpoint.clone(),
+            client: RefCell::new(self.client.borrow().clone()),
+            log_client: RefCell::new(self.log_client.borrow().clone()),
+            root_cas: self.root_cas.clone(),
+            auth: RefCell::new(auth),
+            impersonate_user: self.impersonate_user.clone(),
+            connect_timeout_secs: self.connect_timeout_secs,
+            read_timeout_secs: self.read_timeout_secs,
+            server_url: self.server_url.clone(),
+            tls_server_name: self.tls_server_name.clone(),
+            auth_method: self.auth_method.clone(),
+            proxy: self.proxy.clone(),
         }
     }
 
@@ -181,13 +465,19 @@ impl Context {
     fn get_client(
         root_cas: Option<Vec<Certificate>>,
         auth: Option<UserAuth>,
-        id: Option<Identity>,
         connect_timeout_secs: u32,
         read_timeout_secs: u32,
         server_url: &str,
         tls_server_name: &Option<String>,
+        proxy: &Option<ProxyConfig>,
     ) -> (Client, Option<UserAuth>) {
-        let mut client = Client::builder().use_rustls_tls();
+        // the proxy, including any from the environment, was picked in ProxyConfig, so don't let
+        // reqwest pick one itself
+        let mut client = Client::builder().use_rustls_tls().no_proxy();
+        if let Some(proxy) = proxy {
+            // unwrap okay, the url was validated when the config was loaded
+            client = client.proxy(reqwest::Proxy::all(proxy.url.as_str()).unwrap());
+        }
 
         // Create custom DNS mapping if we have a TLS server name
         if let Some(tls_name) = tls_server_name {
@@ -213,10 +503,6 @@ impl Context {
             },
             None => (client, auth),
         };
-        let client = match id {
-            Some(id) => client.identity(id),
-            None => client,
-        };
         (
             client
                 .connect_timeout(Duration::new(connect_timeout_secs.into(), 0))
@@ -227,82 +513,178 @@ impl Context {
         )
     }
 
-    fn handle_exec_provider(&self, exec_provider: &ExecProvider) -> Option<UserAuth> {
-        let (auth, was_expired) = exec_provider.get_auth();
-        match auth {
-            ExecAuth::Token(_) => {} // handled below
-            ExecAuth::ClientCertKey {
+    // exec plugins can return a client certificate instead of a token, in which case the clients
+    // need rebuilding with it every time the plugin runs
+    fn handle_exec_provider(&self, exec_provider: &ExecProvider) -> Result<(), ClickError> {
+        let (auth, was_expired) = exec_provider.get_auth()?;
+        if was_expired {
+            if let ExecAuth::ClientCertKey {
                 cert_data,
                 key_data,
-                ..
-            } => {
-                if was_expired {
-                    let id =
-                        get_id_from_data(key_data.into_bytes(), cert_data.into_bytes()).unwrap(); // TODO: Handle error
-                    let (new_client, new_auth) = Context::get_client(
-                        self.root_cas.clone(),
-                        self.auth.clone().take(),
-                        Some(id.clone()),
-                        self.connect_timeout_secs,
-                        self.read_timeout_secs,
-                        &self.server_url,
-                        &self.tls_server_name,
-                    );
-                    let (new_log_client, _) = Context::get_client(
-                        self.root_cas.clone(),
-                        self.auth.clone().take(),
-                        Some(id),
-                        u32::MAX,
-                        u32::MAX,
-                        &self.server_url,
-                        &self.tls_server_name,
-                    );
-                    *self.client.borrow_mut() = new_client;
-                    *self.log_client.borrow_mut() = new_log_client;
-                    return new_auth;
-                }
+            } = auth
+            {
+                let ident = UserAuth::from_key_cert_pem(key_data, cert_data)?;
+                self.rebuild_clients(Some(ident));
             }
         }
-        None
+        Ok(())
+    }
+
+    // make new clients, so nothing from connections made with old credentials is reused
+    fn rebuild_clients(&self, ident: Option<UserAuth>) {
+        let (new_client, _) = Context::get_client(
+            self.root_cas.clone(),
+            ident.clone(),
+            self.connect_timeout_secs,
+            self.read_timeout_secs,
+            &self.server_url,
+            &self.tls_server_name,
+            &self.proxy,
+        );
+        let (new_log_client, _) = Context::get_client(
+            self.root_cas.clone(),
+            ident,
+            u32::MAX,
+            u32::MAX,
+            &self.server_url,
+            &self.tls_server_name,
+            &self.proxy,
+        );
+        *self.client.borrow_mut() = new_client;
+        *self.log_client.borrow_mut() = new_log_client;
+    }
+
+    /// Get new credentials from wherever the current ones came from, because the server rejected
+    /// them. Returns false if there's no way to get new ones.
+    fn refresh_auth(&self) -> Result<bool, ClickError> {
+        let ident = match &*self.auth.borrow() {
+            Some(UserAuth::AuthProvider(provider)) => {
+                if !provider.refresh().map_err(|e| self.refresh_error(e))? {
+                    return Ok(false);
+                }
+                None
+            }
+            Some(UserAuth::ExecProvider(exec_provider)) => {
+                exec_provider.refresh().map_err(|e| self.refresh_error(e))?;
+                match exec_provider.get_auth()?.0 {
+                    ExecAuth::ClientCertKey {
+                        cert_data,
+                        key_data,
+                    } => Some(UserAuth::from_key_cert_pem(key_data, cert_data)?),
+                    ExecAuth::Token(_) => None,
+                }
+            }
+            Some(UserAuth::TokenFile(token_file)) => {
+                // an unchanged file has nothing new to offer
+                if !token_file.reload().map_err(|e| self.refresh_error(e))? {
+                    return Ok(false);
+                }
+                None
+            }
+            _ => return Ok(false),
+        };
+        self.rebuild_clients(ident);
+        Ok(true)
+    }
+
+    fn refresh_error(&self, err: ClickError) -> ClickError {
+        ClickError::CommandError(format!(
+            "The cluster rejected the credentials from {}, and getting new ones failed: {err}",
+            self.auth_method
+        ))
+    }
+
+    fn unauthorized(&self, retried: bool) -> ClickError {
+        let method = if retried {
+            format!("{} (refreshed and retried)", self.auth_method)
+        } else {
+            self.auth_method.clone()
+        };
+        ClickError::Kube(ClickErrNo::Unauthorized(method))
     }
 
     pub fn execute(
         &self,
-        impersonate_user: Option<&str>,
+        impersonate: Option<&Impersonation>,
         k8sreq: http::Request<Vec<u8>>,
     ) -> Result<http::Response<Bytes>, ClickError> {
         let (parts, body) = k8sreq.into_parts();
 
         let url = self.endpoint.join(&parts.uri.to_string())?;
 
-        let new_provider = {
-            // TODO: Fix this mess
-            if let Some(UserAuth::ExecProvider(ref exec_provider)) = *self.auth.borrow() {
-                self.handle_exec_provider(exec_provider)
-            } else {
-                None
+        let mut response = self.send(
+            impersonate,
+            &parts.method,
+            url.clone(),
+            parts.headers.clone(),
+            body.clone(),
+        )?;
+        if response.status() == http::StatusCode::UNAUTHORIZED {
+            // the credentials were rejected before they looked expired, so try once more with new
+            // ones
+            if !self.refresh_auth()? {
+                return Err(self.unauthorized(false));
             }
-        };
-        if let Some(new_provider) = new_provider {
-            self.auth.borrow_mut().replace(new_provider);
+            response = self.send(impersonate, &parts.method, url, parts.headers, body)?;
+            if response.status() == http::StatusCode::UNAUTHORIZED {
+                return Err(self.unauthorized(true));
+            }
+        }
+        if response.status() == http::StatusCode::FORBIDDEN {
+            return Err(forbidden(&parts.method, &parts.uri, response.body()));
+        }
+        Ok(response)
+    }
+
+    /// The bearer token sent with requests, or None if the credentials aren't a token
+    pub fn bearer_token(&self) -> Result<Option<String>, ClickError> {
+        match &*self.auth.borrow() {
+            Some(UserAuth::AuthProvider(provider)) => provider.get_token().map(Some),
+            Some(UserAuth::ExecProvider(exec_provider)) => {
+                self.handle_exec_provider(exec_provider)?;
+                match exec_provider.get_auth()?.0 {
+                    ExecAuth::Token(token) => Ok(Some(token)),
+                    ExecAuth::ClientCertKey { .. } => Ok(None),
+                }
+            }
+            Some(UserAuth::Token(token)) => Ok(Some(token.clone())),
+            Some(UserAuth::TokenFile(token_file)) => Ok(Some(token_file.token())),
+            _ => Ok(None),
+        }
+    }
+
+    // build a request with the given client, adding impersonation and credentials
+    fn build_request(
+        &self,
+        log_client: bool,
+        impersonate: Option<&Impersonation>,
+        method: &http::Method,
+        url: Url,
+    ) -> Result<RequestBuilder, ClickError> {
+        if let Some(UserAuth::ExecProvider(ref exec_provider)) = *self.auth.borrow() {
+            self.handle_exec_provider(exec_provider)?;
         }
 
-        let req = match parts.method {
-            http::method::Method::GET => self.client.borrow().get(url),
-            http::method::Method::POST => self.client.borrow().post(url),
-            http::method::Method::DELETE => self.client.borrow().delete(url),
+        let client = if log_client {
+            self.log_client.borrow()
+        } else {
+            self.client.borrow()
+        };
+        let req = match *method {
+            http::method::Method::GET => client.get(url),
+            http::method::Method::POST => client.post(url),
+            http::method::Method::DELETE => client.delete(url),
             _ => unimplemented!(),
         };
 
-        let req = if let Some(user) = impersonate_user {
-            req.header("Impersonate-User", user)
+        let req = if let Some(impersonate) = impersonate {
+            impersonate.add_headers(req)
         } else if let Some(user) = self.impersonate_user.as_ref() {
             req.header("Impersonate-User", user)
         } else {
             req
         };
 
-        let req = req.headers(parts.headers).body(body);
         let req = match &*self.auth.borrow() {
             Some(auth) => match auth {
                 UserAuth::AuthProvider(provider) => {
@@ -310,19 +692,32 @@ impl Context {
                     req.bearer_auth(token)
                 }
                 UserAuth::ExecProvider(ref exec_provider) => {
-                    let (auth, _) = exec_provider.get_auth();
+                    let (auth, _) = exec_provider.get_auth()?;
                     match auth {
                         ExecAuth::Token(token) => req.bearer_auth(token),
                         ExecAuth::ClientCertKey { .. } => req, // handled above
                     }
                 }
                 UserAuth::Token(token) => req.bearer_auth(token),
+                UserAuth::TokenFile(token_file) => req.bearer_auth(token_file.token()),
                 UserAuth::UserPass(user, pass) => req.basic_auth(user, Some(pass)),
                 _ => req,
             },
             None => req,
         };
-        let resp = req.send()?;
+        Ok(req)
+    }
+
+    fn send(
+        &self,
+        impersonate: Option<&Impersonation>,
+        method: &http::Method,
+        url: Url,
+        headers: http::HeaderMap,
+        body: Vec<u8>,
+    ) -> Result<http::Response<Bytes>, ClickError> {
+        let req = self.build_request(false, impersonate, method, url)?;
+        let resp = req.headers(headers).body(body).send()?;
         let stat = resp.status();
         let bytes = resp.bytes()?;
 
@@ -332,11 +727,29 @@ impl Context {
             .unwrap())
     }
 
+    fn send_reader(
+        &self,
+        impersonate: Option<&Impersonation>,
+        method: &http::Method,
+        url: Url,
+        body: Vec<u8>,
+        timeout: Option<Duration>,
+    ) -> Result<reqwest::blocking::Response, ClickError> {
+        let req = self
+            .build_request(true, impersonate, method, url)?
+            .body(body);
+        let req = match timeout {
+            Some(timeout) => req.timeout(timeout),
+            None => req, // log_client above already has a super long timeout
+        };
+        req.send().map_err(ClickError::from)
+    }
+
     // execute a request and return the reqwest response. this implements io::Read so it can be used
     // for streaming operations like logs
     pub fn execute_reader(
         &self,
-        impersonate_user: Option<&str>,
+        impersonate: Option<&Impersonation>,
         k8sreq: http::Request<Vec<u8>>,
         timeout: Option<Duration>,
     ) -> Result<reqwest::blocking::Response, ClickError> {
@@ -344,54 +757,27 @@ impl Context {
 
         let url = self.endpoint.join(&parts.uri.to_string())?;
 
-        if let Some(UserAuth::ExecProvider(ref exec_provider)) = *self.auth.borrow() {
-            self.handle_exec_provider(exec_provider);
+        let mut resp = self.send_reader(
+            impersonate,
+            &parts.method,
+            url.clone(),
+            body.clone(),
+            timeout,
+        )?;
+        if resp.status() == http::StatusCode::UNAUTHORIZED {
+            if !self.refresh_auth()? {
+                return Err(self.unauthorized(false));
+            }
+            resp = self.send_reader(impersonate, &parts.method, url, body, timeout)?;
+            if resp.status() == http::StatusCode::UNAUTHORIZED {
+                return Err(self.unauthorized(true));
+            }
         }
 
-        let req = match parts.method {
-            http::method::Method::GET => self.log_client.borrow().get(url),
-            http::method::Method::POST => self.log_client.borrow().post(url),
-            http::method::Method::DELETE => self.log_client.borrow().delete(url),
-            _ => unimplemented!(),
-        };
-
-        let req = if let Some(user) = impersonate_user {
-            req.header("Impersonate-User", user)
-        } else if let Some(user) = self.impersonate_user.as_ref() {
-            req.header("Impersonate-User", user)
-        } else {
-            req
-        };
-
-        let req = req.body(body);
-        let req = match &*self.auth.borrow() {
-            Some(auth) => match auth {
-                UserAuth::AuthProvider(provider) => {
-                    let token = provider.get_token()?;
-                    req.bearer_auth(token)
-                }
-                UserAuth::ExecProvider(ref exec_provider) => {
-                    let (auth, _) = exec_provider.get_auth();
-                    match auth {
-                        ExecAuth::Token(token) => req.bearer_auth(token),
-                        ExecAuth::ClientCertKey { .. } => req, // handled above
-                    }
-                }
-                UserAuth::Token(token) => req.bearer_auth(token),
-                UserAuth::UserPass(user, pass) => req.basic_auth(user, Some(pass)),
-                _ => req,
-            },
-            None => req,
-        };
-
-        let req = match timeout {
-            Some(timeout) => req.timeout(timeout),
-            None => req, // log_client above already has a super long timeout
-        };
-
-        let resp = req.send()?;
-
-        if resp.status().is_success() {
+        if resp.status() == http::StatusCode::FORBIDDEN {
+            let body = resp.bytes()?;
+            Err(forbidden(&parts.method, &parts.uri, &body))
+        } else if resp.status().is_success() {
             Ok(resp)
         } else {
             let err = match resp.error_for_status_ref() {
@@ -405,10 +791,10 @@ impl Context {
 
     pub fn read<T: k8s_openapi::Response + Debug>(
         &self,
-        impersonate_user: Option<&str>,
+        impersonate: Option<&Impersonation>,
         k8sreq: http::Request<Vec<u8>>,
     ) -> Result<T, ClickError> {
-        let response = self.execute(impersonate_user, k8sreq)?;
+        let response = self.execute(impersonate, k8sreq)?;
         let status_code: http::StatusCode = response.status();
         match k8s_openapi::Response::try_from_parts(status_code, response.body()) {
             Ok((res, _)) => Ok(res),
@@ -419,10 +805,10 @@ impl Context {
 
     pub fn execute_list<T: ListableResource + for<'de> Deserialize<'de> + Debug>(
         &self,
-        impersonate_user: Option<&str>,
+        impersonate: Option<&Impersonation>,
         k8sreq: http::Request<Vec<u8>>,
     ) -> Result<List<T>, ClickError> {
-        let response = self.execute(impersonate_user, k8sreq)?;
+        let response = self.execute(impersonate, k8sreq)?;
         let status_code: http::StatusCode = response.status();
 
         let res_list: List<T> =
@@ -434,7 +820,7 @@ impl Context {
                 // (not HTTP 200, but still parsed successfully)
                 Ok(other) => {
                     if status_code == http::StatusCode::UNAUTHORIZED {
-                        return Err(ClickError::Kube(ClickErrNo::Unauthorized));
+                        return Err(self.unauthorized(false));
                     } else {
                         return Err(ClickError::ParseErr(
                             // TODO maybe a special error type for this
@@ -448,3 +834,241 @@ impl Context {
         Ok(res_list)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub_server::{self, StubRequest, StubResponse};
+    use std::thread::JoinHandle;
+    use tempdir::TempDir;
+
+    // an api server that answers with responses, as a Url
+    fn stub_server(responses: Vec<StubResponse>) -> (Url, JoinHandle<Vec<StubRequest>>) {
+        let (url, handle) = stub_server::serve(responses);
+        (Url::parse(&url).unwrap(), handle)
+    }
+
+    fn ok() -> StubResponse {
+        StubResponse::new("200 OK", "{}")
+    }
+
+    fn unauthorized() -> StubResponse {
+        StubResponse::new("401 Unauthorized", "{}")
+    }
+
+    fn tokens(handle: JoinHandle<Vec<StubRequest>>) -> Vec<String> {
+        handle
+            .join()
+            .unwrap()
+            .iter()
+            .map(|request| request.bearer_token().to_string())
+            .collect()
+    }
+
+    fn test_context(url: Url, auth: UserAuth) -> Context {
+        let server = url.to_string();
+        Context::new(
+            "test",
+            url,
+            None,
+            Some(auth),
+            None,
+            5,
+            5,
+            server,
+            None,
+            None,
+        )
+    }
+
+    fn request() -> http::Request<Vec<u8>> {
+        http::Request::get("/api").body(vec![]).unwrap()
+    }
+
+    #[test]
+    fn test_retry_with_reread_token_file() {
+        let dir = TempDir::new("click_token_file").unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "old-token\n").unwrap();
+        let auth = UserAuth::with_token_file(path.clone()).unwrap();
+        assert_eq!(auth.describe(), format!("token file {}", path.display()));
+
+        let (url, handle) = stub_server(vec![unauthorized(), ok()]);
+        let context = test_context(url, auth);
+        // rotated after click read it
+        std::fs::write(&path, "new-token\n").unwrap();
+        let response = context.execute(None, request()).unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(tokens(handle), vec!["old-token", "new-token"]);
+    }
+
+    #[test]
+    fn test_unauthorized_names_method() {
+        let (url, handle) = stub_server(vec![unauthorized()]);
+        let context = test_context(url, UserAuth::with_token("bad-token".to_string()).unwrap());
+        match context.execute(None, request()) {
+            Err(ClickError::Kube(ClickErrNo::Unauthorized(method))) => assert_eq!(method, "token"),
+            other => panic!("expected unauthorized, got {other:?}"),
+        }
+        // a plain token can't be refreshed, so there's no retry
+        assert_eq!(tokens(handle), vec!["bad-token"]);
+    }
+
+    #[test]
+    fn test_token_file_reread() {
+        let dir = TempDir::new("click_token_reread").unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "first").unwrap();
+        let token_file = TokenFile::new(path.clone()).unwrap();
+        std::fs::write(&path, "second").unwrap();
+        // not re-read until TOKEN_FILE_REREAD has passed
+        assert_eq!(token_file.token(), "first");
+        let long_ago = Instant::now().checked_sub(TOKEN_FILE_REREAD * 2).unwrap();
+        token_file.read_at.set(long_ago);
+        assert_eq!(token_file.token(), "second");
+    }
+
+    #[test]
+    fn test_requests_go_through_proxy() {
+        let (proxy_url, handle) = stub_server(vec![ok()]);
+        let proxy = ProxyConfig {
+            url: proxy_url,
+            source: crate::config::ProxySource::Kubeconfig,
+        };
+        // not resolvable, so this only works if the proxy is used
+        let url = Url::parse("http://k8s.invalid").unwrap();
+        let auth = UserAuth::with_token("new-token".to_string()).unwrap();
+        let context = Context::new(
+            "test",
+            url,
+            None,
+            Some(auth),
+            None,
+            5,
+            5,
+            "http://k8s.invalid".to_string(),
+            None,
+            Some(proxy),
+        );
+        let response = context.execute(None, request()).unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].line, "GET http://k8s.invalid/api HTTP/1.1");
+        assert_eq!(requests[0].bearer_token(), "new-token");
+    }
+
+    #[test]
+    fn test_impersonation_headers() {
+        let mut impersonation = Impersonation::user("jane");
+        impersonation.groups = vec!["dev".to_string(), "ops".to_string()];
+        impersonation.uid = Some("1234".to_string());
+        assert_eq!(
+            impersonation.kubectl_args().unwrap(),
+            vec![
+                "--as=jane",
+                "--as-group=dev",
+                "--as-group=ops",
+                "--as-uid=1234"
+            ]
+        );
+        impersonation
+            .extra
+            .insert("example.com/scopes".to_string(), vec!["view".to_string()]);
+        // kubectl can't send extra fields, so it's not run without them
+        assert_eq!(
+            impersonation.kubectl_args().unwrap_err().to_string(),
+            "Error running command: This command runs kubectl, which can't impersonate with \
+             extra fields (example.com/scopes). Run `as <user>` without --extra to use it."
+        );
+        assert_eq!(impersonation.prompt_str(), "jane[dev,ops]");
+        assert_eq!(
+            impersonation.to_string(),
+            "jane, groups: dev, ops, uid: 1234, example.com/scopes: view"
+        );
+
+        let (url, handle) = stub_server(vec![ok(), ok()]);
+        let context = test_context(url, UserAuth::with_token("token".to_string()).unwrap());
+        context.execute(Some(&impersonation), request()).unwrap();
+        // log streams go through execute_reader
+        context
+            .execute_reader(Some(&impersonation), request(), None)
+            .unwrap();
+        let expected = vec![
+            "impersonate-user: jane",
+            "impersonate-group: dev",
+            "impersonate-group: ops",
+            "impersonate-uid: 1234",
+            "impersonate-extra-example.com%2fscopes: view",
+        ];
+        let impersonate_headers: Vec<Vec<String>> = handle
+            .join()
+            .unwrap()
+            .iter()
+            .map(|request| {
+                request
+                    .headers
+                    .iter()
+                    .filter(|(name, _)| name.to_ascii_lowercase().starts_with("impersonate-"))
+                    .map(|(name, value)| format!("{name}: {value}").to_ascii_lowercase())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(impersonate_headers, vec![expected.clone(), expected]);
+    }
+
+    #[test]
+    fn test_can_i_suggestion() {
+        let suggest = |method: http::Method, uri: &str| {
+            can_i_suggestion(&method, &uri.parse::<http::Uri>().unwrap())
+        };
+        assert_eq!(
+            suggest(http::Method::GET, "/api/v1/namespaces/dev/pods?limit=10").unwrap(),
+            "can-i list pods -n dev"
+        );
+        assert_eq!(
+            suggest(http::Method::GET, "/api/v1/namespaces/dev/pods/web/log").unwrap(),
+            "can-i get pods/log web -n dev"
+        );
+        assert_eq!(
+            suggest(
+                http::Method::DELETE,
+                "/apis/apps/v1/namespaces/dev/deployments/web"
+            )
+            .unwrap(),
+            "can-i delete deployments.apps web -n dev"
+        );
+        assert_eq!(
+            suggest(http::Method::GET, "/api/v1/nodes?watch=true").unwrap(),
+            "can-i watch nodes -A"
+        );
+        assert_eq!(
+            suggest(http::Method::GET, "/api/v1/namespaces/dev").unwrap(),
+            "can-i get namespaces dev -A"
+        );
+        assert_eq!(
+            suggest(http::Method::GET, "/version").unwrap(),
+            "can-i get /version"
+        );
+        assert_eq!(suggest(http::Method::GET, "/api/v1/a/b/c/d"), None);
+    }
+
+    #[test]
+    fn test_forbidden_suggests_can_i() {
+        let (url, handle) = stub_server(vec![StubResponse::new(
+            "403 Forbidden",
+            r#"{"message":"pods is forbidden"}"#,
+        )]);
+        let context = test_context(url, UserAuth::with_token("token".to_string()).unwrap());
+        let request = http::Request::get("/api/v1/namespaces/dev/pods")
+            .body(vec![])
+            .unwrap();
+        match context.execute(None, request) {
+            Err(ClickError::Kube(ClickErrNo::Forbidden(message, suggestion))) => {
+                assert_eq!(message, "pods is forbidden");
+                assert_eq!(suggestion.unwrap(), "can-i list pods -n dev");
+            }
+            other => panic!("expected forbidden, got {other:?}"),
+        }
+        handle.join().unwrap();
+    }
+}
@@ -119,6 +119,7 @@ impl K8sTable {
                     _type: _type.to_string(),
                     group_version: group_version.to_string(),
                 },
+                context: None,
             });
         }
         crate::table::print_table(titles, rows, env, writer);
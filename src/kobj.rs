@@ -59,6 +59,9 @@ pub struct KObj {
     pub name: String,
     pub namespace: Option<String>,
     pub typ: ObjType,
+    /// The context this object lives in, if it's not the current one (set for objects listed by
+    /// `across`)
+    pub context: Option<String>,
 }
 
 pub struct VecWrap {
@@ -77,6 +80,7 @@ impl KObj {
             name,
             namespace: val_str_opt("/metadata/namespace", value),
             typ,
+            context: None,
         })
     }
 
@@ -131,11 +135,10 @@ impl KObj {
             ($read_func:expr, $resp_typ:ty, $resp_ok:path, $($desc_func: expr),*) => {{
                 let (request, _) = $read_func(&self.name, Default::default())?;
                 match env
-                    .run_on_context(|c| c.read::<$resp_typ>(env.get_impersonate_user(), request))
-                    .unwrap()
+                    .run_on_context(|c| c.read::<$resp_typ>(env.get_impersonation(), request))?
                 {
                     $resp_ok(t) => {
-                        if !describe::maybe_full_describe_output(matches, &t, writer) {
+                        if !describe::maybe_full_describe_output(matches, &t, &env.styles, writer) {
                             $(
                                 $desc_func(&t, &mut table)?;
                             )*
@@ -152,11 +155,10 @@ impl KObj {
                     Some(ns) => {
                         let (request, _) = $read_func(&self.name, ns, Default::default())?;
                         match env
-                            .run_on_context(|c| c.read::<$resp_typ>(env.get_impersonate_user(), request))
-                            .unwrap()
+                            .run_on_context(|c| c.read::<$resp_typ>(env.get_impersonation(), request))?
                         {
                             $resp_ok(t) => {
-                                if !describe::maybe_full_describe_output(matches, &t, writer) {
+                                if !describe::maybe_full_describe_output(matches, &t, &env.styles, writer) {
                                     $(
                                         $desc_func(&t, &mut table)?;
                                     )*
@@ -241,7 +243,7 @@ impl KObj {
                     api::Pod::read_namespaced_pod,
                     api::ReadNamespacedPodResponse,
                     api::ReadNamespacedPodResponse::Ok,
-                    describe::legacy::describe_format_pod
+                    |pod, table| describe::legacy::describe_format_pod(pod, table, &env.styles)
                 );
             }
             ObjType::ReplicaSet => {
@@ -292,7 +294,7 @@ impl KObj {
             } => {
                 describe::crd::crd_describe(
                     &self.name,
-                    self.namespace.as_ref().unwrap(),
+                    self.namespace.as_deref(),
                     _type,
                     group_version,
                     matches,
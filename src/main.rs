@@ -37,6 +37,7 @@ extern crate duct_sh;
 extern crate humantime;
 extern crate os_pipe;
 extern crate regex;
+extern crate ring;
 extern crate rustls;
 extern crate rustyline;
 extern crate serde;
@@ -57,14 +58,17 @@ mod command;
 #[macro_use]
 mod completer;
 mod command_processor;
+mod completion_cache;
 mod config;
 mod crd;
 mod describe;
 mod env;
 mod error;
+mod history;
 mod k8s;
 mod k8s_table;
 mod kobj;
+mod pager;
 mod parser;
 mod styles;
 mod table;
@@ -72,13 +76,15 @@ mod values;
 
 #[cfg(test)]
 mod duct_mock;
+#[cfg(test)]
+mod stub_server;
 
 use clap::{Arg, Command as ClapCommand};
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::command_processor::CommandProcessor;
-use crate::config::{ClickConfig, Config};
+use crate::config::{ClickConfig, Config, IN_CLUSTER_CONTEXT, SERVICE_ACCOUNT_DIR};
 use crate::env::Env;
 
 use crate::output::ClickWriter;
@@ -166,18 +172,38 @@ fn main() {
         })
         .collect::<Vec<_>>();
 
-    let config = match Config::from_files(&config_paths) {
-        Ok(c) => c,
+    // in a pod, the service account is enough to get going, so a kubeconfig is optional
+    let in_cluster = Config::in_cluster_available();
+    let (mut config, only_in_cluster) = match Config::from_files(&config_paths) {
+        Ok(c) => (c, false),
+        Err(e) if in_cluster => {
+            println!(
+                "Could not load kubernetes config, only the in-cluster context is available. \
+                 Error was: {e}"
+            );
+            (Config::empty(config_paths.join(":")), true)
+        }
         Err(e) => {
             println!("Could not load kubernetes config. Cannot continue.  Error was: {e}");
             return;
         }
     };
+    if in_cluster {
+        if let Err(e) = config.add_in_cluster_context(Path::new(SERVICE_ACCOUNT_DIR)) {
+            println!("Could not create in-cluster context: {e}");
+            if only_in_cluster {
+                return;
+            }
+        }
+    }
 
     let mut hist_path = conf_dir;
     hist_path.push("click.history");
 
     let mut env = Env::new(config, click_conf, click_path);
+    if only_in_cluster && matches.get_one::<String>("context").is_none() {
+        env.set_context(Some(IN_CLUSTER_CONTEXT));
+    }
     if let Some(context) = matches.get_one::<String>("context").map(|s| s.as_str()) {
         env.set_context(Some(context));
     }
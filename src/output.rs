@@ -14,7 +14,9 @@
 
 /// Module to handle writing data to stdout, and/or copying/writing it
 /// to files etc
-use crossterm::style::{Attribute, Color, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::style::{
+    Attribute, ContentStyle, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
 use duct::Handle;
 use duct_sh::sh_dangerous;
 use os_pipe::{pipe, PipeWriter};
@@ -22,11 +24,16 @@ use serde::ser::Serialize;
 use serde_json::ser::{CharEscape, Formatter, PrettyFormatter, Serializer};
 use serde_json::Error as JsonError;
 
+use crossterm::terminal::size;
+use crossterm::tty::IsTty;
+
 use std::fs::File;
 use std::io;
 use std::io::{Stdout, Write};
 
+use crate::config::on_path;
 use crate::error::ClickError;
+use crate::styles::Styles;
 
 /// Ignore write errors (for now) TODO: What to do with them?
 macro_rules! clickwrite {
@@ -76,12 +83,85 @@ impl PipeProc {
     }
 }
 
+/// What to do with output to the terminal that's too long to fit on it, from the pager setting
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Pager {
+    Internal,
+    External(String),
+    Off,
+}
+
+impl Pager {
+    /// The pager for the setting in the click config. Unset means internal
+    pub fn from_setting(setting: Option<&str>) -> Pager {
+        match setting {
+            None | Some("internal") => Pager::Internal,
+            Some("off") => Pager::Off,
+            Some(command) => Pager::External(command.to_string()),
+        }
+    }
+
+    /// Make sure an external pager can be run, so a typo in the setting doesn't lose output
+    pub fn validate(&self) -> Result<(), ClickError> {
+        match self {
+            Pager::External(command) => {
+                let program = command.split_whitespace().next().unwrap_or_default();
+                if on_path(program, std::env::var_os("PATH")) {
+                    Ok(())
+                } else {
+                    Err(ClickError::CommandError(format!(
+                        "Invalid pager '{command}', {program} was not found. Possible values \
+                         are: [internal, off] or a pager command, like 'less -R'"
+                    )))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Output to stdout that's held back until the command finishes, so it can be paged if it's long
+struct PagedOutput {
+    stdout: Stdout,
+    buffer: Vec<u8>,
+    pager: Pager,
+}
+
+impl PagedOutput {
+    fn finish(self) -> Stdout {
+        let mut stdout = self.stdout;
+        let fits = match size() {
+            Ok((width, height)) => {
+                let text = String::from_utf8_lossy(&self.buffer);
+                crate::pager::display_rows(&text, width as usize) < height as usize
+            }
+            Err(_) => true,
+        };
+        let res = match self.pager {
+            _ if fits => stdout.write_all(&self.buffer),
+            Pager::Internal => crate::pager::page(&String::from_utf8_lossy(&self.buffer)),
+            Pager::External(ref command) => sh_dangerous(command)
+                .stdin_bytes(self.buffer.clone())
+                .unchecked()
+                .run()
+                .map(|_| ()),
+            Pager::Off => stdout.write_all(&self.buffer),
+        };
+        if let Err(e) = res {
+            eprintln!("Failed to page output: {e}");
+            stdout.write_all(&self.buffer).unwrap_or(());
+        }
+        stdout
+    }
+}
+
 enum WriterOutput {
     Stdout(Stdout),
     #[allow(dead_code)] // used in test
     Buffer(Vec<u8>),
     File(File),
     Pipe(Box<PipeProc>),
+    Paged(Box<PagedOutput>),
 }
 
 pub struct ClickWriter {
@@ -117,7 +197,61 @@ impl ClickWriter {
         Ok(())
     }
 
-    pub fn finish_output(self) -> Option<Vec<u8>> {
+    /// Hold back output to the terminal until finish_paging is called, and page it then if it's
+    /// longer than the terminal. Does nothing if output isn't going to a terminal.
+    pub fn start_paging(&mut self, pager: Pager) {
+        if pager == Pager::Off || !std::io::stdout().is_tty() {
+            return;
+        }
+        if let WriterOutput::Stdout(_) = self.output {
+            let stdout = match std::mem::replace(&mut self.output, WriterOutput::Buffer(vec![])) {
+                WriterOutput::Stdout(stdout) => stdout,
+                _ => unreachable!(),
+            };
+            self.output = WriterOutput::Paged(Box::new(PagedOutput {
+                stdout,
+                buffer: vec![],
+                pager,
+            }));
+        }
+    }
+
+    /// Write out held back output, paging it if needed, and go back to writing straight to the
+    /// terminal
+    pub fn finish_paging(&mut self) {
+        if let WriterOutput::Paged(_) = self.output {
+            if let WriterOutput::Paged(paged) =
+                std::mem::replace(&mut self.output, WriterOutput::Buffer(vec![]))
+            {
+                self.output = WriterOutput::Stdout(paged.finish());
+            }
+        }
+    }
+
+    /// Write out held back output without paging, and send the rest straight to the terminal.
+    /// Commands that stream output or ask for input need to call this (flush does as well)
+    pub fn stop_paging(&mut self) {
+        if let WriterOutput::Paged(_) = self.output {
+            if let WriterOutput::Paged(paged) =
+                std::mem::replace(&mut self.output, WriterOutput::Buffer(vec![]))
+            {
+                let mut stdout = paged.stdout;
+                stdout.write_all(&paged.buffer).unwrap_or(());
+                self.output = WriterOutput::Stdout(stdout);
+            }
+        }
+    }
+
+    /// If output goes to the terminal, rather than to a file, a pipe or a buffer
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self.output,
+            WriterOutput::Stdout(_) | WriterOutput::Paged(_)
+        )
+    }
+
+    pub fn finish_output(mut self) -> Option<Vec<u8>> {
+        self.finish_paging();
         match self.output {
             WriterOutput::Pipe(pipe_proc) => {
                 match pipe_proc.finish() {
@@ -135,12 +269,33 @@ impl ClickWriter {
         }
     }
 
-    pub fn pretty_color_json<T>(&mut self, value: &T) -> Result<(), JsonError>
+    /// Finish output, sending anything that was written to a pipe into `out` rather than to
+    /// stdout. For other kinds of output this is the same as finish_output
+    pub fn finish_output_into(mut self, out: &mut ClickWriter) {
+        self.finish_paging();
+        match self.output {
+            WriterOutput::Pipe(pipe_proc) => match pipe_proc.finish() {
+                Ok(output) => {
+                    clickwrite!(out, "{}", output);
+                }
+                Err(e) => {
+                    eprint!("Failed to execute command: {e}");
+                }
+            },
+            _ => {
+                self.finish_output();
+            }
+        }
+    }
+
+    pub fn pretty_color_json<T>(&mut self, value: &T, styles: &Styles) -> Result<(), JsonError>
     where
         T: Serialize + ?Sized,
     {
-        if let WriterOutput::Stdout(_) = self.output {
-            let mut ser = Serializer::with_formatter(self, PrettyColorFormatter::new());
+        if let WriterOutput::Stdout(_) | WriterOutput::Paged(_) = self.output {
+            let formatter =
+                PrettyColorFormatter::new(styles.json_key_style(), styles.json_string_style());
+            let mut ser = Serializer::with_formatter(self, formatter);
             value.serialize(&mut ser)
         } else {
             // don't do color if we're piping/redirecting
@@ -169,15 +324,19 @@ impl Write for ClickWriter {
             WriterOutput::Buffer(ref mut buffer) => buffer.write(buf),
             WriterOutput::File(ref mut file) => file.write(buf),
             WriterOutput::Pipe(ref mut pipe_proc) => pipe_proc.write(buf),
+            WriterOutput::Paged(ref mut paged) => paged.buffer.write(buf),
         }
     }
 
     fn flush(&mut self) -> Result<(), io::Error> {
+        // flushing means the output has to be seen now, like before asking for input
+        self.stop_paging();
         match self.output {
             WriterOutput::Stdout(ref mut stdout) => stdout.flush(),
             WriterOutput::Buffer(ref mut buffer) => buffer.flush(),
             WriterOutput::File(ref mut file) => file.flush(),
             WriterOutput::Pipe(ref mut pipe_proc) => pipe_proc.flush(),
+            WriterOutput::Paged(_) => Ok(()), // can't happen, stop_paging was called
         }
     }
 }
@@ -186,18 +345,48 @@ pub struct PrettyColorFormatter<'a> {
     pretty: PrettyFormatter<'a>,
     invalue: bool,
     iskey: bool,
+    key_style: ContentStyle,
+    string_style: ContentStyle,
 }
 
 impl<'a> PrettyColorFormatter<'a> {
-    pub fn new() -> PrettyColorFormatter<'a> {
+    pub fn new(key_style: ContentStyle, string_style: ContentStyle) -> PrettyColorFormatter<'a> {
         PrettyColorFormatter {
             pretty: PrettyFormatter::new(),
             invalue: false,
             iskey: false,
+            key_style,
+            string_style,
         }
     }
 }
 
+// write the escape codes to start style. Nothing is written for an empty style, so that no
+// escape codes at all are output when color is disabled
+fn begin_style<W>(writer: &mut W, style: &ContentStyle)
+where
+    W: Write + ?Sized,
+{
+    if let Some(fg) = style.foreground_color {
+        write!(writer, "{}", SetForegroundColor(fg)).unwrap_or(());
+    }
+    if let Some(bg) = style.background_color {
+        write!(writer, "{}", SetBackgroundColor(bg)).unwrap_or(());
+    }
+    for attr in Attribute::iterator().filter(|attr| style.attributes.has(*attr)) {
+        write!(writer, "{}", SetAttribute(attr)).unwrap_or(());
+    }
+}
+
+fn end_style<W>(writer: &mut W, style: &ContentStyle)
+where
+    W: Write + ?Sized,
+{
+    if *style != ContentStyle::new() {
+        write!(writer, "{ResetColor}").unwrap_or(());
+    }
+}
+
 impl<'a> Formatter for PrettyColorFormatter<'a> {
     fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
@@ -288,7 +477,7 @@ impl<'a> Formatter for PrettyColorFormatter<'a> {
         W: Write + ?Sized,
     {
         if self.invalue && !self.iskey {
-            write!(writer, "{}", SetForegroundColor(Color::Green)).unwrap_or(());
+            begin_style(writer, &self.string_style);
         }
         self.pretty.begin_string(writer)
     }
@@ -299,7 +488,7 @@ impl<'a> Formatter for PrettyColorFormatter<'a> {
     {
         let r = self.pretty.end_string(writer);
         if self.invalue && !self.iskey {
-            write!(writer, "{ResetColor}").unwrap_or(());
+            end_style(writer, &self.string_style);
         }
         r
     }
@@ -366,8 +555,7 @@ impl<'a> Formatter for PrettyColorFormatter<'a> {
     {
         self.iskey = true;
         let r = self.pretty.begin_object_key(writer, first);
-        write!(writer, "{}", SetForegroundColor(Color::Blue)).unwrap_or(());
-        write!(writer, "{}", SetAttribute(Attribute::Bold)).unwrap_or(());
+        begin_style(writer, &self.key_style);
         r
     }
 
@@ -377,7 +565,7 @@ impl<'a> Formatter for PrettyColorFormatter<'a> {
     {
         let r = self.pretty.end_object_key(writer);
         self.iskey = false;
-        write!(writer, "{ResetColor}").unwrap_or(());
+        end_style(writer, &self.key_style);
         r
     }
 
@@ -398,3 +586,19 @@ impl<'a> Formatter for PrettyColorFormatter<'a> {
         self.pretty.end_object_value(writer)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pager_validate() {
+        assert!(Pager::from_setting(Some("internal")).validate().is_ok());
+        assert!(Pager::from_setting(Some("off")).validate().is_ok());
+        assert!(Pager::from_setting(Some("sh -c cat")).validate().is_ok());
+        assert!(Pager::from_setting(Some("no-such-pager -R"))
+            .validate()
+            .is_err());
+        assert!(Pager::from_setting(Some("")).validate().is_err());
+    }
+}
@@ -0,0 +1,536 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The internal pager, used by ClickWriter for output that doesn't fit on the terminal. It keeps
+//! colors, and supports searching and scrolling sideways for wide tables.
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use regex::Regex;
+
+use std::io::{self, stdout, Stdout, Write};
+use std::ops::Range;
+
+const TAB_WIDTH: usize = 8;
+
+lazy_static! {
+    static ref ANSI_ESCAPE: Regex = Regex::new("\x1b\\[[0-9;]*[A-Za-z]").unwrap();
+}
+
+/// Split a line into (is_escape, piece), so escape sequences can be skipped when counting columns
+fn pieces(line: &str) -> Vec<(bool, &str)> {
+    let mut pieces = vec![];
+    let mut last = 0;
+    for m in ANSI_ESCAPE.find_iter(line) {
+        if m.start() > last {
+            pieces.push((false, &line[last..m.start()]));
+        }
+        pieces.push((true, m.as_str()));
+        last = m.end();
+    }
+    if last < line.len() {
+        pieces.push((false, &line[last..]));
+    }
+    pieces
+}
+
+/// Replace tabs with spaces up to the next tab stop
+fn expand_tabs(line: &str) -> String {
+    if !line.contains('\t') {
+        return line.to_string();
+    }
+    let mut expanded = String::with_capacity(line.len());
+    let mut col = 0;
+    for (escape, piece) in pieces(line) {
+        if escape {
+            expanded.push_str(piece);
+            continue;
+        }
+        for c in piece.chars() {
+            if c == '\t' {
+                let spaces = TAB_WIDTH - col % TAB_WIDTH;
+                expanded.push_str(&" ".repeat(spaces));
+                col += spaces;
+            } else {
+                expanded.push(c);
+                col += 1;
+            }
+        }
+    }
+    expanded
+}
+
+/// The number of columns text takes on screen
+fn display_width(line: &str) -> usize {
+    pieces(line)
+        .iter()
+        .filter(|(escape, _)| !escape)
+        .map(|(_, piece)| piece.chars().count())
+        .sum()
+}
+
+/// How many terminal rows text takes up when printed on a terminal width columns wide
+pub fn display_rows(text: &str, width: usize) -> usize {
+    let width = width.max(1);
+    text.lines()
+        .map(|line| {
+            let cols = display_width(&expand_tabs(line));
+            cols.saturating_sub(1) / width + 1
+        })
+        .sum()
+}
+
+/// The part of line from column left that fits in width columns. All escape sequences are kept,
+/// so colors started off screen to the left still apply
+fn slice_columns(line: &str, left: usize, width: usize) -> String {
+    let mut sliced = String::new();
+    let mut col = 0;
+    let mut has_escapes = false;
+    for (escape, piece) in pieces(line) {
+        if escape {
+            sliced.push_str(piece);
+            has_escapes = true;
+            continue;
+        }
+        for c in piece.chars() {
+            if col >= left && col < left + width {
+                sliced.push(c);
+            }
+            col += 1;
+        }
+    }
+    if has_escapes {
+        sliced.push_str("\x1b[0m");
+    }
+    sliced
+}
+
+/// Column ranges in line where query matches. Ignores case unless query has an uppercase letter
+fn match_columns(line: &str, query: &str) -> Vec<Range<usize>> {
+    let ignore_case = !query.chars().any(|c| c.is_uppercase());
+    let fold = |c: char| {
+        if ignore_case {
+            c.to_lowercase().next().unwrap_or(c)
+        } else {
+            c
+        }
+    };
+    let line: Vec<char> = line.chars().map(fold).collect();
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let mut matches = vec![];
+    if query.is_empty() {
+        return matches;
+    }
+    let mut i = 0;
+    while i + query.len() <= line.len() {
+        if line[i..i + query.len()] == query[..] {
+            matches.push(i..i + query.len());
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
+    matches
+}
+
+#[derive(Debug, PartialEq)]
+enum PagerAction {
+    Continue,
+    Quit,
+}
+
+struct Pager {
+    // lines as printed, with tabs expanded
+    lines: Vec<String>,
+    // lines without escape sequences, for searching
+    plain: Vec<String>,
+    top: usize,
+    left: usize,
+    // rows and columns available for text, updated on each render
+    height: usize,
+    width: usize,
+    search: Option<String>,
+    // the search being typed
+    input: Option<String>,
+    message: Option<String>,
+}
+
+impl Pager {
+    fn new(text: &str) -> Pager {
+        let lines: Vec<String> = text.lines().map(expand_tabs).collect();
+        let plain = lines
+            .iter()
+            .map(|line| ANSI_ESCAPE.replace_all(line, "").into_owned())
+            .collect();
+        Pager {
+            lines,
+            plain,
+            top: 0,
+            left: 0,
+            height: 1,
+            width: 1,
+            search: None,
+            input: None,
+            message: None,
+        }
+    }
+
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.height)
+    }
+
+    fn scroll(&mut self, delta: isize) {
+        let top = (self.top as isize + delta).max(0) as usize;
+        self.top = top.min(self.max_top());
+    }
+
+    fn scroll_sideways(&mut self, right: bool) {
+        let step = (self.width / 2).max(1);
+        if right {
+            let widest = self.plain.iter().map(|l| l.chars().count()).max();
+            if self.left + self.width < widest.unwrap_or(0) {
+                self.left += step;
+            }
+        } else {
+            self.left = self.left.saturating_sub(step);
+        }
+    }
+
+    /// Move to the next line matching the search, starting from line from
+    fn find(&mut self, from: usize, forward: bool) {
+        let query = match self.search.as_ref() {
+            Some(query) if !self.lines.is_empty() => query,
+            _ => return,
+        };
+        let matches = |i: &usize| !match_columns(&self.plain[*i], query).is_empty();
+        let found = if forward {
+            (from..self.lines.len()).find(matches)
+        } else {
+            (0..=from.min(self.lines.len().saturating_sub(1)))
+                .rev()
+                .find(matches)
+        };
+        match found {
+            Some(line) => {
+                self.top = line.min(self.max_top());
+                // make sure the first match on the line is visible
+                if let Some(m) = match_columns(&self.plain[line], query).first() {
+                    if m.start < self.left || m.end > self.left + self.width {
+                        self.left = m.start.saturating_sub(self.width / 4);
+                    }
+                }
+            }
+            None => self.message = Some(format!("Pattern not found: {query}")),
+        }
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent) -> PagerAction {
+        let input = match self.input.as_mut() {
+            Some(input) => input,
+            None => return PagerAction::Continue,
+        };
+        match key.code {
+            KeyCode::Enter => {
+                let query = self.input.take().unwrap(); // safe, checked above
+                if !query.is_empty() {
+                    self.search = Some(query);
+                }
+                self.find(self.top, true);
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input = None
+            }
+            KeyCode::Backspace if input.is_empty() => self.input = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+        PagerAction::Continue
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> PagerAction {
+        if self.input.is_some() {
+            return self.handle_input_key(key);
+        }
+        self.message = None;
+        let page = self.height.max(1) as isize;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => return PagerAction::Quit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return PagerAction::Quit
+            }
+            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.scroll(page)
+            }
+            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.scroll(-page)
+            }
+            KeyCode::Down | KeyCode::Enter | KeyCode::Char('j') => self.scroll(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll(-1),
+            KeyCode::PageDown | KeyCode::Char(' ') | KeyCode::Char('f') => self.scroll(page),
+            KeyCode::PageUp | KeyCode::Char('b') => self.scroll(-page),
+            KeyCode::Char('d') => self.scroll(page / 2),
+            KeyCode::Char('u') => self.scroll(-page / 2),
+            KeyCode::Home | KeyCode::Char('g') => self.top = 0,
+            KeyCode::End | KeyCode::Char('G') => self.top = self.max_top(),
+            KeyCode::Right | KeyCode::Char('l') => self.scroll_sideways(true),
+            KeyCode::Left | KeyCode::Char('h') => self.scroll_sideways(false),
+            KeyCode::Char('0') => self.left = 0,
+            KeyCode::Char('/') => self.input = Some(String::new()),
+            KeyCode::Char('n') => self.find(self.top + 1, true),
+            KeyCode::Char('N') => self.find(self.top.saturating_sub(1), false),
+            _ => {}
+        }
+        PagerAction::Continue
+    }
+
+    fn render(&mut self, out: &mut Stdout) -> io::Result<()> {
+        let (width, height) = size()?;
+        self.width = (width as usize).max(1);
+        self.height = (height as usize).saturating_sub(1).max(1);
+        self.top = self.top.min(self.max_top());
+
+        for row in 0..self.height {
+            queue!(out, MoveTo(0, row as u16))?;
+            let index = self.top + row;
+            if index < self.lines.len() {
+                let matches = match self.search.as_ref() {
+                    Some(query) => match_columns(&self.plain[index], query),
+                    None => vec![],
+                };
+                if matches.is_empty() {
+                    queue!(
+                        out,
+                        Print(slice_columns(&self.lines[index], self.left, self.width))
+                    )?;
+                } else {
+                    // lines with matches are drawn without their colors, so the matches stand out
+                    self.render_matches(out, index, &matches)?;
+                }
+            } else {
+                queue!(out, Print("~"))?;
+            }
+            queue!(out, Clear(ClearType::UntilNewLine))?;
+        }
+
+        let status = match (self.input.as_ref(), self.message.as_ref()) {
+            (Some(input), _) => format!("/{input}"),
+            (None, Some(message)) => message.clone(),
+            (None, None) => {
+                let last = (self.top + self.height).min(self.lines.len());
+                let mut status = format!("lines {}-{} of {}", self.top + 1, last, self.lines.len());
+                if self.left > 0 {
+                    status.push_str(&format!(", column {}", self.left + 1));
+                }
+                if last == self.lines.len() {
+                    status.push_str(" (END)");
+                }
+                status.push_str("  q:quit  /:search  n/N:next/prev  ←/→:scroll");
+                status
+            }
+        };
+        let status: String = status.chars().take(self.width).collect();
+        queue!(out, MoveTo(0, self.height as u16))?;
+        if self.input.is_some() {
+            queue!(out, Print(status), Clear(ClearType::UntilNewLine))?;
+        } else {
+            queue!(
+                out,
+                SetAttribute(Attribute::Reverse),
+                Print(status),
+                SetAttribute(Attribute::Reset),
+                Clear(ClearType::UntilNewLine)
+            )?;
+        }
+        out.flush()
+    }
+
+    fn render_matches(
+        &self,
+        out: &mut Stdout,
+        index: usize,
+        matches: &[Range<usize>],
+    ) -> io::Result<()> {
+        let visible = self.left..self.left + self.width;
+        let mut current: Option<bool> = None;
+        let mut segment = String::new();
+        for (col, c) in self.plain[index].chars().enumerate() {
+            if !visible.contains(&col) {
+                continue;
+            }
+            let in_match = matches.iter().any(|m| m.contains(&col));
+            if current != Some(in_match) {
+                write_segment(out, &segment, current.unwrap_or(false))?;
+                segment.clear();
+                current = Some(in_match);
+            }
+            segment.push(c);
+        }
+        write_segment(out, &segment, current.unwrap_or(false))
+    }
+}
+
+fn write_segment(out: &mut Stdout, segment: &str, reverse: bool) -> io::Result<()> {
+    if reverse {
+        queue!(
+            out,
+            SetAttribute(Attribute::Reverse),
+            Print(segment),
+            SetAttribute(Attribute::Reset)
+        )
+    } else {
+        queue!(out, Print(segment))
+    }
+}
+
+// switches to the alternate screen in raw mode, and back when dropped
+struct PagerScreen;
+
+impl PagerScreen {
+    fn enter() -> io::Result<PagerScreen> {
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen, Hide)?;
+        Ok(PagerScreen)
+    }
+}
+
+impl Drop for PagerScreen {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), LeaveAlternateScreen, Show);
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Show text in the internal pager, returning when the user quits
+pub fn page(text: &str) -> io::Result<()> {
+    let mut out = stdout();
+    let mut pager = Pager::new(text);
+    let _screen = PagerScreen::enter()?;
+    loop {
+        pager.render(&mut out)?;
+        if let Event::Key(key) = read()? {
+            if key.kind != KeyEventKind::Release && pager.handle_key(key) == PagerAction::Quit {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_columns() {
+        assert_eq!(expand_tabs("a\tb"), "a       b");
+        assert_eq!(
+            expand_tabs("\x1b[1mab\x1b[0m\tc"),
+            "\x1b[1mab\x1b[0m      c"
+        );
+        assert_eq!(display_width("\x1b[32mRunning\x1b[0m"), 7);
+
+        assert_eq!(slice_columns("abcdef", 2, 3), "cde");
+        assert_eq!(
+            slice_columns("\x1b[32mabc\x1b[0mdef", 1, 3),
+            "\x1b[32mbc\x1b[0md\x1b[0m"
+        );
+
+        assert_eq!(display_rows("one\ntwo\n", 80), 2);
+        // long lines wrap
+        assert_eq!(display_rows(&"x".repeat(25), 10), 3);
+        assert_eq!(display_rows("\n", 10), 1);
+    }
+
+    #[test]
+    fn test_match_columns() {
+        assert_eq!(
+            match_columns("Running running", "running"),
+            vec![0..7, 8..15]
+        );
+        // an uppercase letter makes the search case sensitive
+        assert_eq!(match_columns("Running running", "Run"), vec![0..3]);
+        assert!(match_columns("pending", "run").is_empty());
+        assert!(match_columns("pending", "").is_empty());
+    }
+
+    #[test]
+    fn test_pager_keys() {
+        let text: Vec<String> = (0..20).map(|i| format!("line {i}")).collect();
+        let mut pager = Pager::new(&text.join("\n"));
+        pager.height = 5;
+        pager.width = 10;
+
+        pager.handle_key(key(KeyCode::Char(' ')));
+        assert_eq!(pager.top, 5);
+        pager.handle_key(key(KeyCode::Char('G')));
+        assert_eq!(pager.top, 15);
+        pager.handle_key(key(KeyCode::Down));
+        assert_eq!(pager.top, 15);
+        pager.handle_key(key(KeyCode::Char('g')));
+        assert_eq!(pager.top, 0);
+
+        for c in "/line 12".chars() {
+            pager.handle_key(key(KeyCode::Char(c)));
+        }
+        pager.handle_key(key(KeyCode::Enter));
+        assert_eq!(pager.top, 12);
+        assert_eq!(pager.search.as_deref(), Some("line 12"));
+
+        pager.search = Some("line 1".to_string());
+        pager.handle_key(key(KeyCode::Char('N')));
+        assert_eq!(pager.top, 11);
+        pager.handle_key(key(KeyCode::Char('n')));
+        assert_eq!(pager.top, 12);
+        pager.search = Some("nope".to_string());
+        pager.handle_key(key(KeyCode::Char('n')));
+        assert_eq!(pager.top, 12);
+        assert!(pager.message.is_some());
+
+        assert_eq!(pager.handle_key(key(KeyCode::Char('q'))), PagerAction::Quit);
+    }
+
+    #[test]
+    fn test_scroll_sideways() {
+        let mut pager = Pager::new("short\nthis is a much longer line of text");
+        pager.height = 5;
+        pager.width = 10;
+        pager.handle_key(key(KeyCode::Right));
+        assert_eq!(pager.left, 5);
+        for _ in 0..10 {
+            pager.handle_key(key(KeyCode::Right));
+        }
+        // stops once the end of the widest line is visible
+        assert_eq!(pager.left, 25);
+        pager.handle_key(key(KeyCode::Left));
+        assert_eq!(pager.left, 20);
+        pager.handle_key(key(KeyCode::Char('0')));
+        assert_eq!(pager.left, 0);
+    }
+}
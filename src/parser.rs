@@ -30,6 +30,25 @@ enum ParsingState {
     DoubleQuotedEscaped,
 }
 
+impl ParsingState {
+    // the state after seeing c. this is used when scanning a line without parsing it into args
+    fn next(self, c: char) -> ParsingState {
+        use self::ParsingState::*;
+
+        match (self, c) {
+            (Normal, '\\') => Escaped,
+            (Normal, '\'') => SingleQuoted,
+            (Normal, '"') => DoubleQuoted,
+            (Normal, _) | (Escaped, _) => Normal,
+            (SingleQuoted, '\'') => Normal,
+            (SingleQuoted, _) => SingleQuoted,
+            (DoubleQuoted, '"') => Normal,
+            (DoubleQuoted, '\\') => DoubleQuotedEscaped,
+            (DoubleQuoted, _) | (DoubleQuotedEscaped, _) => DoubleQuoted,
+        }
+    }
+}
+
 /// Parser for bash-like command lines.
 ///
 /// Supports parsing arguments which use escaping, single quotes and double
@@ -123,6 +142,292 @@ impl<'a> Iterator for Parser<'a> {
     }
 }
 
+/// Split a line into the separate commands it contains. Commands are separated by a `;` that is
+/// not quoted or escaped, and not inside a command substitution (`$( … )`). Empty commands (like
+/// the one after a trailing `;`) are dropped.
+pub fn split_commands(line: &str) -> Vec<&str> {
+    use self::ParsingState::*;
+
+    let mut ret = Vec::new();
+    let mut state = Normal;
+    let mut start = 0;
+    let mut skip_to = 0;
+    for (i, c) in line.char_indices() {
+        if i < skip_to {
+            continue;
+        }
+        if state == Normal && c == '$' && line[i + 1..].starts_with('(') {
+            if let Some(close) = find_close_paren(line, i + 1) {
+                skip_to = close + 1;
+                continue;
+            }
+        }
+        if state == Normal && c == ';' {
+            ret.push(&line[start..i]);
+            start = i + 1;
+        }
+        state = state.next(c);
+    }
+    ret.push(&line[start..]);
+    ret.retain(|cmd| !cmd.trim().is_empty());
+    ret
+}
+
+/// Find the command word (the first word) of each command in line, as a range of byte offsets.
+/// Commands are separated as in split_commands
+pub fn command_word_ranges(line: &str) -> Vec<Range<usize>> {
+    use self::ParsingState::*;
+
+    let mut ret = Vec::new();
+    let mut state = Normal;
+    let mut word_start = None;
+    let mut in_command_word = true;
+    for (i, c) in line.char_indices() {
+        if state == Normal {
+            if c == ';' {
+                if let Some(start) = word_start.take() {
+                    ret.push(start..i);
+                }
+                in_command_word = true;
+            } else if in_command_word {
+                if c.is_whitespace() || c == '|' || c == '>' {
+                    if let Some(start) = word_start.take() {
+                        ret.push(start..i);
+                        in_command_word = false;
+                    }
+                } else if word_start.is_none() {
+                    word_start = Some(i);
+                }
+            }
+        } else if in_command_word && word_start.is_none() {
+            word_start = Some(i);
+        }
+        state = state.next(c);
+    }
+    if let Some(start) = word_start {
+        ret.push(start..line.len());
+    }
+    ret
+}
+
+/// If line has a quote that is never closed, return it
+pub fn unclosed_quote(line: &str) -> Option<char> {
+    use self::ParsingState::*;
+
+    match line.chars().fold(Normal, |state, c| state.next(c)) {
+        SingleQuoted => Some('\''),
+        DoubleQuoted | DoubleQuotedEscaped => Some('"'),
+        Normal | Escaped => None,
+    }
+}
+
+// quote an argument so it will come back out of the Parser unchanged
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty()
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || "'\"\\|>;$".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+// value for a single parameter reference (1-9 or @), or None if it's not a valid reference
+fn alias_param(param: &str, args: &[String], quote: bool) -> Option<String> {
+    let q = |a: &String| {
+        if quote {
+            quote_arg(a)
+        } else {
+            a.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+    };
+    match param {
+        "@" => Some(args.iter().map(q).collect::<Vec<String>>().join(" ")),
+        _ => match param.parse::<usize>() {
+            Ok(n) if (1..=9).contains(&n) => Some(args.get(n - 1).map(q).unwrap_or_default()),
+            _ => None,
+        },
+    }
+}
+
+/// Substitute positional parameters in the body of an alias, like a bash function does. The
+/// following are supported:
+/// $1..$9        - replaced with the specified argument, or nothing if it wasn't passed
+/// $@            - replaced with all the arguments
+/// ${1:-default} - replaced with the argument, or `default` if it wasn't passed
+///
+/// Nothing is substituted inside single quotes, and arguments substituted outside of quotes are
+/// quoted as needed so they end up as a single argument when the line is parsed.
+///
+/// Returns None if the body doesn't reference any parameters, which means it's a plain prefix
+/// alias.
+pub fn substitute_alias_params(body: &str, args: &[String]) -> Option<String> {
+    use self::ParsingState::*;
+
+    let mut ret = String::with_capacity(body.len());
+    let mut state = Normal;
+    let mut found = false;
+    let mut chars = body.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '$' && (state == Normal || state == DoubleQuoted) {
+            let quote = state == Normal;
+            match chars.peek() {
+                Some(&(_, p)) if p == '@' || p.is_ascii_digit() => {
+                    if let Some(val) = alias_param(&p.to_string(), args, quote) {
+                        chars.next();
+                        ret.push_str(&val);
+                        found = true;
+                        continue;
+                    }
+                }
+                Some(&(_, '{')) => {
+                    if let Some(end) = body[i..].find('}') {
+                        let inner = &body[i + 2..i + end];
+                        let (param, default) = match inner.split_once(":-") {
+                            Some((param, default)) => (param, Some(default)),
+                            None => (inner, None),
+                        };
+                        if let Some(val) = alias_param(param, args, quote) {
+                            match default {
+                                Some(default) if val.is_empty() => ret.push_str(default),
+                                _ => ret.push_str(&val),
+                            }
+                            // skip past the closing }
+                            for (j, _) in chars.by_ref() {
+                                if j == i + end {
+                                    break;
+                                }
+                            }
+                            found = true;
+                            continue;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        ret.push(c);
+        state = state.next(c);
+    }
+    if found {
+        Some(ret)
+    } else {
+        None
+    }
+}
+
+/// Something in a line that needs to be expanded by `expand_line`
+#[derive(Debug, Eq, PartialEq)]
+pub enum Expansion<'a> {
+    /// $NAME or ${NAME}
+    Variable(&'a str),
+    /// $( command )
+    Command(&'a str),
+}
+
+fn is_var_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_var_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// escape a value so the Parser returns it unchanged. Outside of quotes whitespace is left alone, so
+// the value gets split into words, like bash does
+fn escape_expansion(val: &str, state: ParsingState) -> String {
+    let mut ret = String::with_capacity(val.len());
+    for c in val.chars() {
+        let special = match state {
+            ParsingState::DoubleQuoted => c == '"' || c == '\\',
+            _ => "'\"\\|>;$".contains(c),
+        };
+        if special {
+            ret.push('\\');
+        }
+        ret.push(c);
+    }
+    ret
+}
+
+// find the index of the ) that closes a $( that starts at start. start should point at the (
+fn find_close_paren(line: &str, start: usize) -> Option<usize> {
+    use self::ParsingState::*;
+
+    let mut depth = 0;
+    let mut state = Normal;
+    for (i, c) in line[start..].char_indices() {
+        if state == Normal && c == '(' {
+            depth += 1;
+        } else if state == Normal && c == ')' {
+            depth -= 1;
+            if depth == 0 {
+                return Some(start + i);
+            }
+        }
+        state = state.next(c);
+    }
+    None
+}
+
+/// Expand variables ($NAME or ${NAME}) and command substitutions ($( command )) in a line. `expand`
+/// is called to get the value for each one. As in bash, nothing is expanded inside single quotes,
+/// and a value expanded outside of quotes is split into words. Trailing newlines are removed from
+/// the value of a command substitution. Anything else that starts with a $ (like an alias parameter)
+/// is left as is.
+pub fn expand_line<F>(line: &str, mut expand: F) -> Result<String, String>
+where
+    F: FnMut(Expansion) -> String,
+{
+    use self::ParsingState::*;
+
+    let mut ret = String::with_capacity(line.len());
+    let mut state = Normal;
+    let mut skip_to = 0;
+    for (i, c) in line.char_indices() {
+        if i < skip_to {
+            continue;
+        }
+        if c == '$' && (state == Normal || state == DoubleQuoted) {
+            let rest = &line[i + 1..];
+            let expanded = if rest.starts_with('(') {
+                let close = find_close_paren(line, i + 1)
+                    .ok_or_else(|| format!("Unterminated $( at {i}"))?;
+                skip_to = close + 1;
+                let mut val = expand(Expansion::Command(line[i + 2..close].trim()));
+                val.truncate(val.trim_end_matches(['\n', '\r']).len());
+                Some(val)
+            } else if let Some(braced) = rest.strip_prefix('{') {
+                match braced.find('}') {
+                    Some(end)
+                        if braced[..end].starts_with(is_var_start)
+                            && braced[..end].chars().all(is_var_char) =>
+                    {
+                        skip_to = i + end + 3;
+                        Some(expand(Expansion::Variable(&braced[..end])))
+                    }
+                    _ => None,
+                }
+            } else if rest.starts_with(is_var_start) {
+                let end = rest.find(|c| !is_var_char(c)).unwrap_or(rest.len());
+                skip_to = i + end + 1;
+                Some(expand(Expansion::Variable(&rest[..end])))
+            } else {
+                None
+            };
+            if let Some(val) = expanded {
+                ret.push_str(&escape_expansion(&val, state));
+                continue;
+            }
+        }
+        ret.push(c);
+        state = state.next(c);
+    }
+    Ok(ret)
+}
+
 /// Try and parse a line of the form [N]..[M]. These conform to Rust's range expressions:
 /// https://doc.rust-lang.org/reference/expressions/range-expr.html
 /// If we parse this successfully, we return
@@ -184,6 +489,28 @@ pub fn try_parse_csl(line: &str) -> Option<Box<dyn Iterator<Item = usize>>> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_command_word_ranges() {
+        let line = "pods -l app=x; logs | grep 'a;b' ;  describe";
+        let words: Vec<&str> = command_word_ranges(line)
+            .into_iter()
+            .map(|r| &line[r])
+            .collect();
+        assert_eq!(words, vec!["pods", "logs", "describe"]);
+        assert_eq!(command_word_ranges("  ns"), vec![2..4]);
+        assert_eq!(command_word_ranges("pods>out"), vec![0..4]);
+        assert!(command_word_ranges(" ; ").is_empty());
+    }
+
+    #[test]
+    fn test_unclosed_quote() {
+        assert_eq!(unclosed_quote("pods 'a b'"), None);
+        assert_eq!(unclosed_quote("pods \"a \\\" b\""), None);
+        assert_eq!(unclosed_quote("pods 'a b"), Some('\''));
+        assert_eq!(unclosed_quote("pods \"a 'b"), Some('"'));
+        assert_eq!(unclosed_quote("pods \\'a"), None);
+    }
+
     #[test]
     fn try_parse_csl_test() {
         let v: Vec<usize> = try_parse_csl("1,2,3").unwrap().collect();
@@ -210,6 +537,95 @@ mod tests {
         assert!(try_parse_csl(",1,2,").is_none());
     }
 
+    #[test]
+    fn split_commands_test() {
+        assert_eq!(split_commands("pods"), vec!["pods"]);
+        assert_eq!(split_commands("pods; nodes"), vec!["pods", " nodes"]);
+        assert_eq!(split_commands("pods;nodes;"), vec!["pods", "nodes"]);
+        assert_eq!(
+            split_commands("set x \"a;b\"; pods"),
+            vec!["set x \"a;b\"", " pods"]
+        );
+        assert_eq!(split_commands("set x 'a;b'"), vec!["set x 'a;b'"]);
+        assert_eq!(split_commands("set x a\\;b"), vec!["set x a\\;b"]);
+        assert_eq!(
+            split_commands("let X = $(pods; nodes); ns"),
+            vec!["let X = $(pods; nodes)", " ns"]
+        );
+        assert!(split_commands(" ; ").is_empty());
+    }
+
+    #[test]
+    fn substitute_alias_params_test() {
+        let args = vec!["one".to_string(), "two words".to_string()];
+        assert_eq!(substitute_alias_params("pods --sort node", &args), None);
+        assert_eq!(
+            substitute_alias_params("logs -c $1 -t 100", &args),
+            Some("logs -c one -t 100".to_string())
+        );
+        assert_eq!(
+            substitute_alias_params("logs $2", &args),
+            Some("logs 'two words'".to_string())
+        );
+        assert_eq!(
+            substitute_alias_params("logs \"$2\"", &args),
+            Some("logs \"two words\"".to_string())
+        );
+        assert_eq!(substitute_alias_params("logs '$2'", &args), None);
+        assert_eq!(
+            substitute_alias_params("exec $@", &args),
+            Some("exec one 'two words'".to_string())
+        );
+        assert_eq!(
+            substitute_alias_params("logs -c ${1:-app} ${3:-a b}", &args),
+            Some("logs -c one a b".to_string())
+        );
+        assert_eq!(
+            substitute_alias_params("logs -c ${1:-app}", &[]),
+            Some("logs -c app".to_string())
+        );
+        assert_eq!(
+            substitute_alias_params("logs $1 $0 ${x}", &[]),
+            Some("logs  $0 ${x}".to_string())
+        );
+    }
+
+    #[test]
+    fn expand_line_test() {
+        let expand = |exp: Expansion| match exp {
+            Expansion::Variable("A") => "a".to_string(),
+            Expansion::Variable("SP") => "x y".to_string(),
+            Expansion::Variable("Q") => "it's \"here\"".to_string(),
+            Expansion::Variable(_) => String::new(),
+            Expansion::Command(cmd) => format!("[{cmd}]\n\n"),
+        };
+        assert_eq!(expand_line("pods", expand).unwrap(), "pods");
+        assert_eq!(expand_line("logs $A", expand).unwrap(), "logs a");
+        assert_eq!(expand_line("logs ${A}b", expand).unwrap(), "logs ab");
+        assert_eq!(expand_line("logs $Ab", expand).unwrap(), "logs ");
+        assert_eq!(expand_line("logs '$A'", expand).unwrap(), "logs '$A'");
+        assert_eq!(expand_line("logs \\$A", expand).unwrap(), "logs \\$A");
+        assert_eq!(expand_line("logs \"$SP\"", expand).unwrap(), "logs \"x y\"");
+        assert_eq!(expand_line("logs $SP", expand).unwrap(), "logs x y");
+        assert_eq!(
+            expand_line("logs $Q", expand).unwrap(),
+            "logs it\\'s \\\"here\\\""
+        );
+        assert_eq!(
+            expand_line("logs \"$Q\"", expand).unwrap(),
+            "logs \"it's \\\"here\\\"\""
+        );
+        assert_eq!(
+            expand_line("logs $( pods (x) ')' ) y", expand).unwrap(),
+            "logs [pods (x) \\')\\'] y"
+        );
+        assert_eq!(
+            expand_line("lg $1 ${1:-app} $@ $", expand).unwrap(),
+            "lg $1 ${1:-app} $@ $"
+        );
+        assert!(expand_line("logs $(pods", expand).is_err());
+    }
+
     #[test]
     fn try_parse_range_test() {
         let v: Vec<usize> = try_parse_range("1..3").unwrap().collect();
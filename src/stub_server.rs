@@ -0,0 +1,181 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test only code to stand in for an api server (or any other http server) on localhost. It
+//! answers each connection with the next of a list of canned responses, and hands back the
+//! requests it got so tests can check them.
+
+use tempdir::TempDir;
+
+use crate::config::{ClickConfig, Config};
+use crate::env::Env;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+/// A request the stub server received
+#[derive(Debug)]
+pub struct StubRequest {
+    /// i.e. "GET /api/v1/pods HTTP/1.1"
+    pub line: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl StubRequest {
+    /// The value of the first header called name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The token from the authorization header, or "" if there isn't one
+    pub fn bearer_token(&self) -> &str {
+        self.header("authorization")
+            .map(|value| value.trim_start_matches("Bearer "))
+            .unwrap_or("")
+    }
+}
+
+/// A canned response, sent as json
+pub struct StubResponse {
+    status: String,
+    body: String,
+}
+
+impl StubResponse {
+    /// status is the code and reason, i.e. "404 Not Found"
+    pub fn new<S: Into<String>>(status: &str, body: S) -> StubResponse {
+        StubResponse {
+            status: status.to_string(),
+            body: body.into(),
+        }
+    }
+}
+
+pub struct StubServer {
+    listener: TcpListener,
+}
+
+impl StubServer {
+    pub fn new() -> StubServer {
+        StubServer {
+            listener: TcpListener::bind("127.0.0.1:0").unwrap(),
+        }
+    }
+
+    /// Where the server listens, i.e. http://127.0.0.1:1234
+    pub fn url(&self) -> String {
+        format!("http://{}", self.listener.local_addr().unwrap())
+    }
+
+    /// Answer one connection with each of responses, in order. The handle returns the requests
+    /// once they've all been answered.
+    pub fn serve(self, responses: Vec<StubResponse>) -> JoinHandle<Vec<StubRequest>> {
+        thread::spawn(move || {
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = self.listener.accept().unwrap();
+                requests.push(read_request(&mut BufReader::new(
+                    stream.try_clone().unwrap(),
+                )));
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{}",
+                    response.status,
+                    response.body.len(),
+                    response.body
+                )
+                .unwrap();
+            }
+            requests
+        })
+    }
+}
+
+/// Start a server that answers with responses, returning its url and a handle that returns the
+/// requests it got
+pub fn serve(responses: Vec<StubResponse>) -> (String, JoinHandle<Vec<StubRequest>>) {
+    let server = StubServer::new();
+    (server.url(), server.serve(responses))
+}
+
+/// An Env whose current context, "test", talks to the server at url with a token. The kubeconfig
+/// is written to dir.
+pub fn env_for(url: &str, dir: &TempDir) -> Env {
+    let path = dir.path().join("config").to_str().unwrap().to_string();
+    std::fs::write(
+        &path,
+        format!(
+            "apiVersion: v1
+kind: Config
+clusters:
+- name: test
+  cluster:
+    server: {url}
+contexts:
+- name: test
+  context:
+    cluster: test
+    user: test
+users:
+- name: test
+  user:
+    token: abc
+"
+        ),
+    )
+    .unwrap();
+    let config = Config::from_files(&[path]).unwrap();
+    let mut env = Env::new(
+        config,
+        ClickConfig::default(),
+        dir.path().join("click.conf"),
+    );
+    env.set_context(Some("test"));
+    env
+}
+
+fn read_request<R: BufRead>(reader: &mut R) -> StubRequest {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let mut headers = vec![];
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let mut request = StubRequest {
+        line: line.trim().to_string(),
+        headers,
+        body: String::new(),
+    };
+    let length = request
+        .header("content-length")
+        .map(|length| length.parse().unwrap())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    request.body = String::from_utf8(body).unwrap();
+    request
+}
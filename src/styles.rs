@@ -12,118 +12,434 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-/// Module that defines methods to color click output appropriately
-use crossterm::style::{Attribute, Attributes, Color, ContentStyle, StyledContent, Stylize};
+/// Module that defines methods to color click output appropriately. Styles come from a theme
+/// preset (dark, light or monochrome), with individual styles overridden by the theme section of
+/// the click config. Each override is a space separated list of words: a color, `on_<color>` for
+/// the background, and attributes (bold, dim, italic, underlined, reverse). Colors can be
+/// crossterm color names (red, dark_red, grey, ...), `#rrggbb`, or an ansi color number.
+///
+/// If NO_COLOR is set the monochrome preset is used, and if CLICK_COLOR=never no styling is done
+/// at all. CLICK_COLOR=always uses the configured theme even if NO_COLOR is set.
+use crossterm::style::{Attribute, Attributes, Color, ContentStyle, StyledContent};
+
+use crate::config::{ThemeConfig, ThemePreset};
+use crate::table::ColorType;
+
 use std::collections::HashMap;
 
+/// Names of all the styles a theme can override, other than object types
+const STYLE_NAMES: &[&str] = &[
+    "success",
+    "warning",
+    "danger",
+    "info",
+    "prompt_context",
+    "prompt_production_context",
+    "prompt_namespace",
+    "prompt_range",
+    "prompt_select_none",
+    "prompt_user",
+    "prompt_server",
+    "prompt_time",
+    "prompt_forwards",
+    "prompt_status_ok",
+    "prompt_status_err",
+    "config_val",
+    "context_table",
+    "json_key",
+    "json_string",
+    "input_command",
+    "input_alias",
+    "input_unknown",
+    "input_hint",
+    "bold",
+];
+
+/// Object types that get their own style in the prompt
+const OBJECT_TYPES: &[&str] = &[
+    "Pod",
+    "Crd",
+    "Node",
+    "DaemonSet",
+    "Deployment",
+    "Service",
+    "ReplicaSet",
+    "StatefulSet",
+    "ConfigMap",
+    "Secret",
+    "CronJob",
+    "Job",
+    "PersistentVolume",
+    "StorageClass",
+    #[cfg(feature = "argorollouts")]
+    "Rollout",
+];
+
+/// How to color output, based on the NO_COLOR and CLICK_COLOR environment variables
+#[derive(Debug, PartialEq, Eq)]
+enum ColorChoice {
+    Theme,
+    Monochrome,
+    Never,
+}
+
+impl ColorChoice {
+    fn from_vars(no_color: Option<String>, click_color: Option<String>) -> ColorChoice {
+        match click_color.as_deref() {
+            Some("never") => ColorChoice::Never,
+            Some("always") => ColorChoice::Theme,
+            // per no-color.org, NO_COLOR only counts if it's not empty
+            _ if no_color.map(|v| !v.is_empty()).unwrap_or(false) => ColorChoice::Monochrome,
+            _ => ColorChoice::Theme,
+        }
+    }
+
+    fn from_env() -> ColorChoice {
+        ColorChoice::from_vars(
+            std::env::var("NO_COLOR").ok(),
+            std::env::var("CLICK_COLOR").ok(),
+        )
+    }
+}
+
+/// The environment variable that's overriding the theme from the config, if any
+pub fn color_env_override() -> Option<&'static str> {
+    match ColorChoice::from_env() {
+        ColorChoice::Theme => None,
+        ColorChoice::Monochrome => Some("NO_COLOR"),
+        ColorChoice::Never => Some("CLICK_COLOR=never"),
+    }
+}
+
+#[derive(Clone)]
 pub struct Styles {
+    styles: HashMap<&'static str, ContentStyle>,
     prompt_object_map: HashMap<&'static str, ContentStyle>,
 }
 
 macro_rules! style {
-    ($style_name:ident, $arg:ident $style_apply:block) => {
-        pub fn $style_name<'a>(&self, $arg: &'a str) -> StyledContent<&'a str> $style_apply
+    ($style_name:ident) => {
+        pub fn $style_name<'a>(&self, s: &'a str) -> StyledContent<&'a str> {
+            self.get(stringify!($style_name)).apply(s)
+        }
     };
 }
 
-macro_rules! obj_style {
-    ($fg: expr) => {
-        ContentStyle {
-            foreground_color: Some($fg),
-            background_color: None,
-            underline_color: None,
-            attributes: Attributes::default(),
-        }
+fn sty(fg: Option<Color>, bg: Option<Color>, attrs: &[Attribute]) -> ContentStyle {
+    ContentStyle {
+        foreground_color: fg,
+        background_color: bg,
+        underline_color: None,
+        attributes: attrs
+            .iter()
+            .fold(Attributes::default(), |attrs, attr| attrs | *attr),
+    }
+}
+
+fn fg(color: Color) -> ContentStyle {
+    sty(Some(color), None, &[])
+}
+
+fn fg_bold(color: Color) -> ContentStyle {
+    sty(Some(color), None, &[Attribute::Bold])
+}
+
+fn attr(attrs: &[Attribute]) -> ContentStyle {
+    sty(None, None, attrs)
+}
+
+fn preset_styles(preset: &ThemePreset) -> Vec<(&'static str, ContentStyle)> {
+    match preset {
+        ThemePreset::Dark => vec![
+            ("success", fg(Color::DarkGreen)),
+            ("warning", fg(Color::DarkYellow)),
+            ("danger", fg(Color::DarkRed)),
+            ("info", fg(Color::DarkBlue)),
+            ("prompt_context", fg_bold(Color::Red)),
+            (
+                "prompt_production_context",
+                sty(
+                    Some(Color::Black),
+                    Some(Color::DarkYellow),
+                    &[Attribute::Bold],
+                ),
+            ),
+            ("prompt_namespace", fg_bold(Color::Green)),
+            ("prompt_range", fg(Color::Blue)),
+            ("prompt_select_none", fg(Color::DarkYellow)),
+            ("prompt_user", fg_bold(Color::Magenta)),
+            ("prompt_server", fg(Color::Cyan)),
+            ("prompt_time", fg(Color::DarkGrey)),
+            ("prompt_forwards", fg(Color::Blue)),
+            ("prompt_status_ok", fg(Color::DarkGreen)),
+            ("prompt_status_err", fg_bold(Color::Red)),
+            ("config_val", fg(Color::Yellow)),
+            ("context_table", fg(Color::Red)),
+            ("json_key", fg_bold(Color::Blue)),
+            ("json_string", fg(Color::Green)),
+            ("input_command", fg_bold(Color::Green)),
+            ("input_alias", fg_bold(Color::Cyan)),
+            ("input_unknown", fg_bold(Color::Red)),
+            ("input_hint", fg(Color::DarkGrey)),
+            ("bold", attr(&[Attribute::Bold])),
+        ],
+        // the bright colors are hard to read on a light background, so use the dark versions
+        ThemePreset::Light => vec![
+            ("success", fg(Color::DarkGreen)),
+            ("warning", fg(Color::DarkYellow)),
+            ("danger", fg(Color::DarkRed)),
+            ("info", fg(Color::DarkBlue)),
+            ("prompt_context", fg_bold(Color::DarkRed)),
+            (
+                "prompt_production_context",
+                sty(Some(Color::White), Some(Color::DarkRed), &[Attribute::Bold]),
+            ),
+            ("prompt_namespace", fg_bold(Color::DarkGreen)),
+            ("prompt_range", fg(Color::DarkBlue)),
+            ("prompt_select_none", fg(Color::DarkYellow)),
+            ("prompt_user", fg_bold(Color::DarkMagenta)),
+            ("prompt_server", fg(Color::DarkCyan)),
+            ("prompt_time", fg(Color::DarkGrey)),
+            ("prompt_forwards", fg(Color::DarkBlue)),
+            ("prompt_status_ok", fg(Color::DarkGreen)),
+            ("prompt_status_err", fg_bold(Color::DarkRed)),
+            ("config_val", fg(Color::DarkMagenta)),
+            ("context_table", fg(Color::DarkRed)),
+            ("json_key", fg_bold(Color::DarkBlue)),
+            ("json_string", fg(Color::DarkGreen)),
+            ("input_command", fg_bold(Color::DarkGreen)),
+            ("input_alias", fg_bold(Color::DarkCyan)),
+            ("input_unknown", fg_bold(Color::DarkRed)),
+            ("input_hint", fg(Color::DarkGrey)),
+            ("bold", attr(&[Attribute::Bold])),
+        ],
+        // no colors at all, things that need to stand out use attributes instead
+        ThemePreset::Monochrome => vec![
+            ("success", attr(&[])),
+            ("warning", attr(&[Attribute::Bold])),
+            ("danger", attr(&[Attribute::Bold, Attribute::Underlined])),
+            ("info", attr(&[])),
+            ("prompt_context", attr(&[Attribute::Bold])),
+            (
+                "prompt_production_context",
+                attr(&[Attribute::Bold, Attribute::Reverse]),
+            ),
+            ("prompt_namespace", attr(&[Attribute::Bold])),
+            ("prompt_range", attr(&[Attribute::Underlined])),
+            ("prompt_select_none", attr(&[Attribute::Dim])),
+            ("prompt_user", attr(&[Attribute::Bold])),
+            ("prompt_server", attr(&[])),
+            ("prompt_time", attr(&[Attribute::Dim])),
+            ("prompt_forwards", attr(&[Attribute::Underlined])),
+            ("prompt_status_ok", attr(&[])),
+            ("prompt_status_err", attr(&[Attribute::Reverse])),
+            ("config_val", attr(&[])),
+            ("context_table", attr(&[])),
+            ("json_key", attr(&[Attribute::Bold])),
+            ("json_string", attr(&[])),
+            ("input_command", attr(&[Attribute::Bold])),
+            ("input_alias", attr(&[Attribute::Underlined])),
+            ("input_unknown", attr(&[Attribute::Reverse])),
+            ("input_hint", attr(&[Attribute::Dim])),
+            ("bold", attr(&[Attribute::Bold])),
+        ],
+    }
+}
+
+fn preset_object_color(preset: &ThemePreset, type_str: &str) -> Option<Color> {
+    let dark = match type_str {
+        "Pod" | "DaemonSet" => Color::Yellow,
+        "Crd" | "Node" | "PersistentVolume" => Color::Blue,
+        "Deployment" | "ConfigMap" | "Job" | "Rollout" => Color::Magenta,
+        "Service" => Color::Cyan,
+        "ReplicaSet" | "StatefulSet" | "CronJob" => Color::Green,
+        "Secret" | "StorageClass" => Color::Red,
+        _ => return None,
     };
-    ($fg: expr, $attrs: expr) => {
-        ContentStyle {
-            foreground_color: Some($fg),
-            background_color: None,
-            underline_color: None,
-            attributes: $attrs,
+    match preset {
+        ThemePreset::Dark => Some(dark),
+        ThemePreset::Light => Some(match dark {
+            Color::Yellow => Color::DarkYellow,
+            Color::Blue => Color::DarkBlue,
+            Color::Magenta => Color::DarkMagenta,
+            Color::Cyan => Color::DarkCyan,
+            Color::Green => Color::DarkGreen,
+            _ => Color::DarkRed,
+        }),
+        ThemePreset::Monochrome => None,
+    }
+}
+
+fn parse_color(s: &str) -> Option<Color> {
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
         }
-    };
+        let component = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(Color::Rgb {
+            r: component(0)?,
+            g: component(2)?,
+            b: component(4)?,
+        });
+    }
+    if let Ok(ansi) = s.parse::<u8>() {
+        return Some(Color::AnsiValue(ansi));
+    }
+    Color::try_from(s).ok()
 }
 
-lazy_static! {
-    static ref BOLD: Attributes = {
-        let mut attrs = Attributes::default();
-        attrs.set(Attribute::Bold);
-        attrs
-    };
-    static ref NOSTYLE: ContentStyle = ContentStyle::new();
+/// Parse a style from the theme section of the config, like "red bold" or "black on_yellow"
+pub fn parse_style(spec: &str) -> Result<ContentStyle, String> {
+    let mut style = ContentStyle::new();
+    for word in spec.split_whitespace() {
+        match word.to_lowercase().as_str() {
+            "none" => {}
+            "bold" => style.attributes.set(Attribute::Bold),
+            "dim" => style.attributes.set(Attribute::Dim),
+            "italic" => style.attributes.set(Attribute::Italic),
+            "underline" | "underlined" => style.attributes.set(Attribute::Underlined),
+            "reverse" => style.attributes.set(Attribute::Reverse),
+            word => match word.strip_prefix("on_") {
+                Some(bg) => {
+                    style.background_color =
+                        Some(parse_color(bg).ok_or(format!("Invalid color: {bg}"))?)
+                }
+                None => {
+                    style.foreground_color =
+                        Some(parse_color(word).ok_or(format!("Invalid color: {word}"))?)
+                }
+            },
+        }
+    }
+    Ok(style)
 }
 
 impl Styles {
+    /// The default (dark) theme, ignoring the environment
     pub fn new() -> Styles {
-        let prompt_object_map = HashMap::from([
-            ("Pod", obj_style!(Color::Yellow, *BOLD)),
-            ("Crd", obj_style!(Color::Blue, *BOLD)),
-            ("Node", obj_style!(Color::Blue, *BOLD)),
-            ("DaemonSet", obj_style!(Color::Yellow, *BOLD)),
-            ("Deployment", obj_style!(Color::Magenta, *BOLD)),
-            ("Service", obj_style!(Color::Cyan, *BOLD)),
-            ("ReplicaSet", obj_style!(Color::Green, *BOLD)),
-            ("StatefulSet", obj_style!(Color::Green, *BOLD)),
-            ("ConfigMap", obj_style!(Color::Magenta, *BOLD)),
-            ("Secret", obj_style!(Color::Red, *BOLD)),
-            ("CronJob", obj_style!(Color::Green, *BOLD)),
-            ("Job", obj_style!(Color::Magenta, *BOLD)),
-            ("PersistentVolume", obj_style!(Color::Blue, *BOLD)),
-            ("StorageClass", obj_style!(Color::Red, *BOLD)),
-            #[cfg(feature = "argorollouts")]
-            ("Rollout", obj_style!(Color::Magenta, *BOLD)),
-        ]);
-
-        Styles { prompt_object_map }
+        Styles::build(&ThemeConfig::default(), ColorChoice::Theme).unwrap() // safe, no overrides
+    }
+
+    /// Build styles from the theme section of the config, taking NO_COLOR and CLICK_COLOR into
+    /// account. Returns an error if any of the overrides is invalid.
+    pub fn from_theme(theme: &ThemeConfig) -> Result<Styles, String> {
+        Styles::build(theme, ColorChoice::from_env())
+    }
+
+    fn build(theme: &ThemeConfig, choice: ColorChoice) -> Result<Styles, String> {
+        let preset = match choice {
+            ColorChoice::Theme => &theme.preset,
+            ColorChoice::Monochrome => &ThemePreset::Monochrome,
+            ColorChoice::Never => {
+                return Ok(Styles {
+                    styles: HashMap::new(),
+                    prompt_object_map: HashMap::new(),
+                });
+            }
+        };
+        let mut styles: HashMap<&'static str, ContentStyle> =
+            preset_styles(preset).into_iter().collect();
+        let mut prompt_object_map: HashMap<&'static str, ContentStyle> = OBJECT_TYPES
+            .iter()
+            .map(|t| {
+                let color = preset_object_color(preset, t);
+                (*t, sty(color, None, &[Attribute::Bold]))
+            })
+            .collect();
+
+        for (key, spec) in theme.colors.iter() {
+            let style = parse_style(spec).map_err(|e| format!("theme key {key}: {e}"))?;
+            if let Some(name) = STYLE_NAMES.iter().find(|n| **n == key) {
+                styles.insert(name, style);
+            } else if let Some(name) = OBJECT_TYPES.iter().find(|n| **n == key) {
+                prompt_object_map.insert(name, style);
+            } else {
+                return Err(format!("Unknown theme key: {key}"));
+            }
+        }
+        Ok(Styles {
+            styles,
+            prompt_object_map,
+        })
+    }
+
+    fn get(&self, name: &str) -> ContentStyle {
+        self.styles.get(name).copied().unwrap_or_default()
     }
 
     pub fn prompt_object<'a>(&self, name: &'a str, type_str: &str) -> StyledContent<&'a str> {
         match self.prompt_object_map.get(type_str) {
             Some(style) => style.apply(name),
-            None => NOSTYLE.apply(name),
+            None => ContentStyle::new().apply(name),
         }
     }
 
     // general colors
-    style!(success, s {s.dark_green()});
-    style!(warning, s {s.dark_yellow()});
-    style!(danger,  s {s.dark_red()});
+    style!(success);
+    style!(warning);
+    style!(danger);
+
+    /// Style s with the style for the specified color type
+    pub fn status<'a>(&self, s: &'a str, color_type: &ColorType) -> StyledContent<&'a str> {
+        self.color_type_style(color_type).apply(s)
+    }
+
+    fn color_type_style(&self, color_type: &ColorType) -> ContentStyle {
+        match color_type {
+            ColorType::Info => self.get("info"),
+            ColorType::Success => self.get("success"),
+            ColorType::Warn => self.get("warning"),
+            ColorType::Danger => self.get("danger"),
+        }
+    }
 
     // prompt colors
-    style!(prompt_context,     s {s.red().bold()});
-    style!(prompt_namespace,   s {s.green().bold()});
-    style!(prompt_range,       s {s.blue()});
-    style!(prompt_select_none, s {s.dark_yellow()});
+    style!(prompt_context);
+    style!(prompt_production_context);
+    style!(prompt_namespace);
+    style!(prompt_range);
+    style!(prompt_select_none);
+    style!(prompt_user);
+    style!(prompt_server);
+    style!(prompt_time);
+    style!(prompt_forwards);
+    style!(prompt_status_ok);
+    style!(prompt_status_err);
 
     // config printing colors
     // TODO: Maybe add this
     // style!(config_key, s {s.red()});
     pub fn config_val_string(&self, s: String) -> StyledContent<String> {
-        s.yellow()
+        self.get("config_val").apply(s)
     }
-    style!(config_val, s {s.yellow()});
+    style!(config_val);
 
-    // other colors
-    pub fn success_color(&self) -> Color {
-        Color::DarkGreen
-    }
-    pub fn warning_color(&self) -> Color {
-        Color::DarkYellow
+    // table colors, None means don't color the cell
+    pub fn table_color(&self, color_type: &ColorType) -> Option<Color> {
+        self.color_type_style(color_type).foreground_color
     }
-    pub fn danger_color(&self) -> Color {
-        Color::DarkRed
+
+    pub fn context_table_color(&self) -> Option<Color> {
+        self.get("context_table").foreground_color
     }
-    pub fn info_color(&self) -> Color {
-        Color::DarkBlue
+
+    // json colors
+    pub fn json_key_style(&self) -> ContentStyle {
+        self.get("json_key")
     }
 
-    pub fn context_table_color(&self) -> Color {
-        Color::Red
+    pub fn json_string_style(&self) -> ContentStyle {
+        self.get("json_string")
     }
 
+    // input line colors
+    style!(input_command);
+    style!(input_alias);
+    style!(input_unknown);
+    style!(input_hint);
+
     // attributes
-    style!(bold, s {s.bold()});
+    style!(bold);
 }
 
 impl Default for Styles {
@@ -131,3 +447,85 @@ impl Default for Styles {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_choice() {
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(ColorChoice::from_vars(None, None), ColorChoice::Theme);
+        assert_eq!(
+            ColorChoice::from_vars(some("1"), None),
+            ColorChoice::Monochrome
+        );
+        assert_eq!(ColorChoice::from_vars(some(""), None), ColorChoice::Theme);
+        assert_eq!(
+            ColorChoice::from_vars(None, some("never")),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            ColorChoice::from_vars(some("1"), some("always")),
+            ColorChoice::Theme
+        );
+    }
+
+    #[test]
+    fn test_parse_style() {
+        let style = parse_style("red bold").unwrap();
+        assert_eq!(style.foreground_color, Some(Color::Red));
+        assert!(style.attributes.has(Attribute::Bold));
+        let style = parse_style("#ff8000 on_dark_grey").unwrap();
+        assert_eq!(
+            style.foreground_color,
+            Some(Color::Rgb {
+                r: 255,
+                g: 128,
+                b: 0
+            })
+        );
+        assert_eq!(style.background_color, Some(Color::DarkGrey));
+        assert_eq!(
+            parse_style("208").unwrap().foreground_color,
+            Some(Color::AnsiValue(208))
+        );
+        assert_eq!(parse_style("none").unwrap(), ContentStyle::new());
+        assert!(parse_style("reddish").is_err());
+        assert!(parse_style("#ff80").is_err());
+    }
+
+    #[test]
+    fn test_theme_overrides() {
+        let mut theme = ThemeConfig::default();
+        theme
+            .colors
+            .insert("success".to_string(), "dark_cyan".to_string());
+        theme.colors.insert("Pod".to_string(), "white".to_string());
+        let styles = Styles::build(&theme, ColorChoice::Theme).unwrap();
+        assert_eq!(
+            styles.table_color(&ColorType::Success),
+            Some(Color::DarkCyan)
+        );
+        assert_eq!(styles.table_color(&ColorType::Danger), Some(Color::DarkRed));
+        assert_eq!(
+            styles.prompt_object("p", "Pod").style().foreground_color,
+            Some(Color::White)
+        );
+
+        // overrides still apply on top of monochrome
+        let styles = Styles::build(&theme, ColorChoice::Monochrome).unwrap();
+        assert_eq!(
+            styles.table_color(&ColorType::Success),
+            Some(Color::DarkCyan)
+        );
+        assert_eq!(styles.table_color(&ColorType::Danger), None);
+
+        let styles = Styles::build(&theme, ColorChoice::Never).unwrap();
+        assert_eq!(styles.table_color(&ColorType::Success), None);
+        assert_eq!(styles.prompt_context("ctx").to_string(), "ctx");
+
+        theme.colors.insert("nope".to_string(), "red".to_string());
+        assert!(Styles::build(&theme, ColorChoice::Theme).is_err());
+    }
+}
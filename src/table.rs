@@ -21,7 +21,7 @@ use crate::output::ClickWriter;
 
 use chrono::{DateTime, Duration, Utc};
 use clap::ArgMatches;
-use comfy_table::{Cell, CellAlignment, Color};
+use comfy_table::{Cell, CellAlignment, Color, ContentArrangement};
 use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 use regex::Regex;
 
@@ -33,6 +33,193 @@ use std::io::Write;
 //pub const ASCII_TABLE_STYLE: &str = "   - --       -    ";
 pub const UTF8_TABLE_STYLE: &str = "   ─ ══       ─    ";
 
+/// How a column behaves when a table is too wide for the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColumnFit {
+    /// Columns with a lower priority are dropped first. Columns with priority u8::MAX are never
+    /// dropped
+    pub priority: u8,
+    /// If set, longer cells are ellipsized to this width
+    pub max_width: Option<usize>,
+}
+
+impl ColumnFit {
+    pub const KEEP: ColumnFit = ColumnFit {
+        priority: u8::MAX,
+        max_width: None,
+    };
+}
+
+/// Options controlling how a list table is printed
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TableOptions {
+    /// print all columns at full width, even if the table doesn't fit the terminal
+    pub wide: bool,
+    pub no_headers: bool,
+    pub no_index: bool,
+    /// export the table in this format instead of printing it
+    pub output: Option<ExportFormat>,
+}
+
+impl TableOptions {
+    pub fn from_matches(matches: &ArgMatches) -> TableOptions {
+        let flag = |id: &str| matches.try_contains_id(id).unwrap_or(false);
+        TableOptions {
+            wide: flag("wide"),
+            no_headers: flag("no-headers"),
+            no_index: flag("no-index"),
+            output: ExportFormat::from_matches(matches),
+        }
+    }
+}
+
+/// Formats a table can be exported in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Tsv,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub const NAMES: [&'static str; 3] = ["csv", "tsv", "markdown"];
+
+    /// The format specified by the "output" arg, if any
+    pub fn from_matches(matches: &ArgMatches) -> Option<ExportFormat> {
+        match matches.try_get_one::<String>("output") {
+            Ok(Some(format)) => match format.to_lowercase().as_str() {
+                "csv" => Some(ExportFormat::Csv),
+                "tsv" => Some(ExportFormat::Tsv),
+                "markdown" => Some(ExportFormat::Markdown),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A table of raw values (full timestamps, quantities in base units, etc) for exporting. The index
+/// column is never included, since it only means something inside click.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportTable {
+    pub titles: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ExportTable {
+    pub fn from_specs(titles: &[&str], specs: &[Vec<CellSpec<'_>>]) -> ExportTable {
+        let shown: Vec<usize> = (0..titles.len()).filter(|i| titles[*i] != "####").collect();
+        ExportTable {
+            titles: shown.iter().map(|i| titles[*i].to_string()).collect(),
+            rows: specs
+                .iter()
+                .enumerate()
+                .map(|(index, row)| {
+                    shown
+                        .iter()
+                        .map(|i| row.get(*i).map(|spec| spec.raw_value(index)))
+                        .map(Option::unwrap_or_default)
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Build an export from a printed table. This is used when the raw values aren't available,
+    /// so the values are as they were displayed
+    pub fn from_table(table: &comfy_table::Table) -> ExportTable {
+        let titles: Vec<String> = table
+            .header()
+            .map(|header| header.cell_iter().map(|cell| cell.content()).collect())
+            .unwrap_or_default();
+        let shown: Vec<usize> = (0..titles.len()).filter(|i| titles[*i] != "####").collect();
+        ExportTable {
+            titles: shown.iter().map(|i| titles[*i].clone()).collect(),
+            rows: table
+                .row_iter()
+                .map(|row| {
+                    let cells: Vec<String> = row.cell_iter().map(|cell| cell.content()).collect();
+                    shown
+                        .iter()
+                        .map(|i| cells.get(*i).cloned().unwrap_or_default())
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    /// Render the table in the given format. Markdown tables always have a header row
+    pub fn render(&self, format: ExportFormat, no_headers: bool) -> String {
+        let mut lines = vec![];
+        match format {
+            ExportFormat::Csv | ExportFormat::Tsv => {
+                let (sep, escape): (&str, fn(&str) -> Cow<'_, str>) = match format {
+                    ExportFormat::Csv => (",", csv_escape),
+                    _ => ("\t", tsv_escape),
+                };
+                if !no_headers {
+                    lines.push(
+                        self.titles
+                            .iter()
+                            .map(|t| escape(t))
+                            .collect::<Vec<_>>()
+                            .join(sep),
+                    );
+                }
+                for row in self.rows.iter() {
+                    lines.push(row.iter().map(|c| escape(c)).collect::<Vec<_>>().join(sep));
+                }
+            }
+            ExportFormat::Markdown => {
+                let md_row = |cells: Vec<Cow<'_, str>>| format!("| {} |", cells.join(" | "));
+                lines.push(md_row(
+                    self.titles.iter().map(|t| markdown_escape(t)).collect(),
+                ));
+                lines.push(md_row(self.titles.iter().map(|_| "---".into()).collect()));
+                for row in self.rows.iter() {
+                    lines.push(md_row(row.iter().map(|c| markdown_escape(c)).collect()));
+                }
+            }
+        }
+        let mut out = lines.join("\n");
+        out.push('\n');
+        out
+    }
+}
+
+// quote fields that have a separator, quote, or newline in them, as per rfc4180
+fn csv_escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+// tsv fields can't contain tabs or newlines, so escape them (and backslash) with backslashes
+fn tsv_escape(field: &str) -> Cow<'_, str> {
+    if field.contains(['\t', '\n', '\r', '\\']) {
+        Cow::Owned(
+            field
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r"),
+        )
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+// pipes would end the cell, and newlines the row
+fn markdown_escape(field: &str) -> Cow<'_, str> {
+    if field.contains(['|', '\n']) {
+        Cow::Owned(field.replace('|', "\\|").replace('\n', "<br>"))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
 #[derive(Debug)]
 enum CellSpecTxt<'a> {
     DateTime(DateTime<Utc>),
@@ -58,15 +245,11 @@ pub enum TableColor {
 }
 
 impl TableColor {
-    fn to_color(&self, env: &Env) -> Color {
+    // None if the theme doesn't color this type
+    fn to_color(&self, env: &Env) -> Option<Color> {
         match self {
-            TableColor::Color(color) => *color,
-            TableColor::ColorType(color_type) => match color_type {
-                ColorType::Info => env.styles.info_color(),
-                ColorType::Success => env.styles.success_color(),
-                ColorType::Warn => env.styles.warning_color(),
-                ColorType::Danger => env.styles.danger_color(),
-            },
+            TableColor::Color(color) => Some(*color),
+            TableColor::ColorType(color_type) => env.styles.table_color(color_type),
         }
     }
 }
@@ -133,6 +316,11 @@ impl<'a> CellSpec<'a> {
     }
 
     pub fn to_cell(&self, index: usize, env: &Env) -> Cell {
+        self.to_fitted_cell(index, env, None)
+    }
+
+    /// Like to_cell, but ellipsize text longer than max_width (if specified)
+    pub fn to_fitted_cell(&self, index: usize, env: &Env, max_width: Option<usize>) -> Cell {
         let cell = match &self.txt {
             CellSpecTxt::DateTime(datetime) => Cell::new(format_duration(time_since(*datetime))),
             CellSpecTxt::Duration(duration) => Cell::new(format_duration(*duration)),
@@ -144,7 +332,10 @@ impl<'a> CellSpec<'a> {
             }
             CellSpecTxt::None => Cell::new("Unknown/None"),
             CellSpecTxt::Quantity(quant) => Cell::new(&quant.0),
-            CellSpecTxt::Str(s) => Cell::new(s),
+            CellSpecTxt::Str(s) => match max_width {
+                Some(max_width) => Cell::new(ellipsize(s, max_width)),
+                None => Cell::new(s),
+            },
         };
 
         let cell = if let Some(a) = self.align {
@@ -153,16 +344,28 @@ impl<'a> CellSpec<'a> {
             cell
         };
 
-        let cell = if let Some(fg) = &self.fg {
-            cell.fg(fg.to_color(env))
-        } else {
-            cell
+        let cell = match self.fg.as_ref().and_then(|fg| fg.to_color(env)) {
+            Some(fg) => cell.fg(fg),
+            None => cell,
         };
 
-        if let Some(bg) = &self.bg {
-            cell.bg(bg.to_color(env))
-        } else {
-            cell
+        match self.bg.as_ref().and_then(|bg| bg.to_color(env)) {
+            Some(bg) => cell.bg(bg),
+            None => cell,
+        }
+    }
+
+    /// The value of the cell for exporting. Unlike the displayed value this isn't humanized, so
+    /// times are full timestamps and quantities are in base units
+    pub fn raw_value(&self, index: usize) -> String {
+        match &self.txt {
+            CellSpecTxt::DateTime(datetime) => datetime.to_rfc3339(),
+            CellSpecTxt::Duration(duration) => duration.num_seconds().to_string(),
+            CellSpecTxt::Index => index.to_string(),
+            CellSpecTxt::Int(num) => num.to_string(),
+            CellSpecTxt::None => String::new(),
+            CellSpecTxt::Quantity(quant) => raw_quantity(quant).to_string(),
+            CellSpecTxt::Str(s) => s.to_string(),
         }
     }
 
@@ -477,9 +680,198 @@ pub fn print_table<T: Into<comfy_table::Row>>(
     table
 }
 
+/// Build a table of all the specs, without printing it
+pub fn build_table(titles: &[&str], specs: &[Vec<CellSpec<'_>>], env: &Env) -> comfy_table::Table {
+    let mut table = comfy_table::Table::new();
+    table.load_preset(UTF8_TABLE_STYLE);
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(titles.to_vec());
+    for (index, t_spec) in specs.iter().enumerate() {
+        let row_vec: Vec<Cell> = t_spec.iter().map(|spec| spec.to_cell(index, env)).collect();
+        table.add_row(row_vec);
+    }
+    table
+}
+
+/// Print a table fit to the width of the terminal. If the table is too wide, cells are ellipsized
+/// according to `fits`, and then columns are dropped, lowest priority first, until it fits. Nothing
+/// is dropped or truncated if the output isn't a terminal, or `options.wide` is set.
+///
+/// The returned table always has every column, untruncated
+pub fn print_fitted_table(
+    titles: &[&str],
+    specs: Vec<Vec<CellSpec<'_>>>,
+    fits: &[ColumnFit],
+    options: TableOptions,
+    env: &Env,
+    writer: &mut ClickWriter,
+) -> comfy_table::Table {
+    let full = build_table(titles, &specs, env);
+
+    let mut shown: Vec<usize> = (0..titles.len())
+        .filter(|i| !(options.no_index && titles[*i] == "####"))
+        .collect();
+    let terminal_width = fit_width(&full, &options, writer);
+    if let Some(terminal_width) = terminal_width {
+        let shown_fits: Vec<ColumnFit> = shown
+            .iter()
+            .map(|i| fits.get(*i).copied().unwrap_or(ColumnFit::KEEP))
+            .collect();
+        let widths: Vec<usize> = shown
+            .iter()
+            .zip(shown_fits.iter())
+            .map(|(i, fit)| {
+                let texts = full
+                    .column_cells_iter(*i)
+                    .flatten()
+                    .map(|cell| cell.content());
+                column_width(titles[*i], texts, fit)
+            })
+            .collect();
+        let keep = fit_columns(&widths, &shown_fits, terminal_width);
+        shown = shown
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(i, keep)| keep.then_some(i))
+            .collect();
+    }
+
+    let mut table = comfy_table::Table::new();
+    if options.no_headers {
+        table.load_preset(comfy_table::presets::NOTHING);
+    } else {
+        table.load_preset(UTF8_TABLE_STYLE);
+        table.set_header(shown.iter().map(|i| titles[*i]).collect::<Vec<&str>>());
+    }
+    if terminal_width.is_some() {
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+    } else {
+        table.set_content_arrangement(ContentArrangement::Disabled);
+    }
+    for (index, t_spec) in specs.iter().enumerate() {
+        let row: Vec<Cell> = shown
+            .iter()
+            .filter_map(|i| {
+                let max_width = terminal_width.and(fits.get(*i).and_then(|fit| fit.max_width));
+                t_spec
+                    .get(*i)
+                    .map(|spec| spec.to_fitted_cell(index, env, max_width))
+            })
+            .collect();
+        table.add_row(row);
+    }
+    clickwriteln!(writer, "{table}");
+    full
+}
+
+/// Export the table in options.output if it's set, otherwise print it as print_fitted_table does.
+/// Returns the full table and its raw values, for Env::set_last_objs and Env::set_last_export
+pub fn print_or_export_table(
+    titles: &[&str],
+    specs: Vec<Vec<CellSpec<'_>>>,
+    fits: &[ColumnFit],
+    options: TableOptions,
+    env: &Env,
+    writer: &mut ClickWriter,
+) -> (comfy_table::Table, ExportTable) {
+    let export = ExportTable::from_specs(titles, &specs);
+    let table = match options.output {
+        Some(format) => {
+            clickwrite!(writer, "{}", export.render(format, options.no_headers));
+            build_table(titles, &specs, env)
+        }
+        None => print_fitted_table(titles, specs, fits, options, env, writer),
+    };
+    (table, export)
+}
+
+// the width to fit a table to, or None if it shouldn't be fit. comfy_table only knows if click's
+// stdout is a terminal, not if this output is being piped or redirected
+fn fit_width(
+    table: &comfy_table::Table,
+    options: &TableOptions,
+    writer: &ClickWriter,
+) -> Option<usize> {
+    if options.wide || !writer.is_terminal() {
+        None
+    } else {
+        table.width().map(usize::from)
+    }
+}
+
+/// Shorten each line of s to at most max_width chars, ending shortened lines with '…'
+pub fn ellipsize(s: &str, max_width: usize) -> Cow<'_, str> {
+    if s.lines().all(|line| line.chars().count() <= max_width) {
+        return Cow::Borrowed(s);
+    }
+    let lines: Vec<String> = s
+        .lines()
+        .map(|line| {
+            if line.chars().count() <= max_width {
+                line.to_string()
+            } else {
+                let mut short: String = line.chars().take(max_width.saturating_sub(1)).collect();
+                short.push('…');
+                short
+            }
+        })
+        .collect();
+    Cow::Owned(lines.join("\n"))
+}
+
+// widest line in the column, after applying any truncation
+fn column_width<I: Iterator<Item = String>>(title: &str, texts: I, fit: &ColumnFit) -> usize {
+    let widest = texts
+        .flat_map(|text| {
+            text.lines()
+                .map(|line| line.chars().count())
+                .collect::<Vec<usize>>()
+        })
+        .max()
+        .unwrap_or(0);
+    let widest = match fit.max_width {
+        Some(max_width) => widest.min(max_width),
+        None => widest,
+    };
+    widest.max(title.chars().count())
+}
+
+/// Decide which columns to keep so a table with columns of the given widths fits in
+/// available_width. Returns a keep flag for each column. Columns are dropped lowest priority first,
+/// and rightmost first among equal priorities.
+pub fn fit_columns(widths: &[usize], fits: &[ColumnFit], available_width: usize) -> Vec<bool> {
+    let mut keep = vec![true; widths.len()];
+    // each column has one char of padding on each side, and one char of border between columns
+    let table_width = |keep: &[bool]| {
+        let kept: Vec<usize> = widths
+            .iter()
+            .zip(keep.iter())
+            .filter_map(|(width, keep)| keep.then_some(*width))
+            .collect();
+        (kept.iter().sum::<usize>() + 3 * kept.len()).saturating_sub(1)
+    };
+    while table_width(&keep) > available_width {
+        let drop = fits
+            .iter()
+            .enumerate()
+            .filter(|(i, fit)| keep[*i] && fit.priority != u8::MAX)
+            .min_by(|(ia, a), (ib, b)| a.priority.cmp(&b.priority).then(ib.cmp(ia)));
+        match drop {
+            Some((i, _)) => keep[i] = false,
+            None => break,
+        }
+    }
+    keep
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::table::raw_quantity;
+    use crate::output::ClickWriter;
+    use crate::table::{
+        ellipsize, fit_columns, fit_width, raw_quantity, CellSpec, ColumnFit, ExportFormat,
+        ExportTable, TableOptions,
+    };
+    use chrono::{TimeZone, Utc};
     use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 
     #[test]
@@ -495,4 +887,100 @@ mod tests {
         assert_eq!(raw_quantity(&Quantity("34".to_string())), 34.0);
         assert_eq!(raw_quantity(&Quantity("-3456".to_string())), -3456.0);
     }
+
+    #[test]
+    fn test_ellipsize() {
+        assert_eq!(ellipsize("short", 10), "short");
+        assert_eq!(ellipsize("exactly10!", 10), "exactly10!");
+        assert_eq!(ellipsize("this is too long", 10), "this is t…");
+        assert_eq!(ellipsize("a=1\nlonger=label", 6), "a=1\nlonge…");
+    }
+
+    #[test]
+    fn test_fit_width() {
+        let mut table = comfy_table::Table::new();
+        table.set_width(40);
+        let options = TableOptions::default();
+        assert_eq!(fit_width(&table, &options, &ClickWriter::new()), Some(40));
+
+        let dir = tempdir::TempDir::new("click_fit_width").unwrap();
+        let mut file_writer = ClickWriter::new();
+        file_writer.set_output_file(std::fs::File::create(dir.path().join("out")).unwrap());
+        assert_eq!(fit_width(&table, &options, &file_writer), None);
+
+        let mut pipe_writer = ClickWriter::new();
+        pipe_writer.setup_pipe("cat > /dev/null").unwrap();
+        assert_eq!(fit_width(&table, &options, &pipe_writer), None);
+        pipe_writer.finish_output();
+
+        let wide = TableOptions {
+            wide: true,
+            ..Default::default()
+        };
+        assert_eq!(fit_width(&table, &wide, &ClickWriter::new()), None);
+    }
+
+    #[test]
+    fn test_fit_columns() {
+        let fit = |priority| ColumnFit {
+            priority,
+            max_width: None,
+        };
+        let widths = [4, 20, 10, 10, 3];
+        let fits = [ColumnFit::KEEP, ColumnFit::KEEP, fit(2), fit(2), fit(7)];
+        // 47 + 15 - 1
+        assert_eq!(fit_columns(&widths, &fits, 100), vec![true; 5]);
+        assert_eq!(fit_columns(&widths, &fits, 61), vec![true; 5]);
+        // drops the rightmost of the lowest priority columns first
+        assert_eq!(
+            fit_columns(&widths, &fits, 60),
+            vec![true, true, true, false, true]
+        );
+        assert_eq!(
+            fit_columns(&widths, &fits, 36),
+            vec![true, true, false, false, true]
+        );
+        // never drops columns that must be kept
+        assert_eq!(
+            fit_columns(&widths, &fits, 10),
+            vec![true, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn test_export_raw_values() {
+        let specs = vec![vec![
+            CellSpec::new_index(),
+            "web-0".into(),
+            Utc.with_ymd_and_hms(2023, 4, 5, 6, 7, 8).unwrap().into(),
+            Quantity("1500m".to_string()).into(),
+            None::<String>.into(),
+        ]];
+        let export = ExportTable::from_specs(&["####", "Name", "Age", "CPU", "Node"], &specs);
+        assert_eq!(export.titles, vec!["Name", "Age", "CPU", "Node"]);
+        assert_eq!(
+            export.rows,
+            vec![vec!["web-0", "2023-04-05T06:07:08+00:00", "1.5", ""]]
+        );
+    }
+
+    #[test]
+    fn test_export_render() {
+        let export = ExportTable {
+            titles: vec!["Name".to_string(), "Labels".to_string()],
+            rows: vec![vec!["a,b".to_string(), "x=1\ny=\"2\"|\t".to_string()]],
+        };
+        assert_eq!(
+            export.render(ExportFormat::Csv, false),
+            "Name,Labels\n\"a,b\",\"x=1\ny=\"\"2\"\"|\t\"\n"
+        );
+        assert_eq!(
+            export.render(ExportFormat::Tsv, true),
+            "a,b\tx=1\\ny=\"2\"|\\t\n"
+        );
+        assert_eq!(
+            export.render(ExportFormat::Markdown, true),
+            "| Name | Labels |\n| --- | --- |\n| a,b | x=1<br>y=\"2\"\\|\t |\n"
+        );
+    }
 }